@@ -2,47 +2,245 @@
 extern crate criterion;
 extern crate helios;
 
-use criterion::Criterion;
+use cgmath::*;
+use criterion::{Criterion, Throughput};
 use helios::scenes;
+use helios::tracer::bounding_volumes::{Accelerator, BVHNode, Grid, KdTree, WideBVH};
+use helios::tracer::geometry::{MeshData, TriangleMesh};
+use helios::tracer::material::{Lambertian, NoiseTexture, Sky};
 use helios::tracer::*;
+use std::sync::Arc;
 use std::time::Duration;
 
+// Render throughputs count camera rays, so that criterion reports them in rays per second
+const WIDTH: u64 = 400;
+const HEIGHT: u64 = 300;
+
 fn weekend_spheres_benchmark_impl() {
-    let width = 400;
-    let height = 300;
     let samples = 1;
-    let scene = scenes::weekend_spheres::get_scene(width, height, samples);
-    let mut render_context = RenderContext::new(width, height);
+    let scene = scenes::weekend_spheres::get_scene(WIDTH, HEIGHT, samples);
+    let mut render_context = RenderContext::new(WIDTH, HEIGHT);
 
     render_context.render(&scene, 10, None);
 }
 
 fn two_spheres_perlin_benchmark_impl() {
-    let width = 400;
-    let height = 300;
     let samples = 5;
-    let scene = scenes::two_spheres_perlin::get_scene(width, height, samples);
-    let mut render_context = RenderContext::new(width, height);
+    let scene = scenes::two_spheres_perlin::get_scene(WIDTH, HEIGHT, samples);
+    let mut render_context = RenderContext::new(WIDTH, HEIGHT);
 
     render_context.render(&scene, 10, None);
 }
 
+/// A noisy sphere of 131,072 triangles: an octahedron subdivided 7 times, pushed out to the unit
+/// sphere and displaced.
+fn large_mesh() -> MeshData {
+    let material = Arc::new(Lambertian::from_constant(Color::new(0.8, 0.6, 0.4)));
+    let octahedron = MeshData {
+        positions: vec![
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(0.0, -1.0, 0.0),
+            Point3::new(0.0, 0.0, 1.0),
+            Point3::new(0.0, 0.0, -1.0),
+        ],
+        normals: Vec::new(),
+        uvs: Vec::new(),
+        colors: Vec::new(),
+        indices: vec![
+            [0, 2, 4],
+            [2, 1, 4],
+            [1, 3, 4],
+            [3, 0, 4],
+            [2, 0, 5],
+            [1, 2, 5],
+            [3, 1, 5],
+            [0, 3, 5],
+        ],
+        material,
+    };
+
+    let mut sphere = octahedron.subdivided(7);
+    for position in &mut sphere.positions {
+        *position = Point3::from_vec(position.to_vec().normalize());
+    }
+    sphere.normals = sphere.smooth_normals();
+
+    let height = NoiseTexture::new(4.0, 4, 1.0, 0.5, 2.0, Color::black(), Color::white());
+    sphere.displaced(&height, 0.2)
+}
+
+fn large_mesh_scene(mesh: MeshData, samples: u64) -> Scene {
+    let camera = SimpleCamera::new(
+        Point3::new(0.0, 1.0, 4.0),
+        vec3(0.0, 0.0, 0.0),
+        vec3(0.0, 1.0, 0.0),
+        40.0,
+        WIDTH as f64 / HEIGHT as f64,
+        0.0,
+        4.0,
+    );
+    let options = RenderOpts {
+        max_depth: 50,
+        samples: samples as u32,
+    };
+
+    Scene::new(
+        options,
+        Arc::new(camera),
+        Arc::new(BVHNode::build(TriangleMesh::new(mesh).triangles())),
+        Arc::new(Sky::default()),
+    )
+}
+
+/// `scene` with its objects in a `WideBVH` rather than a `BVHNode`.
+fn with_wide_bvh(scene: &Scene) -> Scene {
+    Scene::new(
+        scene.options,
+        scene.camera.clone(),
+        Arc::new(WideBVH::build(scene.leaves())),
+        scene.background.clone(),
+    )
+}
+
+/// `scene` with its objects in a new `accelerator`.
+fn with_accelerator(scene: &Scene, accelerator: Accelerator) -> Scene {
+    Scene::new(
+        scene.options,
+        scene.camera.clone(),
+        accelerator.build(scene.leaves()),
+        scene.background.clone(),
+    )
+}
+
+/// Renders of `scene` with its objects in a kd-tree and in a grid, to compare with a BVH.
+fn accelerators_benchmark(c: &mut Criterion, name: &str, scene: &Scene) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(WIDTH * HEIGHT));
+    for &(accelerator, id) in &[
+        (Accelerator::KdTree, "render_kdtree"),
+        (Accelerator::Grid, "render_grid"),
+    ] {
+        let scene = with_accelerator(scene, accelerator);
+        group.bench_function(id, |b| {
+            b.iter(|| RenderContext::new(WIDTH, HEIGHT).render(&scene, 10, None))
+        });
+    }
+    group.finish();
+}
+
+/// Camera rays through the centers of the image pixels, by tiles of 8x8 pixels.
+fn primary_rays(scene: &Scene) -> Vec<Ray> {
+    let mut rays = Vec::with_capacity((WIDTH * HEIGHT) as usize);
+    for tile_y in (0..HEIGHT).step_by(8) {
+        for tile_x in (0..WIDTH).step_by(8) {
+            for y in tile_y..(tile_y + 8).min(HEIGHT) {
+                for x in tile_x..(tile_x + 8).min(WIDTH) {
+                    let u = (x as f64 + 0.5) / WIDTH as f64;
+                    let v = (HEIGHT as f64 - y as f64 - 0.5) / HEIGHT as f64;
+                    rays.push(scene.camera.get_ray(u, v));
+                }
+            }
+        }
+    }
+    rays
+}
+
+/// Closest hits of the camera rays with a `BVHNode`, and with a `WideBVH` one ray at a time or
+/// by packets of one tile.
+fn primary_rays_benchmark(c: &mut Criterion, name: &str, scene: &Scene) {
+    let rays = primary_rays(scene);
+    let binary = BVHNode::build(scene.leaves());
+    let wide = WideBVH::build(scene.leaves());
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(rays.len() as u64));
+    group.bench_function("primary_rays_bvh", |b| {
+        b.iter(|| {
+            rays.iter()
+                .filter(|ray| binary.intersect(ray, 0.001, f64::MAX).is_some())
+                .count()
+        })
+    });
+    group.bench_function("primary_rays_wide_bvh", |b| {
+        b.iter(|| {
+            rays.iter()
+                .filter(|ray| wide.intersect(ray, 0.001, f64::MAX).is_some())
+                .count()
+        })
+    });
+    group.bench_function("primary_rays_wide_bvh_packets", |b| {
+        b.iter(|| {
+            rays.chunks(64)
+                .flat_map(|packet| wide.intersect_packet(packet, 0.001, f64::MAX))
+                .filter(Option::is_some)
+                .count()
+        })
+    });
+    group.finish();
+}
+
 fn weekend_spheres_benchmark(c: &mut Criterion) {
-    c.bench_function("weekend_spheres_benchmark_impl", |b| {
-        b.iter(|| weekend_spheres_benchmark_impl())
+    let mut group = c.benchmark_group("weekend_spheres");
+    group.throughput(Throughput::Elements(WIDTH * HEIGHT));
+    group.bench_function("render", |b| b.iter(weekend_spheres_benchmark_impl));
+    group.finish();
+
+    let scene = scenes::weekend_spheres::get_scene(WIDTH, HEIGHT, 1);
+    let wide_scene = with_wide_bvh(&scene);
+    let mut group = c.benchmark_group("weekend_spheres");
+    group.throughput(Throughput::Elements(WIDTH * HEIGHT));
+    group.bench_function("render_wide_bvh", |b| {
+        b.iter(|| RenderContext::new(WIDTH, HEIGHT).render(&wide_scene, 10, None))
     });
+    group.finish();
+
+    primary_rays_benchmark(c, "weekend_spheres", &scene);
+    accelerators_benchmark(c, "weekend_spheres", &scene);
 }
 
 fn two_spheres_perlin_benchmark(c: &mut Criterion) {
     c.bench_function("two_spheres_perlin_benchmark", |b| {
-        b.iter(|| two_spheres_perlin_benchmark_impl())
+        b.iter(two_spheres_perlin_benchmark_impl)
+    });
+}
+
+fn large_mesh_benchmark(c: &mut Criterion) {
+    let samples = 1;
+    let scene = large_mesh_scene(large_mesh(), samples);
+
+    let mut group = c.benchmark_group("large_mesh");
+    group.throughput(Throughput::Elements(WIDTH * HEIGHT * samples));
+    group.bench_function("render", |b| {
+        b.iter(|| RenderContext::new(WIDTH, HEIGHT).render(&scene, 10, None))
+    });
+    let wide_scene = with_wide_bvh(&scene);
+    group.bench_function("render_wide_bvh", |b| {
+        b.iter(|| RenderContext::new(WIDTH, HEIGHT).render(&wide_scene, 10, None))
+    });
+    group.finish();
+
+    primary_rays_benchmark(c, "large_mesh", &scene);
+    accelerators_benchmark(c, "large_mesh", &scene);
+
+    let triangles = TriangleMesh::new(large_mesh()).triangles();
+    let mut group = c.benchmark_group("large_mesh");
+    group.throughput(Throughput::Elements(triangles.len() as u64));
+    group.bench_function("bvh_build", |b| {
+        b.iter(|| BVHNode::build(triangles.clone()))
+    });
+    group.bench_function("kdtree_build", |b| {
+        b.iter(|| KdTree::build(triangles.clone()))
     });
+    group.bench_function("grid_build", |b| b.iter(|| Grid::build(triangles.clone())));
+    group.finish();
 }
 
 criterion_group!(
     name = benches;
     config = Criterion::default().sample_size(10).measurement_time(Duration::new(15, 0));
-    targets= weekend_spheres_benchmark, two_spheres_perlin_benchmark
+    targets= weekend_spheres_benchmark, two_spheres_perlin_benchmark, large_mesh_benchmark
 );
 
 criterion_main!(benches);
@@ -0,0 +1,24 @@
+use crate::render_cmd::SceneNames;
+use helios::tracer::scene_file::SceneDescription;
+use helios::tracer::seed_rng;
+use std::path::Path;
+
+pub fn export(
+    scene_name: SceneNames,
+    output: &Path,
+    width: u64,
+    height: u64,
+    samples: u64,
+    seed: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Seeding as `render --seed` does makes randomly generated scenes match the rendered ones
+    if let Some(seed) = seed {
+        seed_rng(seed);
+    }
+
+    let scene = scene_name.get_scene(width, height, samples);
+    SceneDescription::from_scene(&scene, width, height)?.save(output)?;
+
+    println!("Exported {:?} to {:?}", scene_name, output);
+    Ok(())
+}
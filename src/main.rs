@@ -1,12 +1,13 @@
 use env_logger::Builder as LoggerBuilder;
 use log::Level;
 use std::path::PathBuf;
+use std::process;
+use structopt::clap::{Error, ErrorKind};
 use structopt::StructOpt;
 
+mod export_cmd;
 mod noise_cmd;
 mod render_cmd;
-mod scenes;
-mod tracer;
 
 #[derive(StructOpt, Debug)]
 #[structopt(
@@ -17,11 +18,36 @@ mod tracer;
 enum Cli {
     #[structopt(name = "render")]
     Render {
+        /// Built-in scene to render, omitted when rendering a --scene-file or a model
         #[structopt(name = "scene_name")]
-        scene_name: render_cmd::SceneNames,
-
-        #[structopt(name = "out")]
-        output: PathBuf,
+        scene_name: Option<String>,
+
+        #[structopt(name = "out", parse(from_os_str))]
+        output: Option<PathBuf>,
+
+        /// JSON scene description to render instead of a built-in scene
+        #[structopt(long = "scene-file", parse(from_os_str))]
+        scene_file: Option<PathBuf>,
+
+        /// Wavefront OBJ model to render, framed on a ground under a sky
+        #[structopt(long = "obj", parse(from_os_str), conflicts_with = "scene-file")]
+        obj: Option<PathBuf>,
+
+        /// PLY model to render, framed on a ground under a sky
+        #[structopt(
+            long = "ply",
+            parse(from_os_str),
+            conflicts_with_all = &["scene-file", "obj"]
+        )]
+        ply: Option<PathBuf>,
+
+        /// glTF or GLB scene to render, through its first camera when it has one
+        #[structopt(
+            long = "gltf",
+            parse(from_os_str),
+            conflicts_with_all = &["scene-file", "obj", "ply"]
+        )]
+        gltf: Option<PathBuf>,
 
         #[structopt(long = "width", default_value = "300")]
         width: u64,
@@ -29,12 +55,26 @@ enum Cli {
         #[structopt(long = "height", default_value = "200")]
         height: u64,
 
-        #[structopt(long = "samples", default_value = "100")]
-        samples: u64,
+        /// Samples per pixel [default: 100, or the scene file's `options.samples`]
+        #[structopt(long = "samples")]
+        samples: Option<u64>,
 
         #[structopt(long = "threads", short = "t")]
         threads: Option<usize>,
 
+        /// Seed making the render reproducible
+        #[structopt(long = "seed")]
+        seed: Option<u64>,
+
+        /// Renders a heatmap of the `nodes` visited or `primitives` tested by each primary ray
+        #[structopt(long = "heatmap")]
+        heatmap: Option<helios::tracer::HeatmapCounter>,
+
+        /// Structure holding the scene objects: `bvh`, `kdtree`, `grid`, or `list` to test every
+        /// object [default: the scene's own, a BVH for most]
+        #[structopt(long = "accel")]
+        accel: Option<helios::tracer::bounding_volumes::Accelerator>,
+
         #[structopt(flatten)]
         verbose: clap_verbosity_flag::Verbosity,
 
@@ -61,19 +101,53 @@ enum Cli {
         #[structopt(long = "octaves", short = "o", default_value = "8")]
         octaves: u32,
     },
+    /// Writes a built-in scene as a JSON scene file
+    #[structopt(name = "export-scene")]
+    ExportScene {
+        #[structopt(name = "scene_name")]
+        scene_name: render_cmd::SceneNames,
+
+        #[structopt(name = "out", parse(from_os_str))]
+        output: PathBuf,
+
+        #[structopt(long = "width", default_value = "300")]
+        width: u64,
+
+        #[structopt(long = "height", default_value = "200")]
+        height: u64,
+
+        #[structopt(long = "samples", default_value = "100")]
+        samples: u64,
+
+        /// Seed for randomly generated scenes, matching `render --seed`
+        #[structopt(long = "seed")]
+        seed: Option<u64>,
+    },
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Cli::from_args();
+fn main() {
+    if let Err(error) = run(Cli::from_args()) {
+        eprintln!("Error: {}", error);
+        process::exit(1);
+    }
+}
 
+fn run(args: Cli) -> Result<(), Box<dyn std::error::Error>> {
     match args {
         Cli::Render {
             scene_name,
             output,
+            scene_file,
+            obj,
+            ply,
+            gltf,
             width,
             height,
             samples,
             threads,
+            seed,
+            heatmap,
+            accel,
             verbose,
             open,
         } => {
@@ -83,20 +157,42 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             };
             LoggerBuilder::new().filter(None, level_filter).try_init()?;
 
-            let result = render_cmd::render(
-                scene_name,
+            let source = scene_file
+                .map(render_cmd::SceneSource::File)
+                .or_else(|| obj.map(render_cmd::SceneSource::Obj))
+                .or_else(|| ply.map(render_cmd::SceneSource::Ply))
+                .or_else(|| gltf.map(render_cmd::SceneSource::Gltf));
+
+            // With a scene file or a model the only positional argument is the output path
+            let (scene, output) = match (source, scene_name, output) {
+                (Some(source), Some(output), None) => (source, PathBuf::from(output)),
+                (None, Some(scene_name), Some(output)) => {
+                    (render_cmd::SceneSource::BuiltIn(scene_name.parse()?), output)
+                }
+                _ => Error::with_description(
+                    "expected either `render <scene_name> <out>`, `render --scene-file <file> <out>` or `render --obj|--ply|--gltf <file> <out>`",
+                    ErrorKind::WrongNumberOfValues,
+                )
+                .exit(),
+            };
+
+            render_cmd::render(
+                scene,
                 output.as_path(),
                 width,
                 height,
                 samples,
                 threads,
-            );
+                seed,
+                heatmap,
+                accel,
+            )?;
 
             if open {
                 opener::open(output)?;
             }
 
-            result
+            Ok(())
         }
         Cli::Noise {
             output,
@@ -106,5 +202,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             persistency,
             octaves,
         } => noise_cmd::render(output, scale, frequency, lacunarity, persistency, octaves),
+        Cli::ExportScene {
+            scene_name,
+            output,
+            width,
+            height,
+            samples,
+            seed,
+        } => export_cmd::export(scene_name, output.as_path(), width, height, samples, seed),
     }
 }
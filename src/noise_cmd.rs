@@ -1,5 +1,5 @@
-use crate::tracer::material::noises::PerlinNoise;
-use crate::tracer::Color;
+use helios::tracer::material::noises::PerlinNoise;
+use helios::tracer::Color;
 use image::ImageBuffer;
 use std::path::PathBuf;
 
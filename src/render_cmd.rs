@@ -1,28 +1,62 @@
-use crate::scenes;
-use crate::tracer::*;
 use console::{style, Emoji};
+use helios::scenes;
+use helios::tracer::*;
 use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
 use rayon::current_num_threads;
 use serde::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum SceneNames {
     WeekendSpheres,
+    MovingSpheres,
     TwoSpheresPerlin,
     TwoSpheresLight,
+    CornellBox,
+    NextWeekFinal,
+    SdfShapes,
 }
 
 impl FromStr for SceneNames {
     type Err = serde_json::error::Error;
     fn from_str(s: &str) -> Result<SceneNames, serde_json::error::Error> {
-        let scene_name = serde_json::from_str(&format!("\"{}\"", s)).unwrap();
-        Ok(scene_name)
+        serde_json::from_str(&format!("\"{}\"", s))
     }
 }
 
+impl SceneNames {
+    pub fn get_scene(&self, width: u64, height: u64, samples: u64) -> Scene {
+        match self {
+            SceneNames::WeekendSpheres => {
+                scenes::weekend_spheres::get_scene(width, height, samples)
+            }
+            SceneNames::MovingSpheres => scenes::moving_spheres::get_scene(width, height, samples),
+            SceneNames::TwoSpheresPerlin => {
+                scenes::two_spheres_perlin::get_scene(width, height, samples)
+            }
+            SceneNames::TwoSpheresLight => {
+                scenes::two_spheres_light::get_scene(width, height, samples)
+            }
+            SceneNames::CornellBox => scenes::cornell_box::get_scene(width, height, samples),
+            SceneNames::NextWeekFinal => scenes::next_week_final::get_scene(width, height, samples),
+            SceneNames::SdfShapes => scenes::sdf_shapes::get_scene(width, height, samples),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneSource {
+    BuiltIn(SceneNames),
+    File(PathBuf),
+    Obj(PathBuf),
+    Ply(PathBuf),
+    Gltf(PathBuf),
+}
+
 fn init_thread_pool(threads: Option<usize>) -> usize {
     match threads {
         Some(threads_count) => {
@@ -36,18 +70,24 @@ fn init_thread_pool(threads: Option<usize>) -> usize {
     }
 }
 
+pub const DEFAULT_SAMPLES: u64 = 100;
+
 static SCENE: Emoji<'_, '_> = Emoji("🎬 ", "");
 static THREAD: Emoji<'_, '_> = Emoji("🧵  ", "");
 static SPARKLE: Emoji<'_, '_> = Emoji("✨ ", ":-)");
 static RENDER: Emoji<'_, '_> = Emoji("🖼️  ", "");
 
+#[allow(clippy::too_many_arguments)]
 pub fn render(
-    scene_name: SceneNames,
+    scene: SceneSource,
     output: &Path,
     width: u64,
     height: u64,
-    samples: u64,
+    samples: Option<u64>,
     threads: Option<usize>,
+    seed: Option<u64>,
+    heatmap: Option<HeatmapCounter>,
+    accelerator: Option<bounding_volumes::Accelerator>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let thread_count = init_thread_pool(threads);
     println!(
@@ -59,12 +99,72 @@ pub fn render(
 
     println!("{} {}Loading scene...", style("[2/4]").bold().dim(), SCENE);
 
-    let scene = match scene_name {
-        SceneNames::WeekendSpheres => scenes::weekend_spheres::get_scene(width, height, samples),
-        SceneNames::TwoSpheresPerlin => {
-            scenes::two_spheres_perlin::get_scene(width, height, samples)
+    if let Some(seed) = seed {
+        seed_rng(seed);
+    }
+
+    let scene = match scene {
+        SceneSource::BuiltIn(scene_name) => {
+            scene_name.get_scene(width, height, samples.unwrap_or(DEFAULT_SAMPLES))
+        }
+        SceneSource::File(path) => {
+            let mut scene = scene_file::load_scene(&path, width, height)?;
+            if let Some(samples) = samples {
+                scene.options.samples = samples as u32;
+            }
+            scene
+        }
+        SceneSource::Obj(path) => {
+            let objects = import::obj_scene_objects(import::load_obj(&path)?);
+            if objects.is_empty() {
+                return Err(format!("{} has no faces to render", path.display()).into());
+            }
+            scenes::model_preview::get_scene(
+                objects,
+                width,
+                height,
+                samples.unwrap_or(DEFAULT_SAMPLES),
+            )
+        }
+        SceneSource::Ply(path) => {
+            let mesh: Arc<dyn SceneObject> = Arc::new(import::load_ply(&path, None)?);
+            scenes::model_preview::get_scene(
+                vec![mesh],
+                width,
+                height,
+                samples.unwrap_or(DEFAULT_SAMPLES),
+            )
+        }
+        SceneSource::Gltf(path) => {
+            let gltf = import::load_gltf(&path)?;
+            if gltf.objects.is_empty() {
+                return Err(format!("{} has no meshes to render", path.display()).into());
+            }
+            let samples = samples.unwrap_or(DEFAULT_SAMPLES);
+            match gltf.camera() {
+                Some(camera) => {
+                    let options = RenderOpts {
+                        max_depth: RenderOpts::default().max_depth,
+                        samples: samples as u32,
+                    };
+                    Scene::new(
+                        options,
+                        camera.build(width, height)?,
+                        Arc::new(bounding_volumes::BVHNode::build(gltf.objects)),
+                        Arc::new(material::Sky::default()),
+                    )
+                }
+                None => scenes::model_preview::get_scene(gltf.objects, width, height, samples),
+            }
         }
-        SceneNames::TwoSpheresLight => scenes::two_spheres_light::get_scene(width, height, samples),
+    };
+
+    let scene = match accelerator {
+        Some(accelerator) => {
+            info!("Using a {:?} accelerator", accelerator);
+            scene.with_accelerator(accelerator)
+        }
+        None => scene,
     };
 
     println!(
@@ -74,6 +174,7 @@ pub fn render(
     );
 
     let mut render_context = RenderContext::new(width, height);
+    render_context.seed = seed;
 
     println!(
         "{} {}Rendering image to {:?}",
@@ -82,6 +183,29 @@ pub fn render(
         output
     );
 
+    if let Some(counter) = heatmap {
+        let stats = render_context.render_heatmap(&scene, counter);
+        let rays = stats.rays.max(1) as f64;
+
+        render_context.print_stats(&scene);
+        println!(
+            "| Nodes visited: {} | Per ray: {:.2} | Max: {}",
+            stats.total.nodes,
+            stats.total.nodes as f64 / rays,
+            stats.max.nodes
+        );
+        println!(
+            "| Primitive tests: {} | Per ray: {:.2} | Max: {}",
+            stats.total.primitives,
+            stats.total.primitives as f64 / rays,
+            stats.max.primitives
+        );
+        println!("==========================================");
+
+        render_context.save(output);
+        return Ok(());
+    }
+
     let progress_bar = ProgressBar::new(width * height);
     progress_bar.set_draw_delta(100);
     progress_bar.set_style(
@@ -92,7 +216,7 @@ pub fn render(
 
     render_context.render(&scene, thread_count, Some(&progress_bar));
 
-    render_context.print_stats();
+    render_context.print_stats(&scene);
 
     render_context.save(output);
 
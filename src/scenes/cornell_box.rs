@@ -0,0 +1,86 @@
+use crate::tracer::bounding_volumes::SceneBVH;
+use crate::tracer::geometry::{Cuboid, Instance, Quad};
+use crate::tracer::material::{DiffuseLight, Lambertian, SolidTexture};
+use crate::tracer::{Camera, Color, RenderOpts, Scene, SceneObjectList, SimpleCamera};
+use cgmath::*;
+use std::sync::Arc;
+
+fn get_camera(width: u64, height: u64) -> Arc<dyn Camera> {
+    let width = width as f64;
+    let height = height as f64;
+
+    let look_from = Point3::new(278.0, 278.0, -800.0);
+    let look_at = vec3(278.0, 278.0, 0.0);
+    let up = vec3(0.0, 1.0, 0.0);
+    let focus_dist = 10.0;
+    let aperture = 0.0;
+
+    let camera = SimpleCamera::new(
+        look_from,
+        look_at,
+        up,
+        40.0,
+        width / height,
+        aperture,
+        focus_dist,
+    );
+
+    Arc::new(camera)
+}
+
+/// The Cornell box, lit by a single area light on its ceiling.
+pub fn get_scene(width: u64, height: u64, samples: u64) -> Scene {
+    let camera = get_camera(width, height);
+    let render_options = RenderOpts {
+        max_depth: 50,
+        samples: samples as u32,
+    };
+
+    let red = Arc::new(Lambertian::from_constant(Color::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::from_constant(Color::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::from_constant(Color::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new(Arc::new(SolidTexture::new(Color::new(
+        15.0, 15.0, 15.0,
+    )))));
+
+    let mut objects = SceneObjectList::new();
+    let size = (0.0, 555.0);
+    objects.push(Arc::new(Quad::yz_rect(size, size, 555.0, green)));
+    objects.push(Arc::new(Quad::yz_rect(size, size, 0.0, red)));
+    objects.push(Arc::new(Quad::xz_rect(
+        (213.0, 343.0),
+        (227.0, 332.0),
+        554.0,
+        light,
+    )));
+    objects.push(Arc::new(Quad::xz_rect(size, size, 0.0, white.clone())));
+    objects.push(Arc::new(Quad::xz_rect(size, size, 555.0, white.clone())));
+    objects.push(Arc::new(Quad::xy_rect(size, size, 555.0, white.clone())));
+
+    let tall_box = Arc::new(Cuboid::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    ));
+    objects.push(Arc::new(Instance::new(
+        tall_box,
+        Matrix4::from_translation(vec3(265.0, 0.0, 295.0)) * Matrix4::from_angle_y(Deg(15.0)),
+    )));
+
+    let short_box = Arc::new(Cuboid::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 165.0, 165.0),
+        white,
+    ));
+    objects.push(Arc::new(Instance::new(
+        short_box,
+        Matrix4::from_translation(vec3(130.0, 0.0, 65.0)) * Matrix4::from_angle_y(Deg(-18.0)),
+    )));
+
+    Scene::new(
+        render_options,
+        camera,
+        Arc::new(SceneBVH::build(objects.objects)),
+        Arc::new(Lambertian::from_constant(Color::black())),
+    )
+}
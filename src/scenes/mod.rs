@@ -1,3 +1,8 @@
+pub mod cornell_box;
+pub mod model_preview;
+pub mod moving_spheres;
+pub mod next_week_final;
+pub mod sdf_shapes;
 pub mod two_spheres_light;
 pub mod two_spheres_perlin;
 pub mod weekend_spheres;
@@ -0,0 +1,67 @@
+use crate::tracer::bounding_volumes::{SceneBVH, AABB};
+use crate::tracer::geometry::Plane;
+use crate::tracer::material::{Lambertian, Sky};
+use crate::tracer::{Camera, Color, RenderOpts, Scene, SceneObject, SimpleCamera};
+use cgmath::*;
+use std::sync::Arc;
+
+/// Camera looking at `bounds` from the front and slightly above, fitting them in the frame.
+fn get_camera(bounds: &AABB, width: u64, height: u64) -> Arc<dyn Camera> {
+    let center = (bounds.min + bounds.max) / 2.0;
+    let radius = (bounds.max - bounds.min).magnitude() / 2.0;
+    let vfov: f64 = 30.0;
+
+    let distance = radius / (vfov.to_radians() / 2.0).sin();
+    let look_from = Point3::from_vec(center + vec3(0.3, 0.4, 1.0).normalize() * distance);
+
+    let camera = SimpleCamera::new(
+        look_from,
+        center,
+        vec3(0.0, 1.0, 0.0),
+        vfov,
+        width as f64 / height as f64,
+        0.0,
+        distance,
+    );
+
+    Arc::new(camera)
+}
+
+/// A scene framing imported `objects`, standing on a ground under a sky.
+pub fn get_scene(
+    objects: Vec<Arc<dyn SceneObject>>,
+    width: u64,
+    height: u64,
+    samples: u64,
+) -> Scene {
+    let bounds = objects
+        .iter()
+        .map(|object| object.get_bounds())
+        .fold(None, |acc: Option<AABB>, bounds| match acc {
+            Some(acc) => Some(acc.union(&bounds)),
+            None => Some(bounds),
+        })
+        .expect("preview scenes need at least one object");
+
+    let camera = get_camera(&bounds, width, height);
+    let render_options = RenderOpts {
+        max_depth: 50,
+        samples: samples as u32,
+    };
+
+    let ground = Plane::new(
+        Point3::new(0.0, bounds.min.y, 0.0),
+        vec3(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::from_constant(Color::new(0.5, 0.5, 0.5))),
+    );
+
+    let mut objects = objects;
+    objects.push(Arc::new(ground));
+
+    Scene::new(
+        render_options,
+        camera,
+        Arc::new(SceneBVH::build(objects)),
+        Arc::new(Sky::default()),
+    )
+}
@@ -0,0 +1,128 @@
+use crate::tracer::bounding_volumes::BVHNode;
+use crate::tracer::geometry::{MovingSphere, Sphere};
+use crate::tracer::material::{CheckersTexture, Dielectric, Lambertian, Material, Metal, Sky};
+use crate::tracer::{random, Camera, Color, RenderOpts, Scene, SceneObjectList, SimpleCamera};
+use cgmath::*;
+use std::sync::Arc;
+
+fn rand() -> f64 {
+    random()
+}
+
+fn get_camera(width: u64, height: u64) -> Arc<dyn Camera> {
+    let width = width as f64;
+    let height = height as f64;
+
+    let look_from = Point3::new(13.0, 2.0, 3.0);
+    let look_at = vec3(0.0, 0.0, 0.0);
+    let up = vec3(0.0, 1.0, 0.0);
+    let focus_dist = 10.0;
+    let aperture = 0.1;
+
+    let camera = SimpleCamera::new(
+        look_from,
+        look_at,
+        up,
+        20.0,
+        width / height,
+        aperture,
+        focus_dist,
+    )
+    .with_shutter(0.0, 1.0);
+
+    Arc::new(camera)
+}
+
+/// The weekend spheres scene with bouncing diffuse spheres, blurred by the camera motion blur.
+pub fn get_scene(width: u64, height: u64, samples: u64) -> Scene {
+    let camera = get_camera(width, height);
+    let render_options = RenderOpts {
+        max_depth: 50,
+        samples: samples as u32,
+    };
+
+    let mut objects = SceneObjectList::new();
+
+    let checkers_texture = Arc::new(CheckersTexture::from_colors(
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+        0.025,
+    ));
+
+    let bg_sphere = Sphere {
+        center: Point3::new(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material: Arc::new(Lambertian::new(checkers_texture)),
+    };
+    objects.push(Arc::new(bg_sphere));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let a = a as f64;
+            let b = b as f64;
+
+            let center = Point3::new(a + 0.9 * rand(), 0.2, b + 0.9 * rand());
+            let v = center.to_vec() - vec3(4.0, 0.2, 0.0);
+
+            let radius = 0.2;
+            if v.magnitude() > 0.9 {
+                let random_mat: f64 = rand();
+                if random_mat < 0.8 {
+                    // Diffuse spheres bounce up during the exposure
+                    let albedo = Color::new(rand() * rand(), rand() * rand(), rand() * rand());
+                    objects.push(Arc::new(MovingSphere {
+                        center0: center,
+                        center1: center + vec3(0.0, 0.5 * rand(), 0.0),
+                        time0: 0.0,
+                        time1: 1.0,
+                        radius,
+                        material: Arc::new(Lambertian::from_constant(albedo)),
+                    }));
+                    continue;
+                }
+
+                let material: Arc<dyn Material + Send> = if random_mat < 0.95 {
+                    // Metal
+                    let albedo = vec3(
+                        0.5 * (1.0 + rand()),
+                        0.5 * (1.0 + rand()),
+                        0.5 * (1.0 + rand()),
+                    );
+                    Arc::new(Metal::new(albedo, 0.5 * rand()))
+                } else {
+                    // Glass
+                    Arc::new(Dielectric::new_glass())
+                };
+                objects.push(Arc::new(Sphere {
+                    center,
+                    radius,
+                    material,
+                }));
+            }
+        }
+    }
+
+    objects.push(Arc::new(Sphere {
+        center: Point3::new(4.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Metal::new(vec3(0.7, 0.6, 0.5), 0.0)),
+    }));
+    objects.push(Arc::new(Sphere {
+        center: Point3::new(0.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Dielectric::new_glass()),
+    }));
+    objects.push(Arc::new(Sphere {
+        center: Point3::new(-4.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Lambertian::from_constant(Color::new(0.4, 0.2, 0.1))),
+    }));
+
+    let bvh = BVHNode::build(objects.objects);
+    Scene::new(
+        render_options,
+        camera,
+        Arc::new(bvh),
+        Arc::new(Sky::default()),
+    )
+}
@@ -0,0 +1,157 @@
+use crate::tracer::bounding_volumes::SceneBVH;
+use crate::tracer::geometry::{Cuboid, MovingSphere, Quad, Sphere};
+use crate::tracer::material::{
+    Dielectric, DiffuseLight, Isotropic, Lambertian, Metal, NoiseTexture, SolidTexture,
+};
+use crate::tracer::volume::ConstantMedium;
+use crate::tracer::{random, Camera, Color, RenderOpts, Scene, SceneObjectList, SimpleCamera};
+use cgmath::*;
+use std::sync::Arc;
+
+fn rand() -> f64 {
+    random()
+}
+
+fn get_camera(width: u64, height: u64) -> Arc<dyn Camera> {
+    let width = width as f64;
+    let height = height as f64;
+
+    let look_from = Point3::new(478.0, 278.0, -600.0);
+    let look_at = vec3(278.0, 278.0, 0.0);
+    let up = vec3(0.0, 1.0, 0.0);
+    let focus_dist = 10.0;
+    let aperture = 0.0;
+
+    let camera = SimpleCamera::new(
+        look_from,
+        look_at,
+        up,
+        40.0,
+        width / height,
+        aperture,
+        focus_dist,
+    )
+    .with_shutter(0.0, 1.0);
+
+    Arc::new(camera)
+}
+
+/// The final scene of "Ray Tracing: The Next Week", with a smoke-filled glass ball in a
+/// thin fog. The earth textured sphere is left out.
+pub fn get_scene(width: u64, height: u64, samples: u64) -> Scene {
+    let camera = get_camera(width, height);
+    let render_options = RenderOpts {
+        max_depth: 50,
+        samples: samples as u32,
+    };
+
+    let mut objects = SceneObjectList::new();
+
+    // Ground made of boxes of random heights
+    let ground = Arc::new(Lambertian::from_constant(Color::new(0.48, 0.83, 0.53)));
+    let boxes_per_side = 20;
+    for i in 0..boxes_per_side {
+        for j in 0..boxes_per_side {
+            let w = 100.0;
+            let x0 = -1000.0 + i as f64 * w;
+            let z0 = -1000.0 + j as f64 * w;
+            let y1 = 1.0 + 100.0 * rand();
+            objects.push(Arc::new(Cuboid::new(
+                Point3::new(x0, 0.0, z0),
+                Point3::new(x0 + w, y1, z0 + w),
+                ground.clone(),
+            )));
+        }
+    }
+
+    let light = Arc::new(DiffuseLight::new(Arc::new(SolidTexture::new(Color::new(
+        7.0, 7.0, 7.0,
+    )))));
+    objects.push(Arc::new(Quad::xz_rect(
+        (123.0, 423.0),
+        (147.0, 412.0),
+        554.0,
+        light,
+    )));
+
+    let center0 = Point3::new(400.0, 400.0, 200.0);
+    objects.push(Arc::new(MovingSphere {
+        center0,
+        center1: center0 + vec3(30.0, 0.0, 0.0),
+        time0: 0.0,
+        time1: 1.0,
+        radius: 50.0,
+        material: Arc::new(Lambertian::from_constant(Color::new(0.7, 0.3, 0.1))),
+    }));
+
+    objects.push(Arc::new(Sphere {
+        center: Point3::new(260.0, 150.0, 45.0),
+        radius: 50.0,
+        material: Arc::new(Dielectric::new_glass()),
+    }));
+    objects.push(Arc::new(Sphere {
+        center: Point3::new(0.0, 150.0, 145.0),
+        radius: 50.0,
+        material: Arc::new(Metal::new(vec3(0.8, 0.8, 0.9), 1.0)),
+    }));
+
+    // A glass ball filled with blue smoke
+    let boundary = Arc::new(Sphere {
+        center: Point3::new(360.0, 150.0, 145.0),
+        radius: 70.0,
+        material: Arc::new(Dielectric::new_glass()),
+    });
+    objects.push(boundary.clone());
+    objects.push(Arc::new(ConstantMedium::new(
+        boundary,
+        0.2,
+        Arc::new(Isotropic::from_constant(Color::new(0.2, 0.4, 0.9))),
+    )));
+
+    // Thin fog over the whole scene
+    let boundary = Arc::new(Sphere {
+        center: Point3::new(0.0, 0.0, 0.0),
+        radius: 5000.0,
+        material: Arc::new(Dielectric::new_glass()),
+    });
+    objects.push(Arc::new(ConstantMedium::new(
+        boundary,
+        0.0001,
+        Arc::new(Isotropic::from_constant(Color::white())),
+    )));
+
+    let noise_texture = Arc::new(NoiseTexture::new(
+        0.1,
+        7,
+        1.0,
+        0.5,
+        2.0,
+        Color::black(),
+        Color::white(),
+    ));
+    objects.push(Arc::new(Sphere {
+        center: Point3::new(220.0, 280.0, 300.0),
+        radius: 80.0,
+        material: Arc::new(Lambertian::new(noise_texture)),
+    }));
+
+    // A cube of small spheres, turned and moved next to the others
+    let white = Arc::new(Lambertian::from_constant(Color::new(0.73, 0.73, 0.73)));
+    let placement =
+        Matrix4::from_translation(vec3(-100.0, 270.0, 395.0)) * Matrix4::from_angle_y(Deg(15.0));
+    for _ in 0..1000 {
+        let center = Point3::new(165.0 * rand(), 165.0 * rand(), 165.0 * rand());
+        objects.push(Arc::new(Sphere {
+            center: placement.transform_point(center),
+            radius: 10.0,
+            material: white.clone(),
+        }));
+    }
+
+    Scene::new(
+        render_options,
+        camera,
+        Arc::new(SceneBVH::build(objects.objects)),
+        Arc::new(Lambertian::from_constant(Color::black())),
+    )
+}
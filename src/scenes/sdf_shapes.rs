@@ -0,0 +1,108 @@
+use crate::tracer::bounding_volumes::{SceneBVH, AABB};
+use crate::tracer::geometry::{Plane, Sphere};
+use crate::tracer::material::{Dielectric, Lambertian, Metal, Sky};
+use crate::tracer::sdf::{
+    Mandelbulb, Repeat, Round, SdfBox, SdfCapsule, SdfObject, SdfSphere, SmoothUnion, Translate,
+    Twist,
+};
+use crate::tracer::{Camera, Color, RenderOpts, Scene, SceneObjectList, SimpleCamera};
+use cgmath::*;
+use std::sync::Arc;
+
+fn get_camera(width: u64, height: u64) -> Arc<dyn Camera> {
+    let width = width as f64;
+    let height = height as f64;
+
+    let look_from = Point3::new(0.0, 2.5, 7.0);
+    let look_at = vec3(0.0, 0.6, 0.0);
+    let up = vec3(0.0, 1.0, 0.0);
+    let focus_dist = 7.0;
+    let aperture = 0.0;
+
+    let camera = SimpleCamera::new(
+        look_from,
+        look_at,
+        up,
+        40.0,
+        width / height,
+        aperture,
+        focus_dist,
+    );
+
+    Arc::new(camera)
+}
+
+/// Shapes traced from signed distance functions, side by side with a regular sphere.
+pub fn get_scene(width: u64, height: u64, samples: u64) -> Scene {
+    let camera = get_camera(width, height);
+    let render_options = RenderOpts {
+        max_depth: 50,
+        samples: samples as u32,
+    };
+
+    let mut objects = SceneObjectList::new();
+    objects.push(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        vec3(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::from_constant(Color::new(0.5, 0.5, 0.5))),
+    )));
+
+    let mandelbulb = Translate::new(Arc::new(Mandelbulb::new(8.0, 12)), vec3(0.0, 1.2, 0.0));
+    objects.push(Arc::new(SdfObject::new(
+        Arc::new(mandelbulb),
+        AABB::new(vec3(-1.2, 0.0, -1.2), vec3(1.2, 2.4, 1.2)),
+        Arc::new(Lambertian::from_constant(Color::new(0.8, 0.5, 0.3))),
+    )));
+
+    let twisted_box = Twist::new(
+        Arc::new(Round::new(Arc::new(SdfBox::new(vec3(0.3, 0.8, 0.3))), 0.1)),
+        1.2,
+    );
+    objects.push(Arc::new(SdfObject::new(
+        Arc::new(Translate::new(Arc::new(twisted_box), vec3(-2.5, 0.9, 0.0))),
+        AABB::new(vec3(-3.1, 0.0, -0.6), vec3(-1.9, 1.8, 0.6)),
+        Arc::new(Metal::new(vec3(0.7, 0.7, 0.8), 0.1)),
+    )));
+
+    let blob = SmoothUnion::new(
+        Arc::new(SdfSphere::new(0.5)),
+        Arc::new(Translate::new(
+            Arc::new(SdfSphere::new(0.35)),
+            vec3(0.45, 0.4, 0.0),
+        )),
+        0.3,
+    );
+    objects.push(Arc::new(SdfObject::new(
+        Arc::new(Translate::new(Arc::new(blob), vec3(2.5, 0.5, 0.0))),
+        AABB::new(vec3(1.9, 0.0, -0.6), vec3(3.4, 1.3, 0.6)),
+        Arc::new(Dielectric::new(1.5)),
+    )));
+
+    let capsules = Repeat::new(
+        Arc::new(SdfCapsule::new(
+            Point3::new(0.0, 0.15, -0.3),
+            Point3::new(0.0, 0.15, 0.3),
+            0.15,
+        )),
+        vec3(0.5, 0.0, 0.0),
+        [4, 0, 0],
+    );
+    objects.push(Arc::new(SdfObject::new(
+        Arc::new(Translate::new(Arc::new(capsules), vec3(0.0, 0.0, 2.0))),
+        AABB::new(vec3(-2.2, 0.0, 1.55), vec3(2.2, 0.3, 2.45)),
+        Arc::new(Lambertian::from_constant(Color::new(0.2, 0.4, 0.7))),
+    )));
+
+    objects.push(Arc::new(Sphere {
+        center: Point3::new(-1.3, 0.4, 1.2),
+        radius: 0.4,
+        material: Arc::new(Metal::new(vec3(0.9, 0.9, 0.9), 0.0)),
+    }));
+
+    Scene::new(
+        render_options,
+        camera,
+        Arc::new(SceneBVH::build(objects.objects)),
+        Arc::new(Sky::default()),
+    )
+}
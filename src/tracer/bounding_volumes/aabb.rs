@@ -15,6 +15,41 @@ impl AABB {
         AABB { min, max }
     }
 
+    /// Bounds of unbounded objects, such as infinite planes.
+    pub fn infinite() -> AABB {
+        let inf = f64::INFINITY;
+        AABB::new(vec3(-inf, -inf, -inf), vec3(inf, inf, inf))
+    }
+
+    pub fn is_finite(&self) -> bool {
+        (0..3).all(|axis| self.min[axis].is_finite() && self.max[axis].is_finite())
+    }
+
+    /// These bounds grown to at least `size` along every axis, so that flat objects still have
+    /// some thickness for ray/box tests.
+    #[must_use]
+    pub fn padded(&self, size: f64) -> AABB {
+        let mut padded = *self;
+        for axis in 0..3 {
+            let missing = size - (self.max[axis] - self.min[axis]);
+            if missing > 0.0 {
+                padded.min[axis] -= missing / 2.0;
+                padded.max[axis] += missing / 2.0;
+            }
+        }
+        padded
+    }
+
+    /// Area of the six faces, proportional to the chance of a random ray hitting the box.
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn centroid(&self) -> Vector3f {
+        (self.min + self.max) / 2.0
+    }
+
     #[must_use]
     pub fn union(&self, other: &AABB) -> Self {
         let min = vec3(
@@ -30,6 +65,33 @@ impl AABB {
         Self { min, max }
     }
 
+    /// The overlap of both bounds, flattened to a point of `other` when they don't overlap.
+    #[must_use]
+    pub fn intersection(&self, other: &AABB) -> Self {
+        let mut min = other.min;
+        let mut max = other.max;
+        for axis in 0..3 {
+            min[axis] = self.min[axis].max(other.min[axis]).min(other.max[axis]);
+            max[axis] = self.max[axis].min(other.max[axis]).max(min[axis]);
+        }
+        Self { min, max }
+    }
+
+    /// The part of `dist_min..dist_max` along `ray` inside these bounds, if any.
+    pub fn clip_ray(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<(f64, f64)> {
+        let (tmin, tmax) = find_min_max(
+            &self.min,
+            &self.max,
+            &ray.origin,
+            &ray.get_inverse_direction(),
+        );
+        let (near, far) = (tmin.max(dist_min), tmax.min(dist_max));
+        if near > far {
+            return None;
+        }
+        Some((near, far))
+    }
+
     #[allow(dead_code)]
     pub fn contains_point(self, p: Vector3f) -> bool {
         if p.x < self.min.x || p.x > self.max.x {
@@ -61,7 +123,7 @@ fn find_min_max(min: &Vector3f, max: &Vector3f, ro: &Point3f, invrd: &Vector3f)
 }
 
 impl BoundingVolume for AABB {
-    fn fast_intersects(&self, ray: &Ray, _dist_min: f64, _dist_max: f64) -> bool {
+    fn fast_intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
         let (tmin, tmax) = find_min_max(
             &self.min,
             &self.max,
@@ -69,8 +131,13 @@ impl BoundingVolume for AABB {
             &ray.get_inverse_direction(),
         );
 
-        // if tmax < 0, ray (line) is intersecting AABB, but the whole AABB is behind us
-        if tmax < 0. {
+        // if tmax < dist_min, ray (line) is intersecting AABB, but the whole AABB is behind us
+        if tmax < dist_min {
+            return false;
+        }
+
+        // the AABB is farther than anything the ray may still hit
+        if tmin > dist_max {
             return false;
         }
 
@@ -92,12 +159,12 @@ mod tests {
     fn test_fast_intersects() {
         let b = AABB::new(vec3(1., 1., 1.), vec3(2., 2., 2.));
         let r = Ray::new(Point3f::new(0.0, 0.0, 0.0), Vector3f::new(1.4, 1.0, 1.1));
-        let i = b.fast_intersects(&r, 0.0001, std::f64::MAX);
+        let i = b.fast_intersects(&r, 0.0001, f64::MAX);
         assert!(i);
 
         let b = AABB::new(vec3(1., 1., 1.), vec3(2., 2., 2.));
         let r = Ray::new(Point3f::new(0.0, 0.0, 0.0), Vector3f::new(1.4, 1.0, -1.1));
-        let i = b.fast_intersects(&r, 0.0001, std::f64::MAX);
+        let i = b.fast_intersects(&r, 0.0001, f64::MAX);
         assert!(!i);
     }
 }
@@ -0,0 +1,202 @@
+use crate::tracer::bounding_volumes::{Grid, KdTree, SceneBVH};
+use crate::tracer::{SceneIntersectable, SceneObject, SceneObjectList};
+use serde::Deserialize;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The structures a scene's objects can be held in, all behind `SceneIntersectable`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Accelerator {
+    /// `SceneBVH`
+    #[default]
+    Bvh,
+    /// `KdTree`
+    #[serde(rename = "kdtree")]
+    KdTree,
+    /// `Grid`
+    Grid,
+    /// `SceneObjectList`, testing every object: slow, but the reference the others must match
+    List,
+}
+
+impl FromStr for Accelerator {
+    type Err = serde_json::error::Error;
+    fn from_str(s: &str) -> Result<Accelerator, serde_json::error::Error> {
+        serde_json::from_str(&format!("\"{}\"", s))
+    }
+}
+
+impl Accelerator {
+    pub fn build(self, objects: Vec<Arc<dyn SceneObject>>) -> Arc<dyn SceneIntersectable> {
+        match self {
+            Accelerator::Bvh => Arc::new(SceneBVH::build(objects)),
+            Accelerator::KdTree => Arc::new(KdTree::build(objects)),
+            Accelerator::Grid => Arc::new(Grid::build(objects)),
+            Accelerator::List => Arc::new(SceneObjectList { objects }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::bounding_volumes::test_fixtures::*;
+    use crate::tracer::bounding_volumes::TraversalCounters;
+    use crate::tracer::geometry::{Cuboid, Disk, HeightMap, Heightfield, Plane, Quad, Sphere};
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{Color, Point3f, Ray, Vector3f};
+    use cgmath::*;
+
+    #[test]
+    fn test_matches_list() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        // A sparse field of spheres around a dense cluster, for the grid to nest a level in
+        let mut objects = sphere_grid(100, 10, |i| vec3((i % 10 * 2) as f64, 0.0, 0.0));
+        objects.push(Arc::new(Plane::new(
+            Point3::new(0.0, -1.0, 0.0),
+            Vector3f::unit_y(),
+            material.clone(),
+        )));
+        for i in 0..200 {
+            let i = i as f64;
+            let offset = vec3((i * 0.37).sin(), (i * 0.71).cos(), (i * 0.13).sin());
+            objects.push(Arc::new(Sphere {
+                center: Point3::new(13.0, 1.0, 9.0) + offset,
+                radius: 0.05,
+                material: material.clone(),
+            }));
+        }
+
+        let mut rays = ray_fan(Point3f::new(13.0, 8.0, -6.0), 900);
+        rays.extend(ray_fan(Point3f::new(13.0, 1.0, 9.0), 900));
+        for accelerator in &[Accelerator::Bvh, Accelerator::KdTree, Accelerator::Grid] {
+            let structure = accelerator.build(objects.clone());
+            assert_eq!(structure.leaves().len(), 301);
+            assert!(assert_matches_list(&*structure, objects.clone(), &rays) > rays.len() / 4);
+        }
+
+        assert_eq!(
+            "kdtree".parse::<Accelerator>().unwrap(),
+            Accelerator::KdTree
+        );
+        assert!("octree".parse::<Accelerator>().is_err());
+    }
+
+    #[test]
+    fn test_occluded() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let mut objects: Vec<Arc<dyn SceneObject>> = vec![Arc::new(Plane::new(
+            Point3::new(0.0, -2.0, 0.0),
+            Vector3f::unit_y(),
+            material.clone(),
+        ))];
+        let heights = (0..64).map(|i| ((i as f32) * 0.7).sin() * 0.5 + 0.5);
+        objects.push(Arc::new(Heightfield::new(
+            HeightMap::new([8, 8], heights.collect()),
+            Point3::new(-6.0, -1.5, -6.0),
+            vec3(12.0, 1.0, 12.0),
+            material.clone(),
+        )));
+        for i in 0..40 {
+            let center = Point3::new((i % 8) as f64 * 1.5 - 5.0, 1.0, (i / 8) as f64 * 2.0 - 4.0);
+            let object: Arc<dyn SceneObject> = match i % 4 {
+                0 => Arc::new(Quad::new(
+                    center,
+                    vec3(0.8, 0.0, 0.3),
+                    vec3(0.0, 0.9, 0.2),
+                    material.clone(),
+                )),
+                1 => Arc::new(Disk::new(
+                    center,
+                    vec3(0.3, 1.0, -0.4),
+                    0.6,
+                    material.clone(),
+                )),
+                2 => Arc::new(Cuboid::new(
+                    center,
+                    center + vec3(0.7, 0.4, 0.9),
+                    material.clone(),
+                )),
+                _ => Arc::new(Sphere {
+                    center,
+                    radius: 0.5,
+                    material: material.clone(),
+                }),
+            };
+            objects.push(object);
+        }
+
+        let origins = [
+            Point3f::new(0.0, 6.0, -12.0),
+            Point3f::new(-1.6, 1.2, -2.1),
+            Point3f::new(0.2, -1.0, 0.3),
+        ];
+        for accelerator in &[
+            Accelerator::Bvh,
+            Accelerator::KdTree,
+            Accelerator::Grid,
+            Accelerator::List,
+        ] {
+            let objects = accelerator.build(objects.clone());
+            for origin in &origins {
+                for i in 0..1000 {
+                    let direction = vec3(
+                        (i % 20) as f64 * 0.1 - 1.0,
+                        (i / 20 % 10) as f64 * 0.2 - 1.0,
+                        (i / 200) as f64 * 0.4 - 0.8,
+                    );
+                    let ray = Ray::new(*origin, direction);
+                    // Short shadow rays as well as unbounded ones
+                    for &dist_max in &[2.0, f64::MAX] {
+                        assert_eq!(
+                            objects.occluded(&ray, 0.001, dist_max),
+                            objects.intersect(&ray, 0.001, dist_max).is_some(),
+                            "{:?} {} {}",
+                            accelerator,
+                            ray,
+                            dist_max
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_occluded_stops_early() {
+        // Nested boxes around the ray origin: the closest hit is only known after testing them
+        // all, while any of them occludes the ray
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let objects: Vec<Arc<dyn SceneObject>> = (1..=50)
+            .map(|i| {
+                let size = i as f64;
+                Arc::new(Cuboid::new(
+                    Point3::new(-size, -size, -size),
+                    Point3::new(size, size, size),
+                    material.clone(),
+                )) as Arc<dyn SceneObject>
+            })
+            .collect();
+
+        let ray = Ray::new(Point3f::new(0.1, 0.2, 0.3), vec3(1.0, 0.3, 0.2));
+        for accelerator in &[
+            Accelerator::Bvh,
+            Accelerator::KdTree,
+            Accelerator::Grid,
+            Accelerator::List,
+        ] {
+            let objects = accelerator.build(objects.clone());
+
+            let (hit, counters) =
+                TraversalCounters::measure(|| objects.intersect(&ray, 0.001, f64::MAX));
+            assert!((hit.unwrap().intersection.point.x - 1.0).abs() < 1e-9);
+            assert!(counters.primitives >= 50, "{:?}", accelerator);
+
+            let (occluded, counters) =
+                TraversalCounters::measure(|| objects.occluded(&ray, 0.001, f64::MAX));
+            assert!(occluded);
+            assert_eq!(counters.primitives, 1, "{:?}", accelerator);
+        }
+    }
+}
@@ -28,119 +28,862 @@
 //  Avg rays/sec = 734,142.4
 //
 //  TOTAL ~44 percent improve
+//
+// With the binned SAH builder (cargo bench, 400x300, 1 sample, camera rays per sec):
+//
+//  weekend_spheres/render:                 469 K -> 1.02 M
+//  large_mesh/render (131,072 triangles):  212 K -> 987 K
+//  large_mesh/bvh_build:                   261 ms -> 126 ms
+//
+// Flattened, with an iterative front-to-back traversal:
+//
+//  weekend_spheres/render:                 1.02 M -> 1.17 M
+//  large_mesh/render:                      987 K -> 1.15 M
+//  large_mesh/bvh_build:                   126 ms -> 109 ms
+//
+// Collapsed into a 4-wide BVH (WideBVH), camera rays per sec:
+//
+//  weekend_spheres/render:                 1.23 M -> 1.48 M
+//  large_mesh/render:                      1.25 M -> 1.59 M
+//
+// Closest hits of the camera rays only, BVHNode / WideBVH / WideBVH packets of 8x8 pixels:
+//
+//  weekend_spheres:                        4.66 M / 6.32 M / 5.56 M
+//  large_mesh:                             3.27 M / 4.02 M / 3.70 M
+//
+// The other accelerators (--accel), renders in camera rays per sec and builds:
+//
+//                     BVHNode    KdTree     Grid
+//  weekend_spheres:   1.17 M     1.23 M     1.04 M
+//  large_mesh:        1.18 M     861 K      736 K
+//  large_mesh build:  119 ms     538 ms     61 ms
 
-use crate::tracer::bounding_volumes::{Boundable, BoundingVolume, AABB};
-use crate::tracer::{Ray, SceneIntersectable, SceneIntersection, SceneObject};
-use rand::prelude::*;
+use crate::tracer::bounding_volumes::{
+    Accelerator, Boundable, BoundingVolume, TraversalCounters, AABB,
+};
+use crate::tracer::{
+    Intersection, Ray, SceneIntersectable, SceneIntersection, SceneObject, SceneObjectList,
+};
+use rayon::prelude::*;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Number of buckets the centroids are sorted into when looking for a split.
+const BINS: usize = 12;
+/// Nodes with at most this many objects may become leaves.
+const MAX_LEAF_SIZE: usize = 4;
+/// Cost of testing a ray against the bounds of a node, relative to testing it against an object.
+const TRAVERSAL_COST: f64 = 0.125;
+/// Nodes with fewer objects than this are built on the current thread, larger ones are binned
+/// and have their children built in parallel on the rayon thread pool.
+const PARALLEL_THRESHOLD: usize = 4096;
+/// SAH cost of a refitted tree, relative to its cost when it was built, past which `update`
+/// rebuilds it.
+const MAX_REFIT_COST_RATIO: f64 = 1.5;
 
+/// Bounding volume hierarchy, built with the surface area heuristic (SAH): each node is split
+/// where the expected cost of tracing a ray through both halves is the lowest, or not at all when
+/// testing its few objects one by one is cheaper.
+///
+/// The build doesn't depend on anything but the objects and their order, so the same scene always
+/// gets the same tree.
+///
+/// The tree is stored flat, in depth-first order: the first child of a node comes right after it,
+/// and the objects are reordered so that each leaf holds a contiguous range of them.
 pub struct BVHNode {
+    pub(super) nodes: Vec<LinearNode>,
+    pub(super) objects: Vec<Arc<dyn SceneObject>>,
+    /// Index in the list given to `build` of each of `objects`
+    order: Vec<u32>,
+    /// SAH cost of the tree right after it was built
+    build_cost: f64,
+    stats: BVHStats,
+}
+
+/// Shape of BVHs and time spent building them.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct BVHStats {
+    /// Number of trees these stats cover
+    pub builds: usize,
+    pub build_time: Duration,
+    pub nodes: usize,
+    pub leaves: usize,
+    pub objects: usize,
+    pub max_depth: usize,
+}
+
+impl BVHStats {
+    /// Adds the stats of `other` trees to these.
+    pub fn add(&mut self, other: &BVHStats) {
+        self.builds += other.builds;
+        self.build_time += other.build_time;
+        self.nodes += other.nodes;
+        self.leaves += other.leaves;
+        self.objects += other.objects;
+        self.max_depth = self.max_depth.max(other.max_depth);
+    }
+}
+
+pub(super) struct LinearNode {
+    pub(super) bounds: AABB,
+    /// Index of the first object of a leaf, or of the second child of an inner node
+    pub(super) offset: u32,
+    /// Number of objects of a leaf, 0 for inner nodes
+    pub(super) count: u16,
+    /// Axis along which the children of an inner node were split
+    pub(super) axis: u8,
+}
+
+/// Deepest path through a tree. Past half of it, nodes are split in halves rather than by cost,
+/// which leaves room for 2^32 objects. Traversals keep the far child of each node on the path
+/// to the current one, so their stacks hold `MAX_DEPTH` nodes.
+pub(super) const MAX_DEPTH: usize = 64;
+
+/// An object waiting to be placed in the tree, with its bounds computed once.
+struct BuildItem {
+    index: u32,
+    object: Arc<dyn SceneObject>,
     bounds: AABB,
-    object: Option<Arc<dyn SceneObject>>,
-    left: Option<Arc<Self>>,
-    right: Option<Arc<Self>>,
+    centroid: [f64; 3],
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    count: usize,
+    bounds: Option<AABB>,
+}
+
+const EMPTY_BIN: Bin = Bin {
+    count: 0,
+    bounds: None,
+};
+
+#[derive(Clone, Copy)]
+struct Split {
+    axis: usize,
+    /// Objects in the bins up to this one go left
+    bin: usize,
+    cost: f64,
 }
 
 impl BVHNode {
-    pub fn new(
-        bounds: AABB,
-        object: Option<Arc<dyn SceneObject>>,
-        left: Option<Arc<Self>>,
-        right: Option<Arc<Self>>,
-    ) -> BVHNode {
-        BVHNode {
-            bounds,
-            object,
-            left,
-            right,
+    /// Builds the tree over `objects`, using the rayon thread pool for large ones.
+    pub fn build(objects: Vec<Arc<dyn SceneObject>>) -> Self {
+        assert!(!objects.is_empty(), "cannot build a BVH without objects");
+        let start_time = Instant::now();
+
+        let mut items: Vec<BuildItem> = objects
+            .into_par_iter()
+            .enumerate()
+            .map(|(index, object)| {
+                let bounds = object.get_bounds();
+                let centroid = bounds.centroid();
+                BuildItem {
+                    index: index as u32,
+                    object,
+                    bounds,
+                    centroid: [centroid.x, centroid.y, centroid.z],
+                }
+            })
+            .collect();
+
+        let mut nodes = Vec::with_capacity(2 * items.len());
+        let max_depth = Self::build_node(&mut items, 0, 0, &mut nodes);
+
+        let stats = BVHStats {
+            builds: 1,
+            build_time: start_time.elapsed(),
+            nodes: nodes.len(),
+            leaves: nodes.iter().filter(|node| node.count > 0).count(),
+            objects: items.len(),
+            max_depth,
+        };
+
+        let mut bvh = BVHNode {
+            nodes,
+            order: items.iter().map(|item| item.index).collect(),
+            objects: items.into_iter().map(|item| item.object).collect(),
+            build_cost: 0.0,
+            stats,
+        };
+        bvh.build_cost = bvh.sah_cost();
+        bvh
+    }
+
+    pub fn stats(&self) -> BVHStats {
+        self.stats
+    }
+
+    /// Expected cost of tracing a random ray through the tree, in object tests.
+    pub fn sah_cost(&self) -> f64 {
+        let root_area = self.nodes[0].bounds.surface_area();
+        let cost: f64 = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let node_cost = if node.count > 0 {
+                    node.count as f64
+                } else {
+                    TRAVERSAL_COST
+                };
+                node_cost * node.bounds.surface_area()
+            })
+            .sum();
+        if root_area > 0.0 {
+            cost / root_area
+        } else {
+            cost
         }
     }
 
-    pub fn build(objects: Vec<Arc<dyn SceneObject>>) -> Self {
-        let axis = rand::thread_rng().gen_range(0, 3);
-        let mut objects = objects;
+    /// Replaces the objects of the tree by `objects`, given in the same order as to `build`,
+    /// such as the same objects moved for the next frame of an animation, then updates the bounds
+    /// of the nodes from the leaves up to the root. The tree keeps its shape.
+    ///
+    /// # Panics
+    ///
+    /// If the number of objects changed.
+    pub fn refit(&mut self, objects: Vec<Arc<dyn SceneObject>>) {
+        assert_eq!(
+            objects.len(),
+            self.objects.len(),
+            "refitted BVHs must keep the same objects"
+        );
+        for (object, &index) in self.objects.iter_mut().zip(&self.order) {
+            *object = objects[index as usize].clone();
+        }
+
+        // Children come after their parent, so going backwards updates them first
+        for index in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[index];
+            let bounds = if node.count > 0 {
+                let first = node.offset as usize;
+                let objects = &self.objects[first..first + node.count as usize];
+                objects[1..]
+                    .iter()
+                    .fold(objects[0].get_bounds(), |bounds, object| {
+                        bounds.union(&object.get_bounds())
+                    })
+            } else {
+                let first_child = &self.nodes[index + 1];
+                let second_child = &self.nodes[node.offset as usize];
+                first_child.bounds.union(&second_child.bounds)
+            };
+            self.nodes[index].bounds = bounds;
+        }
+    }
 
-        objects.sort_by(|a, b| {
-            let left_hit = a.get_bounds().min;
-            let right_hit = b.get_bounds().min;
+    /// Refits the tree to `objects`, or builds it again if the objects moved so much that the
+    /// refitted tree would be much slower to trace. Returns whether it was rebuilt.
+    ///
+    /// # Panics
+    ///
+    /// If the number of objects changed.
+    pub fn update(&mut self, objects: Vec<Arc<dyn SceneObject>>) -> bool {
+        self.refit(objects);
+        if self.sah_cost() <= MAX_REFIT_COST_RATIO * self.build_cost {
+            return false;
+        }
+
+        let mut objects = vec![None; self.objects.len()];
+        for (object, &index) in self.objects.iter().zip(&self.order) {
+            objects[index as usize] = Some(object.clone());
+        }
+        *self = BVHNode::build(objects.into_iter().flatten().collect());
+        true
+    }
 
-            left_hit[axis].partial_cmp(&right_hit[axis]).unwrap()
+    /// Appends the subtree holding `items`, the objects from index `first`, to `nodes`, and
+    /// returns the depth of its deepest leaf.
+    fn build_node(
+        items: &mut [BuildItem],
+        first: usize,
+        depth: usize,
+        nodes: &mut Vec<LinearNode>,
+    ) -> usize {
+        let bounds = if items.len() < PARALLEL_THRESHOLD {
+            bounds_of(items)
+        } else {
+            items
+                .par_chunks(PARALLEL_THRESHOLD)
+                .map(bounds_of)
+                .reduce_with(|a, b| a.union(&b))
+                .unwrap()
+        };
+        let index = nodes.len();
+        nodes.push(LinearNode {
+            bounds,
+            offset: first as u32,
+            count: items.len() as u16,
+            axis: 0,
         });
+        if items.len() == 1 {
+            return depth;
+        }
 
-        match objects.len() {
-            0 => panic!("invalid length"),
-            1 => {
-                let scene_object = objects.remove(0);
-                let bounding_box = scene_object.get_bounds();
-                BVHNode::new(bounding_box, Some(scene_object), None, None)
+        let (centroid_min, centroid_max) = centroid_range(items);
+        let split = if depth < MAX_DEPTH / 2 {
+            find_split(items, &bounds, &centroid_min, &centroid_max)
+        } else {
+            None
+        };
+        let items_len = items.len();
+        let leaf_cost = items_len as f64;
+
+        let middle = match split {
+            Some(split) if split.cost < leaf_cost || items.len() > MAX_LEAF_SIZE => {
+                let axis = split.axis;
+                let (min, max) = (centroid_min[axis], centroid_max[axis]);
+                partition(items, |item| {
+                    bin_index(item.centroid[axis], min, max) <= split.bin
+                })
             }
-            _ => {
-                let (h1, h2) = objects.split_at(objects.len() / 2);
-                let h1 = h1.to_vec();
-                let h2 = h2.to_vec();
-
-                let left = Self::build(h1);
-                let right = Self::build(h2);
-                let bounding_box = left.get_bounds().union(&right.get_bounds());
-
-                BVHNode::new(
-                    bounding_box,
-                    None,
-                    Some(Arc::new(left)),
-                    Some(Arc::new(right)),
-                )
+            _ if items.len() <= MAX_LEAF_SIZE => return depth,
+            // All the centroids are at the same place, the costs can't be compared or the tree is
+            // too deep: any split is as good as another, but the leaves must stay small
+            _ => items.len() / 2,
+        };
+
+        let (left, right) = items.split_at_mut(middle);
+        let (left_depth, second, right_depth) = if items_len < PARALLEL_THRESHOLD {
+            let left_depth = Self::build_node(left, first, depth + 1, nodes);
+            let second = nodes.len();
+            let right_depth = Self::build_node(right, first + middle, depth + 1, nodes);
+            (left_depth, second, right_depth)
+        } else {
+            // Both subtrees are built apart then appended, which keeps the same layout as a
+            // sequential build
+            let build_subtree = |items: &mut [BuildItem], first| {
+                let mut subtree = Vec::with_capacity(2 * items.len());
+                let depth = Self::build_node(items, first, depth + 1, &mut subtree);
+                (depth, subtree)
+            };
+            let ((left_depth, left_nodes), (right_depth, right_nodes)) = rayon::join(
+                || build_subtree(left, first),
+                || build_subtree(right, first + middle),
+            );
+            append_subtree(nodes, left_nodes);
+            let second = nodes.len();
+            append_subtree(nodes, right_nodes);
+            (left_depth, second, right_depth)
+        };
+
+        let node = &mut nodes[index];
+        node.offset = second as u32;
+        node.count = 0;
+        node.axis = split.map_or(0, |split| split.axis as u8);
+        left_depth.max(right_depth)
+    }
+}
+
+/// Moves the nodes of a subtree built on its own to the end of `nodes`.
+fn append_subtree(nodes: &mut Vec<LinearNode>, subtree: Vec<LinearNode>) {
+    let base = nodes.len() as u32;
+    nodes.extend(subtree.into_iter().map(|mut node| {
+        if node.count == 0 {
+            node.offset += base;
+        }
+        node
+    }));
+}
+
+fn bounds_of(items: &[BuildItem]) -> AABB {
+    items[1..]
+        .iter()
+        .fold(items[0].bounds, |bounds, item| bounds.union(&item.bounds))
+}
+
+fn centroid_range(items: &[BuildItem]) -> ([f64; 3], [f64; 3]) {
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for item in items {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(item.centroid[axis]);
+            max[axis] = max[axis].max(item.centroid[axis]);
+        }
+    }
+    (min, max)
+}
+
+fn bin_index(centroid: f64, min: f64, max: f64) -> usize {
+    let bin = ((centroid - min) / (max - min) * BINS as f64) as usize;
+    bin.min(BINS - 1)
+}
+
+/// The cheapest split of `items` between buckets along any axis, if the centroids are spread
+/// along one of them.
+fn find_split(
+    items: &[BuildItem],
+    bounds: &AABB,
+    centroid_min: &[f64; 3],
+    centroid_max: &[f64; 3],
+) -> Option<Split> {
+    let area = bounds.surface_area();
+    let mut best: Option<Split> = None;
+
+    for axis in 0..3 {
+        let (min, max) = (centroid_min[axis], centroid_max[axis]);
+        let extent = max - min;
+        if !extent.is_finite() || extent <= 0.0 {
+            continue;
+        }
+
+        let bin_items = |items: &[BuildItem]| {
+            let mut bins = [EMPTY_BIN; BINS];
+            for item in items {
+                let bin = &mut bins[bin_index(item.centroid[axis], min, max)];
+                bin.count += 1;
+                bin.bounds = Some(union(bin.bounds, &item.bounds));
             }
+            bins
+        };
+        let bins = if items.len() < PARALLEL_THRESHOLD {
+            bin_items(items)
+        } else {
+            items.par_chunks(PARALLEL_THRESHOLD).map(bin_items).reduce(
+                || [EMPTY_BIN; BINS],
+                |mut a, b| {
+                    for (a, b) in a.iter_mut().zip(&b) {
+                        *a = merge(*a, *b);
+                    }
+                    a
+                },
+            )
+        };
+
+        // Costs of the right side of each split, sweeping from the last bucket
+        let mut right_costs = [0.0; BINS];
+        let mut right = EMPTY_BIN;
+        for bin in (1..BINS).rev() {
+            right = merge(right, bins[bin]);
+            right_costs[bin - 1] = side_cost(&right);
         }
+
+        let mut left = EMPTY_BIN;
+        for bin in 0..BINS - 1 {
+            left = merge(left, bins[bin]);
+            if left.count == 0 || left.count == items.len() {
+                continue;
+            }
+
+            let cost = TRAVERSAL_COST + (side_cost(&left) + right_costs[bin]) / area;
+            if best
+                .as_ref()
+                .map_or(cost.is_finite(), |best| cost < best.cost)
+            {
+                best = Some(Split { axis, bin, cost });
+            }
+        }
+    }
+
+    best
+}
+
+fn union(bounds: Option<AABB>, other: &AABB) -> AABB {
+    bounds.map_or(*other, |bounds| bounds.union(other))
+}
+
+fn merge(a: Bin, b: Bin) -> Bin {
+    Bin {
+        count: a.count + b.count,
+        bounds: match b.bounds {
+            Some(bounds) => Some(union(a.bounds, &bounds)),
+            None => a.bounds,
+        },
     }
 }
 
+fn side_cost(side: &Bin) -> f64 {
+    side.bounds
+        .map_or(0.0, |bounds| bounds.surface_area() * side.count as f64)
+}
+
+/// Moves the items matching `left` first and returns how many there are.
+fn partition(items: &mut [BuildItem], left: impl Fn(&BuildItem) -> bool) -> usize {
+    let mut middle = 0;
+    for i in 0..items.len() {
+        if left(&items[i]) {
+            items.swap(middle, i);
+            middle += 1;
+        }
+    }
+    middle
+}
+
 impl Boundable for BVHNode {
     fn get_bounds(&self) -> AABB {
-        self.bounds
+        self.nodes[0].bounds
     }
 }
 
 impl SceneIntersectable for BVHNode {
     fn intersect(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<SceneIntersection> {
-        let box_intersection = self.bounds.fast_intersects(ray, dist_min, dist_max);
-        if box_intersection {
-            if let Some(object) = &self.object {
-                // We reached a leaf node
-
-                let object = object.clone();
-                return object
-                    .intersects(ray, dist_min, dist_max)
-                    .map(|intersection| SceneIntersection {
-                        intersection,
-                        object,
-                    });
+        let counting = TraversalCounters::enabled();
+        let mut closest: Option<(&Arc<dyn SceneObject>, Intersection)> = None;
+        let mut dist_max = dist_max;
+
+        // Far children waiting for a visit, one per ancestor of the current node at most
+        let mut stack = [0; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            if counting {
+                TraversalCounters::count_node();
             }
+            if node.bounds.fast_intersects(ray, dist_min, dist_max) {
+                if node.count == 0 {
+                    // Visit the child the ray reaches first, so that its hits cut the other one
+                    // short
+                    let first_child = index + 1;
+                    let second_child = node.offset as usize;
+                    let (near, far) = if ray.direction[node.axis as usize] < 0.0 {
+                        (second_child, first_child)
+                    } else {
+                        (first_child, second_child)
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    index = near;
+                    continue;
+                }
 
-            let left = self.left.clone();
-            let right = self.right.clone();
-            let lsi = match left {
-                Some(node) => node.intersect(ray, dist_min, dist_max),
-                None => None,
-            };
-            let rsi = match right {
-                Some(node) => node.intersect(ray, dist_min, dist_max),
-                None => None,
-            };
+                let first = node.offset as usize;
+                for object in &self.objects[first..first + node.count as usize] {
+                    if counting {
+                        TraversalCounters::count_primitive();
+                    }
+                    if let Some(intersection) = object.intersects(ray, dist_min, dist_max) {
+                        if intersection.dist < dist_max {
+                            dist_max = intersection.dist;
+                            closest = Some((object, intersection));
+                        }
+                    }
+                }
+            }
 
-            return match (lsi, rsi) {
-                (Some(lsi), Some(rsi)) => {
-                    if lsi.intersection.dist < rsi.intersection.dist {
-                        Some(lsi)
-                    } else {
-                        Some(rsi)
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            index = stack[stack_len];
+        }
+
+        closest.map(|(object, intersection)| SceneIntersection {
+            intersection,
+            object: object.clone(),
+        })
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        let counting = TraversalCounters::enabled();
+        let mut stack = [0; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            if counting {
+                TraversalCounters::count_node();
+            }
+            if node.bounds.fast_intersects(ray, dist_min, dist_max) {
+                if node.count == 0 {
+                    stack[stack_len] = node.offset as usize;
+                    stack_len += 1;
+                    index += 1;
+                    continue;
+                }
+
+                let first = node.offset as usize;
+                let objects = &self.objects[first..first + node.count as usize];
+                if objects.iter().any(|object| {
+                    if counting {
+                        TraversalCounters::count_primitive();
                     }
+                    object.occluded(ray, dist_min, dist_max)
+                }) {
+                    return true;
                 }
-                (Some(lsi), None) => Some(lsi),
-                (None, Some(rsi)) => Some(rsi),
-                _ => None,
-            };
+            }
+
+            if stack_len == 0 {
+                return false;
+            }
+            stack_len -= 1;
+            index = stack[stack_len];
         }
+    }
+
+    fn leaves(&self) -> Vec<Arc<dyn SceneObject>> {
+        self.objects.clone()
+    }
+
+    fn accelerator(&self) -> Option<Accelerator> {
+        Some(Accelerator::Bvh)
+    }
+
+    fn bvh_stats(&self) -> Option<BVHStats> {
+        Some(self.stats)
+    }
+}
+
+/// The objects of a scene: a BVH over the bounded ones, and the unbounded ones (such as
+/// infinite planes) which no box can hold, tested one by one.
+///
+/// Instances are leaves of this top-level tree: they move rays into the space of their object,
+/// such as a mesh with its own BVH, which all the instances of that object share.
+pub struct SceneBVH {
+    bvh: Option<BVHNode>,
+    unbounded: SceneObjectList,
+}
+
+impl SceneBVH {
+    pub fn build(objects: Vec<Arc<dyn SceneObject>>) -> SceneBVH {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .partition(|object| object.get_bounds().is_finite());
+
+        SceneBVH {
+            bvh: if bounded.is_empty() {
+                None
+            } else {
+                Some(BVHNode::build(bounded))
+            },
+            unbounded: SceneObjectList { objects: unbounded },
+        }
+    }
+
+    /// Updates the BVH with `objects`, given in the same order as to `build`, see
+    /// `BVHNode::update`. Returns whether it was rebuilt.
+    pub fn update(&mut self, objects: Vec<Arc<dyn SceneObject>>) -> bool {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .partition(|object| object.get_bounds().is_finite());
+        self.unbounded = SceneObjectList { objects: unbounded };
+
+        match &mut self.bvh {
+            Some(bvh) if bvh.objects.len() == bounded.len() => bvh.update(bounded),
+            _ if bounded.is_empty() => {
+                self.bvh = None;
+                false
+            }
+            _ => {
+                self.bvh = Some(BVHNode::build(bounded));
+                true
+            }
+        }
+    }
+}
+
+impl SceneIntersectable for SceneBVH {
+    fn intersect(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<SceneIntersection> {
+        let closest = self
+            .bvh
+            .as_ref()
+            .and_then(|bvh| bvh.intersect(ray, dist_min, dist_max));
+        let dist_max = closest
+            .as_ref()
+            .map_or(dist_max, |closest| closest.intersection.dist);
+
+        self.unbounded
+            .intersect(ray, dist_min, dist_max)
+            .or(closest)
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.bvh
+            .as_ref()
+            .is_some_and(|bvh| bvh.occluded(ray, dist_min, dist_max))
+            || self.unbounded.occluded(ray, dist_min, dist_max)
+    }
+
+    fn leaves(&self) -> Vec<Arc<dyn SceneObject>> {
+        let mut leaves = self.bvh.as_ref().map_or_else(Vec::new, |bvh| bvh.leaves());
+        leaves.extend(self.unbounded.leaves());
+        leaves
+    }
+
+    fn accelerator(&self) -> Option<Accelerator> {
+        Some(Accelerator::Bvh)
+    }
+
+    fn bvh_stats(&self) -> Option<BVHStats> {
+        self.bvh.as_ref().map(BVHNode::stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::bounding_volumes::test_fixtures::*;
+    use crate::tracer::geometry::{Plane, Sphere};
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{Color, Point3f, Vector3f};
+    use cgmath::*;
+
+    #[test]
+    fn test_unbounded_objects() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let ground = Arc::new(Plane::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3f::unit_y(),
+            material.clone(),
+        ));
+        let sphere = Arc::new(Sphere {
+            center: Point3::new(0.0, 1.0, 0.0),
+            radius: 0.5,
+            material,
+        });
+        let scene = SceneBVH::build(vec![ground, sphere]);
+        assert_eq!(scene.leaves().len(), 2);
+
+        let down = -Vector3f::unit_y();
+        let hit = scene
+            .intersect(
+                &Ray::new(Point3f::new(0.0, 5.0, 0.0), down),
+                0.001,
+                f64::MAX,
+            )
+            .unwrap();
+        assert!((hit.intersection.dist - 3.5).abs() < 1e-12);
+
+        let hit = scene
+            .intersect(
+                &Ray::new(Point3f::new(3.0, 5.0, 0.0), down),
+                0.001,
+                f64::MAX,
+            )
+            .unwrap();
+        assert!((hit.intersection.dist - 5.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let objects = sphere_grid(200, 10, |_| Vector3f::zero());
+        let bvh = BVHNode::build(objects.clone());
+        assert_eq!(bvh.leaves().len(), 200);
+
+        let rays = ray_fan(Point3f::new(4.5, 8.0, -6.0), 600);
+        assert!(assert_matches_list(&bvh, objects, &rays) > 0);
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        // Spheres growing 16 times in size and distance: each SAH split only peels the largest
+        // one off, down to the depth where nodes are split in halves, here through a pile of
+        // spheres sharing the same center
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let mut objects: Vec<Arc<dyn SceneObject>> = (0..40)
+            .map(|i| {
+                let size = 16f64.powi(i);
+                Arc::new(Sphere {
+                    center: Point3::new(size, 0.0, 0.0),
+                    radius: size / 4.0,
+                    material: material.clone(),
+                }) as Arc<dyn SceneObject>
+            })
+            .collect();
+        for i in 0..1000 {
+            objects.push(Arc::new(Sphere {
+                center: Point3::new(0.0, 0.0, 0.0),
+                radius: 0.01 + i as f64 * 0.0001,
+                material: material.clone(),
+            }));
+        }
+        let bvh = BVHNode::build(objects.clone());
+        assert!(bvh.stats().max_depth > MAX_DEPTH / 2);
+
+        let rays: Vec<Ray> = (0..2000)
+            .map(|i| {
+                let origin = Point3f::new(-1.0, 0.5, -2.0) * (1 + i % 40) as f64;
+                let target = Point3f::new(16f64.powi(i % 40), (i % 7) as f64 * 0.02, 0.0);
+                Ray::new(origin, target - origin)
+            })
+            .collect();
+        assert!(assert_matches_list(&bvh, objects, &rays) > 0);
+    }
+
+    #[test]
+    fn test_parallel_build() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let objects: Vec<Arc<dyn SceneObject>> = (0..3 * PARALLEL_THRESHOLD)
+            .map(|i| {
+                let x = (i % 97) as f64;
+                let z = (i / 97) as f64;
+                Arc::new(Sphere {
+                    center: Point3::new(x, ((i * 7919) % 13) as f64, z),
+                    radius: 0.2 + (i % 3) as f64 * 0.2,
+                    material: material.clone(),
+                }) as Arc<dyn SceneObject>
+            })
+            .collect();
+
+        let build = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| BVHNode::build(objects.clone()))
+        };
+        let sequential = build(1);
+        let parallel = build(4);
+
+        let stats = |bvh: &BVHNode| BVHStats {
+            build_time: Duration::from_secs(0),
+            ..bvh.stats()
+        };
+        assert_eq!(stats(&sequential), stats(&parallel));
+        assert_eq!(sequential.stats().objects, objects.len());
+        assert!(sequential
+            .leaves()
+            .iter()
+            .zip(&parallel.leaves())
+            .all(|(a, b)| Arc::ptr_eq(a, b)));
+        for (a, b) in sequential.nodes.iter().zip(&parallel.nodes) {
+            assert_eq!((a.offset, a.count, a.axis), (b.offset, b.count, b.axis));
+        }
+    }
+
+    #[test]
+    fn test_refit() {
+        let mut bvh = BVHNode::build(sphere_grid(300, 15, |_| Vector3f::zero()));
+
+        // Small moves keep the tree, with every node bounding what's below it again
+        let moved = sphere_grid(300, 15, |i| vec3(0.0, (i % 4) as f64 * 0.1, 0.0));
+        assert!(!bvh.update(moved.clone()));
+        let contains = |outer: &AABB, inner: &AABB| {
+            (0..3).all(|axis| {
+                outer.min[axis] <= inner.min[axis] && outer.max[axis] >= inner.max[axis]
+            })
+        };
+        for (index, node) in bvh.nodes.iter().enumerate() {
+            if node.count == 0 {
+                assert!(contains(&node.bounds, &bvh.nodes[index + 1].bounds));
+                assert!(contains(
+                    &node.bounds,
+                    &bvh.nodes[node.offset as usize].bounds
+                ));
+            } else {
+                let first = node.offset as usize;
+                for object in &bvh.objects[first..first + node.count as usize] {
+                    assert!(contains(&node.bounds, &object.get_bounds()));
+                }
+            }
+        }
+        let bounds = moved[1..]
+            .iter()
+            .fold(moved[0].get_bounds(), |bounds, object| {
+                bounds.union(&object.get_bounds())
+            });
+        assert_eq!(bvh.get_bounds().min, bounds.min);
+        assert_eq!(bvh.get_bounds().max, bounds.max);
+
+        let rays = ray_fan(Point3f::new(7.0, 5.0, -3.0), 300);
+        assert!(assert_matches_list(&bvh, moved, &rays) > 0);
 
-        None
+        // Shuffling the spheres stretches every node across the whole grid
+        let position = |i: usize| vec3((i % 15) as f64, (i % 7) as f64 * 0.3, (i / 15) as f64);
+        let shuffled = sphere_grid(300, 15, |i| position(i * 7 % 300) - position(i));
+        assert!(bvh.update(shuffled));
+        assert_eq!(bvh.leaves().len(), 300);
     }
 }
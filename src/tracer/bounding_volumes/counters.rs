@@ -0,0 +1,154 @@
+use std::cell::Cell;
+use std::ops::AddAssign;
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static COUNTERS: Cell<TraversalCounters> = const {
+        Cell::new(TraversalCounters {
+            nodes: 0,
+            primitives: 0,
+        })
+    };
+}
+
+/// Work done by the rays traced on a thread: the acceleration structure nodes they visited and
+/// the objects they were tested against.
+///
+/// Counting is off unless the rays are traced within `measure`. Traversals check whether it is
+/// on once when they start, so they pay a branch per node rather than a thread-local update.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct TraversalCounters {
+    pub nodes: u64,
+    pub primitives: u64,
+}
+
+impl TraversalCounters {
+    /// Calls `f` with counting on for the current thread, returning its result and the work
+    /// done by the rays it traced.
+    pub fn measure<T>(f: impl FnOnce() -> T) -> (T, TraversalCounters) {
+        let was_enabled = ENABLED.with(|enabled| enabled.replace(true));
+        let outer = COUNTERS.with(Cell::take);
+
+        let result = f();
+
+        // The work also counts towards an enclosing measurement
+        let counters = COUNTERS.with(Cell::get);
+        let mut total = outer;
+        total += counters;
+        COUNTERS.with(|value| value.set(total));
+        ENABLED.with(|enabled| enabled.set(was_enabled));
+        (result, counters)
+    }
+
+    /// Whether traversals on the current thread should call `count_node` and
+    /// `count_primitive`.
+    #[inline]
+    pub(crate) fn enabled() -> bool {
+        ENABLED.with(Cell::get)
+    }
+
+    #[inline]
+    pub(crate) fn count_node() {
+        COUNTERS.with(|counters| {
+            let mut value = counters.get();
+            value.nodes += 1;
+            counters.set(value);
+        });
+    }
+
+    #[inline]
+    pub(crate) fn count_primitive() {
+        COUNTERS.with(|counters| {
+            let mut value = counters.get();
+            value.primitives += 1;
+            counters.set(value);
+        });
+    }
+
+    #[must_use]
+    pub fn max(self, other: TraversalCounters) -> TraversalCounters {
+        TraversalCounters {
+            nodes: self.nodes.max(other.nodes),
+            primitives: self.primitives.max(other.primitives),
+        }
+    }
+}
+
+impl AddAssign for TraversalCounters {
+    fn add_assign(&mut self, other: TraversalCounters) {
+        self.nodes += other.nodes;
+        self.primitives += other.primitives;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::bounding_volumes::BVHNode;
+    use crate::tracer::geometry::Sphere;
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{
+        Color, Point3f, Ray, SceneIntersectable, SceneObject, SceneObjectList, Vector3f,
+    };
+    use cgmath::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_counters() {
+        // 16 spheres in a row, 10 apart: SAH splits them in halves down to one per leaf, a full
+        // tree of 31 nodes with leaves 4 levels below the root
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let objects: Vec<Arc<dyn SceneObject>> = (0..16)
+            .map(|i| {
+                Arc::new(Sphere {
+                    center: Point3::new(i as f64 * 10.0, 0.0, 0.0),
+                    radius: 1.0,
+                    material: material.clone(),
+                }) as Arc<dyn SceneObject>
+            })
+            .collect();
+        let bvh = BVHNode::build(objects.clone());
+        let stats = bvh.stats();
+        assert_eq!((stats.nodes, stats.leaves, stats.max_depth), (31, 16, 4));
+
+        let count = |ray: &Ray| {
+            let (_, intersect) = TraversalCounters::measure(|| bvh.intersect(ray, 0.001, f64::MAX));
+            let (_, occluded) = TraversalCounters::measure(|| bvh.occluded(ray, 0.001, f64::MAX));
+            (intersect, occluded)
+        };
+        let counters = |nodes, primitives| TraversalCounters { nodes, primitives };
+
+        // Down onto the first sphere: the 5 nodes of its path, then the 4 siblings left on the
+        // stack are rejected, while occlusion stops at the first hit
+        let first = Ray::new(Point3f::new(0.0, 5.0, 0.0), -Vector3f::unit_y());
+        assert_eq!(count(&first), (counters(9, 1), counters(5, 1)));
+
+        // Down onto the last one, which occlusion only reaches after rejecting the 4 first
+        // children on its way
+        let last = Ray::new(Point3f::new(150.0, 5.0, 0.0), -Vector3f::unit_y());
+        assert_eq!(count(&last), (counters(9, 1), counters(9, 1)));
+
+        // Along the whole row: the first hit is closer than all the other nodes
+        let row = Ray::new(Point3f::new(-5.0, 0.0, 0.0), Vector3f::unit_x());
+        assert_eq!(count(&row), (counters(9, 1), counters(5, 1)));
+
+        // Above the row, missing the root
+        let above = Ray::new(Point3f::new(-5.0, 5.0, 0.0), Vector3f::unit_x());
+        assert_eq!(count(&above), (counters(1, 0), counters(1, 0)));
+
+        // Lists test every object, and stop at the first hit for occlusion
+        let list = SceneObjectList { objects };
+        let (_, intersect) = TraversalCounters::measure(|| list.intersect(&last, 0.001, f64::MAX));
+        let (_, occluded) = TraversalCounters::measure(|| list.occluded(&first, 0.001, f64::MAX));
+        assert_eq!((intersect, occluded), (counters(0, 16), counters(0, 1)));
+
+        // Nothing is counted outside of a measurement, and nested ones add up
+        bvh.intersect(&first, 0.001, f64::MAX);
+        let (_, outer) = TraversalCounters::measure(|| {
+            bvh.intersect(&first, 0.001, f64::MAX);
+            let (_, inner) = TraversalCounters::measure(|| bvh.intersect(&last, 0.001, f64::MAX));
+            assert_eq!(inner, counters(9, 1));
+        });
+        assert_eq!(outer, counters(18, 2));
+    }
+}
@@ -0,0 +1,354 @@
+use crate::tracer::bounding_volumes::{Accelerator, TraversalCounters, AABB};
+use crate::tracer::{
+    Ray, SceneIntersectable, SceneIntersection, SceneObject, SceneObjectList, Vector3f,
+};
+use std::sync::Arc;
+
+/// Cells per object, spread along the axes in proportion to the extent of the grid.
+const DENSITY: f64 = 3.0;
+const MAX_RESOLUTION: usize = 64;
+/// Cells referencing more objects than this get a grid of their own.
+const MAX_CELL_OBJECTS: usize = 16;
+const MAX_LEVELS: usize = 3;
+
+/// A grid of cells referencing the objects overlapping them, walked cell by cell along rays.
+///
+/// Cells crowded with objects, as around a detailed model in a large and mostly empty scene,
+/// are split by a nested grid. Unbounded objects are kept aside and tested one by one, as in
+/// `SceneBVH`.
+pub struct Grid {
+    root: Option<GridLevel>,
+    objects: Vec<Arc<dyn SceneObject>>,
+    unbounded: SceneObjectList,
+}
+
+struct GridLevel {
+    bounds: AABB,
+    resolution: [usize; 3],
+    cell_size: Vector3f,
+    cells: Vec<Cell>,
+    indices: Vec<u32>,
+}
+
+enum Cell {
+    Objects { first: u32, count: u32 },
+    Grid(Box<GridLevel>),
+}
+
+impl Grid {
+    pub fn build(objects: Vec<Arc<dyn SceneObject>>) -> Grid {
+        let (objects, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .partition(|object| object.get_bounds().is_finite());
+        let object_bounds: Vec<AABB> = objects.iter().map(|object| object.get_bounds()).collect();
+
+        let root = object_bounds.first().map(|first| {
+            let bounds = object_bounds
+                .iter()
+                .fold(*first, |bounds, other| bounds.union(other));
+            let all: Vec<u32> = (0..objects.len() as u32).collect();
+            GridLevel::build(bounds, &all, &object_bounds, 0)
+        });
+
+        Grid {
+            root,
+            objects,
+            unbounded: SceneObjectList { objects: unbounded },
+        }
+    }
+}
+
+impl GridLevel {
+    fn build(bounds: AABB, objects: &[u32], object_bounds: &[AABB], level: usize) -> GridLevel {
+        let extent = bounds.max - bounds.min;
+        let longest = extent.x.max(extent.y).max(extent.z);
+        // Flat grids still need cells with some thickness
+        let bounds = bounds.padded(longest * 1e-6 + 1e-9);
+        let extent = bounds.max - bounds.min;
+        let longest = extent.x.max(extent.y).max(extent.z);
+
+        let cells_per_unit = (DENSITY * objects.len() as f64).cbrt() / longest;
+        let mut resolution = [1; 3];
+        let mut cell_size = extent;
+        for axis in 0..3 {
+            resolution[axis] =
+                ((extent[axis] * cells_per_unit).round() as usize).clamp(1, MAX_RESOLUTION);
+            cell_size[axis] = extent[axis] / resolution[axis] as f64;
+        }
+
+        let mut grid = GridLevel {
+            bounds,
+            resolution,
+            cell_size,
+            cells: Vec::new(),
+            indices: Vec::new(),
+        };
+
+        let mut cell_objects = vec![Vec::new(); resolution.iter().product()];
+        for &object in objects {
+            let object_bounds = &object_bounds[object as usize];
+            let from = grid.cell_of(object_bounds.min);
+            let to = grid.cell_of(object_bounds.max);
+            for z in from[2]..=to[2] {
+                for y in from[1]..=to[1] {
+                    for x in from[0]..=to[0] {
+                        cell_objects[grid.cell_index([x, y, z])].push(object);
+                    }
+                }
+            }
+        }
+
+        // Nesting only narrows things down when cells are smaller than this grid
+        let nested = cell_objects.len() > 1 && level + 1 < MAX_LEVELS;
+        for (index, objects_in_cell) in cell_objects.iter().enumerate() {
+            let count = objects_in_cell.len();
+            if nested && count > MAX_CELL_OBJECTS {
+                let overlap = objects_in_cell
+                    .iter()
+                    .map(|&object| object_bounds[object as usize])
+                    .fold(None, |bounds: Option<AABB>, other| {
+                        Some(bounds.map_or(other, |bounds| bounds.union(&other)))
+                    })
+                    .unwrap();
+                let cell_bounds = grid.cell_bounds(index).intersection(&overlap);
+                grid.cells.push(Cell::Grid(Box::new(GridLevel::build(
+                    cell_bounds,
+                    objects_in_cell,
+                    object_bounds,
+                    level + 1,
+                ))));
+            } else {
+                grid.cells.push(Cell::Objects {
+                    first: grid.indices.len() as u32,
+                    count: count as u32,
+                });
+                grid.indices.extend_from_slice(objects_in_cell);
+            }
+        }
+
+        grid
+    }
+
+    fn cell_of(&self, point: Vector3f) -> [usize; 3] {
+        let mut cell = [0; 3];
+        for (axis, cell) in cell.iter_mut().enumerate() {
+            let coord = ((point[axis] - self.bounds.min[axis]) / self.cell_size[axis]).floor();
+            *cell = (coord.max(0.0) as usize).min(self.resolution[axis] - 1);
+        }
+        cell
+    }
+
+    fn cell_index(&self, cell: [usize; 3]) -> usize {
+        (cell[2] * self.resolution[1] + cell[1]) * self.resolution[0] + cell[0]
+    }
+
+    fn cell_bounds(&self, index: usize) -> AABB {
+        let x = index % self.resolution[0];
+        let y = index / self.resolution[0] % self.resolution[1];
+        let z = index / (self.resolution[0] * self.resolution[1]);
+
+        let mut min = self.bounds.min;
+        for (axis, &cell) in [x, y, z].iter().enumerate() {
+            min[axis] += cell as f64 * self.cell_size[axis];
+        }
+        AABB::new(min, min + self.cell_size)
+    }
+
+    /// Walks the cells along `ray` front to back, handing their objects to `hit`, which returns
+    /// the distance up to which the ray is still looking for hits. Stops at the first cell
+    /// holding a hit and returns its distance.
+    fn traverse(
+        &self,
+        ray: &Ray,
+        dist_min: f64,
+        dist_max: f64,
+        hit: &mut dyn FnMut(&[u32], f64) -> f64,
+    ) -> f64 {
+        let counting = TraversalCounters::enabled();
+        let (t_enter, t_exit) = match self.bounds.clip_ray(ray, dist_min, dist_max) {
+            Some(range) => range,
+            None => return dist_max,
+        };
+
+        let entry = ray.point_at(t_enter);
+        let mut cell = self.cell_of(Vector3f::new(entry.x, entry.y, entry.z));
+        let mut next = [f64::INFINITY; 3];
+        let mut delta = [f64::INFINITY; 3];
+        let mut step = [0isize; 3];
+        for axis in 0..3 {
+            let direction = ray.direction[axis];
+            let cell_min = self.bounds.min[axis] + cell[axis] as f64 * self.cell_size[axis];
+            if direction > 0.0 {
+                let cell_max = cell_min + self.cell_size[axis];
+                next[axis] = t_enter + (cell_max - entry[axis]) / direction;
+                delta[axis] = self.cell_size[axis] / direction;
+                step[axis] = 1;
+            } else if direction < 0.0 {
+                next[axis] = t_enter + (cell_min - entry[axis]) / direction;
+                delta[axis] = -self.cell_size[axis] / direction;
+                step[axis] = -1;
+            }
+        }
+
+        let mut dist_max = dist_max;
+        loop {
+            if counting {
+                TraversalCounters::count_node();
+            }
+            let axis = if next[0] < next[1] {
+                if next[0] < next[2] {
+                    0
+                } else {
+                    2
+                }
+            } else if next[1] < next[2] {
+                1
+            } else {
+                2
+            };
+            let cell_exit = next[axis].min(t_exit);
+
+            dist_max = match &self.cells[self.cell_index(cell)] {
+                Cell::Objects { first, count } => {
+                    let first = *first as usize;
+                    hit(&self.indices[first..first + *count as usize], dist_max)
+                }
+                Cell::Grid(grid) => grid.traverse(ray, dist_min, dist_max, hit),
+            };
+
+            if dist_max <= cell_exit || next[axis] > t_exit {
+                return dist_max;
+            }
+
+            let coord = cell[axis] as isize + step[axis];
+            if coord < 0 || coord >= self.resolution[axis] as isize {
+                return dist_max;
+            }
+            cell[axis] = coord as usize;
+            next[axis] += delta[axis];
+        }
+    }
+}
+
+impl SceneIntersectable for Grid {
+    fn intersect(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<SceneIntersection> {
+        let counting = TraversalCounters::enabled();
+        let mut closest: Option<SceneIntersection> = None;
+
+        if let Some(root) = &self.root {
+            root.traverse(ray, dist_min, dist_max, &mut |indices, dist_max| {
+                let mut dist_max = dist_max;
+                for &index in indices {
+                    if counting {
+                        TraversalCounters::count_primitive();
+                    }
+                    let object = &self.objects[index as usize];
+                    if let Some(intersection) = object.intersects(ray, dist_min, dist_max) {
+                        dist_max = intersection.dist;
+                        closest = Some(SceneIntersection {
+                            intersection,
+                            object: object.clone(),
+                        });
+                    }
+                }
+                dist_max
+            });
+        }
+
+        let dist_max = closest
+            .as_ref()
+            .map_or(dist_max, |closest| closest.intersection.dist);
+        self.unbounded
+            .intersect(ray, dist_min, dist_max)
+            .or(closest)
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        let counting = TraversalCounters::enabled();
+        let mut occluded = false;
+
+        if let Some(root) = &self.root {
+            root.traverse(ray, dist_min, dist_max, &mut |indices, dist_max| {
+                occluded = indices.iter().any(|&index| {
+                    if counting {
+                        TraversalCounters::count_primitive();
+                    }
+                    self.objects[index as usize].occluded(ray, dist_min, dist_max)
+                });
+                // Any hit will do, so a negative distance stops the traversal
+                if occluded {
+                    -1.0
+                } else {
+                    dist_max
+                }
+            });
+        }
+
+        occluded || self.unbounded.occluded(ray, dist_min, dist_max)
+    }
+
+    fn leaves(&self) -> Vec<Arc<dyn SceneObject>> {
+        let mut leaves = self.objects.clone();
+        leaves.extend(self.unbounded.leaves());
+        leaves
+    }
+
+    fn accelerator(&self) -> Option<Accelerator> {
+        Some(Accelerator::Grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::bounding_volumes::test_fixtures::*;
+    use crate::tracer::Point3f;
+    use cgmath::*;
+
+    #[test]
+    fn test_edge_cases() {
+        let objects = sphere_grid(200, 10, |_| Vector3f::zero());
+        let grid = Grid::build(objects.clone());
+        let root = grid.root.as_ref().unwrap();
+        assert!(root.resolution[0] > 1 && root.resolution[2] > 1);
+        let directions: Vec<Vector3f> = (0..60)
+            .map(|i| {
+                let i = i as f64;
+                vec3((i * 0.37).sin(), (i * 0.71).cos(), (i * 0.13).sin() - 0.3)
+            })
+            .collect();
+
+        // Starting inside cells, in the middle of the grid and in a corner cell
+        let mut rays = Vec::new();
+        for origin in &[Point3f::new(4.5, 0.5, 9.5), Point3f::new(0.0, 0.0, 0.0)] {
+            rays.extend(
+                directions
+                    .iter()
+                    .map(|&direction| Ray::new(*origin, direction)),
+            );
+        }
+
+        // Lying on the boundaries between cells and along their edges, and starting on them
+        let center = Point3f::from_vec((root.bounds.min + root.bounds.max) / 2.0);
+        for axis in 0..3 {
+            for cell in 0..=root.resolution[axis] {
+                let boundary = root.bounds.min[axis] + cell as f64 * root.cell_size[axis];
+                for &direction in &directions {
+                    let mut origin = center;
+                    origin[axis] = boundary;
+                    let mut along = direction;
+                    along[axis] = 0.0;
+                    rays.push(Ray::new(origin, along));
+                    rays.push(Ray::new(origin, direction));
+
+                    let next = (axis + 1) % 3;
+                    origin[next] = root.bounds.min[next] + root.cell_size[next];
+                    let mut edge = Vector3f::zero();
+                    edge[3 - axis - next] = direction.x.signum();
+                    rays.push(Ray::new(origin, edge));
+                }
+            }
+        }
+        assert!(assert_matches_list(&grid, objects, &rays) > rays.len() / 10);
+    }
+}
@@ -0,0 +1,424 @@
+use crate::tracer::bounding_volumes::{Accelerator, TraversalCounters, AABB};
+use crate::tracer::{Ray, SceneIntersectable, SceneIntersection, SceneObject, SceneObjectList};
+use cgmath::*;
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+/// Cost of testing a ray against one object, relative to visiting a node.
+const INTERSECT_COST: f64 = 80.0;
+const TRAVERSAL_COST: f64 = 1.0;
+/// Share of the cost taken off splits leaving one side empty, so that empty space gets cut off.
+const EMPTY_BONUS: f64 = 0.5;
+/// Splits worse than keeping a leaf tolerated on a path before giving up on it.
+const MAX_BAD_REFINES: u32 = 3;
+const MAX_DEPTH: usize = 64;
+
+/// A kd-tree splitting space along the plane with the lowest surface area heuristic cost.
+///
+/// Unlike a BVH, nodes don't overlap and traversal stops at the first leaf holding a hit, but
+/// objects straddling a split are referenced by both sides. Unbounded objects are kept aside and
+/// tested one by one, as in `SceneBVH`.
+pub struct KdTree {
+    nodes: Vec<KdNode>,
+    indices: Vec<u32>,
+    objects: Vec<Arc<dyn SceneObject>>,
+    bounds: AABB,
+    unbounded: SceneObjectList,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum KdNode {
+    /// The objects below `split` are in the next node, the ones above in node `above`.
+    Inner {
+        axis: u8,
+        split: f64,
+        above: u32,
+    },
+    Leaf {
+        first: u32,
+        count: u32,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum EdgeKind {
+    Start,
+    End,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Edge {
+    position: f64,
+    kind: EdgeKind,
+    object: u32,
+}
+
+struct Builder<'a> {
+    object_bounds: &'a [AABB],
+    nodes: Vec<KdNode>,
+    indices: Vec<u32>,
+}
+
+impl KdTree {
+    pub fn build(objects: Vec<Arc<dyn SceneObject>>) -> KdTree {
+        let (objects, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .partition(|object| object.get_bounds().is_finite());
+        let object_bounds: Vec<AABB> = objects.iter().map(|object| object.get_bounds()).collect();
+        let bounds = object_bounds
+            .iter()
+            .skip(1)
+            .fold(object_bounds.first().copied(), |bounds, other| {
+                bounds.map(|bounds| bounds.union(other))
+            });
+
+        let mut builder = Builder {
+            object_bounds: &object_bounds,
+            nodes: Vec::new(),
+            indices: Vec::new(),
+        };
+        if let Some(bounds) = bounds {
+            let max_depth = (8.0 + 1.3 * (objects.len() as f64).log2()).round() as usize;
+            let all: Vec<u32> = (0..objects.len() as u32).collect();
+            builder.build_node(bounds, &all, max_depth.min(MAX_DEPTH), 0);
+        }
+
+        KdTree {
+            nodes: builder.nodes,
+            indices: builder.indices,
+            objects,
+            bounds: bounds.unwrap_or_else(|| AABB::new(Vector3::zero(), Vector3::zero())),
+            unbounded: SceneObjectList { objects: unbounded },
+        }
+    }
+
+    /// Visits the leaves along `ray` front to back, handing their objects to `hit`, which
+    /// returns the distance up to which the ray is still looking for hits. Stops once that
+    /// distance ends before the next leaf.
+    fn traverse(
+        &self,
+        ray: &Ray,
+        dist_min: f64,
+        dist_max: f64,
+        mut hit: impl FnMut(&[u32], f64) -> f64,
+    ) {
+        let counting = TraversalCounters::enabled();
+        let (mut t_min, mut t_max) = match self.nodes.first() {
+            Some(_) => match self.bounds.clip_ray(ray, dist_min, dist_max) {
+                Some(range) => range,
+                None => return,
+            },
+            None => return,
+        };
+
+        let inverse_direction = ray.get_inverse_direction();
+        let mut dist_max = dist_max;
+        let mut stack = [(0usize, 0.0, 0.0); MAX_DEPTH];
+        let mut stack_size = 0;
+        let mut index = 0;
+
+        while dist_max >= t_min {
+            if counting {
+                TraversalCounters::count_node();
+            }
+            match self.nodes[index] {
+                KdNode::Inner { axis, split, above } => {
+                    let axis = axis as usize;
+                    let origin = ray.origin[axis];
+                    let t_plane = (split - origin) * inverse_direction[axis];
+
+                    let below_first =
+                        origin < split || (origin == split && ray.direction[axis] <= 0.0);
+                    let (first, second) = if below_first {
+                        (index + 1, above as usize)
+                    } else {
+                        (above as usize, index + 1)
+                    };
+
+                    if t_plane.is_nan() || t_plane > t_max || t_plane <= 0.0 {
+                        index = first;
+                    } else if t_plane < t_min {
+                        index = second;
+                    } else {
+                        stack[stack_size] = (second, t_plane, t_max);
+                        stack_size += 1;
+                        index = first;
+                        t_max = t_plane;
+                    }
+                    continue;
+                }
+                KdNode::Leaf { first, count } => {
+                    let first = first as usize;
+                    dist_max = hit(&self.indices[first..first + count as usize], dist_max);
+                }
+            }
+
+            if stack_size == 0 {
+                break;
+            }
+            stack_size -= 1;
+            let (next, next_min, next_max) = stack[stack_size];
+            index = next;
+            t_min = next_min;
+            t_max = next_max;
+        }
+    }
+}
+
+impl<'a> Builder<'a> {
+    fn leaf(&mut self, objects: &[u32]) {
+        self.nodes.push(KdNode::Leaf {
+            first: self.indices.len() as u32,
+            count: objects.len() as u32,
+        });
+        self.indices.extend_from_slice(objects);
+    }
+
+    fn build_node(&mut self, bounds: AABB, objects: &[u32], depth: usize, bad_refines: u32) {
+        if objects.len() <= 1 || depth == 0 {
+            return self.leaf(objects);
+        }
+
+        let (axis, split, cost, edges) = match self.find_split(&bounds, objects) {
+            Some(split) => split,
+            None => return self.leaf(objects),
+        };
+
+        let leaf_cost = INTERSECT_COST * objects.len() as f64;
+        let bad_refines = if cost > leaf_cost {
+            bad_refines + 1
+        } else {
+            bad_refines
+        };
+        if (cost > 4.0 * leaf_cost && objects.len() < 16) || bad_refines == MAX_BAD_REFINES {
+            return self.leaf(objects);
+        }
+
+        // Objects starting before the split go below it, the ones ending after it above
+        let (below_edges, above_edges) = edges.split_at(split);
+        let below: Vec<u32> = below_edges
+            .iter()
+            .filter(|edge| edge.kind == EdgeKind::Start)
+            .map(|edge| edge.object)
+            .collect();
+        let above: Vec<u32> = above_edges[1..]
+            .iter()
+            .filter(|edge| edge.kind == EdgeKind::End)
+            .map(|edge| edge.object)
+            .collect();
+        let position = above_edges[0].position;
+
+        let mut below_bounds = bounds;
+        below_bounds.max[axis] = position;
+        let mut above_bounds = bounds;
+        above_bounds.min[axis] = position;
+
+        let index = self.nodes.len();
+        self.nodes.push(KdNode::Inner {
+            axis: axis as u8,
+            split: position,
+            above: 0,
+        });
+        self.build_node(below_bounds, &below, depth - 1, bad_refines);
+        let above_index = self.nodes.len() as u32;
+        if let KdNode::Inner { above, .. } = &mut self.nodes[index] {
+            *above = above_index;
+        }
+        self.build_node(above_bounds, &above, depth - 1, bad_refines);
+    }
+
+    /// The cheapest split of `objects`, trying the longest axis of `bounds` first: its axis, the
+    /// index of its edge among the sorted edges, its cost, and these edges.
+    fn find_split(&self, bounds: &AABB, objects: &[u32]) -> Option<(usize, usize, f64, Vec<Edge>)> {
+        let extent = bounds.max - bounds.min;
+        let total_area = bounds.surface_area();
+        if !total_area.is_finite() || total_area <= 0.0 {
+            return None;
+        }
+
+        let longest = (0..3)
+            .max_by(|&a, &b| extent[a].partial_cmp(&extent[b]).unwrap_or(Ordering::Equal))
+            .unwrap();
+
+        for retry in 0..3 {
+            let axis = (longest + retry) % 3;
+            let mut edges: Vec<Edge> = objects
+                .iter()
+                .flat_map(|&object| {
+                    let object_bounds = &self.object_bounds[object as usize];
+                    [
+                        Edge {
+                            position: object_bounds.min[axis],
+                            kind: EdgeKind::Start,
+                            object,
+                        },
+                        Edge {
+                            position: object_bounds.max[axis],
+                            kind: EdgeKind::End,
+                            object,
+                        },
+                    ]
+                })
+                .collect();
+            edges.sort_by(|a, b| {
+                a.position
+                    .partial_cmp(&b.position)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| (a.kind == EdgeKind::End).cmp(&(b.kind == EdgeKind::End)))
+            });
+
+            let (other_a, other_b) = ((axis + 1) % 3, (axis + 2) % 3);
+            let cap_area = extent[other_a] * extent[other_b];
+            let side_area = extent[other_a] + extent[other_b];
+
+            let mut best: Option<(usize, f64)> = None;
+            let (mut below, mut above) = (0, objects.len());
+            for (i, edge) in edges.iter().enumerate() {
+                if edge.kind == EdgeKind::End {
+                    above -= 1;
+                }
+
+                let position = edge.position;
+                if position > bounds.min[axis] && position < bounds.max[axis] {
+                    let below_area = 2.0 * (cap_area + (position - bounds.min[axis]) * side_area);
+                    let above_area = 2.0 * (cap_area + (bounds.max[axis] - position) * side_area);
+                    let bonus = if below == 0 || above == 0 {
+                        EMPTY_BONUS
+                    } else {
+                        0.0
+                    };
+                    let cost = TRAVERSAL_COST
+                        + INTERSECT_COST
+                            * (1.0 - bonus)
+                            * (below_area * below as f64 + above_area * above as f64)
+                            / total_area;
+                    if best.is_none_or(|(_, best_cost)| cost < best_cost) {
+                        best = Some((i, cost));
+                    }
+                }
+
+                if edge.kind == EdgeKind::Start {
+                    below += 1;
+                }
+            }
+
+            if let Some((split, cost)) = best {
+                return Some((axis, split, cost, edges));
+            }
+        }
+
+        None
+    }
+}
+
+impl SceneIntersectable for KdTree {
+    fn intersect(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<SceneIntersection> {
+        let counting = TraversalCounters::enabled();
+        let mut closest: Option<SceneIntersection> = None;
+
+        self.traverse(ray, dist_min, dist_max, |indices, dist_max| {
+            let mut dist_max = dist_max;
+            for &index in indices {
+                if counting {
+                    TraversalCounters::count_primitive();
+                }
+                let object = &self.objects[index as usize];
+                if let Some(intersection) = object.intersects(ray, dist_min, dist_max) {
+                    dist_max = intersection.dist;
+                    closest = Some(SceneIntersection {
+                        intersection,
+                        object: object.clone(),
+                    });
+                }
+            }
+            dist_max
+        });
+
+        let dist_max = closest
+            .as_ref()
+            .map_or(dist_max, |closest| closest.intersection.dist);
+        self.unbounded
+            .intersect(ray, dist_min, dist_max)
+            .or(closest)
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        let counting = TraversalCounters::enabled();
+        let mut occluded = false;
+
+        self.traverse(ray, dist_min, dist_max, |indices, dist_max| {
+            occluded = indices.iter().any(|&index| {
+                if counting {
+                    TraversalCounters::count_primitive();
+                }
+                self.objects[index as usize].occluded(ray, dist_min, dist_max)
+            });
+            // Any hit will do, so a negative distance stops the traversal
+            if occluded {
+                -1.0
+            } else {
+                dist_max
+            }
+        });
+
+        occluded || self.unbounded.occluded(ray, dist_min, dist_max)
+    }
+
+    fn leaves(&self) -> Vec<Arc<dyn SceneObject>> {
+        let mut leaves = self.objects.clone();
+        leaves.extend(self.unbounded.leaves());
+        leaves
+    }
+
+    fn accelerator(&self) -> Option<Accelerator> {
+        Some(Accelerator::KdTree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::bounding_volumes::test_fixtures::*;
+    use crate::tracer::{Point3f, Vector3f};
+
+    #[test]
+    fn test_edge_cases() {
+        let objects = sphere_grid(200, 10, |_| Vector3f::zero());
+        let tree = KdTree::build(objects.clone());
+        let directions: Vec<Vector3f> = (0..60)
+            .map(|i| {
+                let i = i as f64;
+                vec3((i * 0.37).sin(), (i * 0.71).cos(), (i * 0.13).sin() - 0.3)
+            })
+            .collect();
+
+        // Starting inside the tree, in the leaves around these points
+        let mut rays = Vec::new();
+        for origin in &[Point3f::new(4.5, 0.5, 9.5), Point3f::new(0.0, 0.0, 0.0)] {
+            rays.extend(
+                directions
+                    .iter()
+                    .map(|&direction| Ray::new(*origin, direction)),
+            );
+        }
+
+        // Lying in the split planes, and starting on them
+        let center = Point3f::from_vec((tree.bounds.min + tree.bounds.max) / 2.0);
+        for node in &tree.nodes {
+            if let KdNode::Inner { axis, split, .. } = *node {
+                let axis = axis as usize;
+                for &direction in &directions {
+                    let mut origin = center;
+                    origin[axis] = split;
+                    let mut along = direction;
+                    along[axis] = 0.0;
+                    rays.push(Ray::new(origin, along));
+                    rays.push(Ray::new(origin, direction));
+                    origin[axis] -= direction[axis].signum() * 2.0;
+                    rays.push(Ray::new(origin, direction));
+                }
+            }
+        }
+        assert!(assert_matches_list(&tree, objects, &rays) > rays.len() / 4);
+    }
+}
@@ -1,7 +1,19 @@
 mod aabb;
+mod accelerator;
 mod boundable;
 mod bvh;
+mod counters;
+mod grid;
+mod kd_tree;
+#[cfg(test)]
+mod test_fixtures;
+mod wide_bvh;
 
 pub use aabb::*;
+pub use accelerator::*;
 pub use boundable::*;
 pub use bvh::*;
+pub use counters::*;
+pub use grid::*;
+pub use kd_tree::*;
+pub use wide_bvh::*;
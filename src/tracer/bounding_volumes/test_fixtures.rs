@@ -0,0 +1,78 @@
+//! Objects and rays shared by the tests of the acceleration structures, which check their hits
+//! against testing every object in turn.
+
+use crate::tracer::geometry::Sphere;
+use crate::tracer::material::Lambertian;
+use crate::tracer::{
+    Color, Point3f, Ray, SceneIntersectable, SceneIntersection, SceneObject, SceneObjectList,
+    Vector3f,
+};
+use cgmath::*;
+use std::sync::Arc;
+
+/// `count` spheres of various heights and sizes in rows of `columns` along x, on a grid a unit
+/// apart, each moved by `offset` of its index.
+pub(super) fn sphere_grid(
+    count: usize,
+    columns: usize,
+    offset: impl Fn(usize) -> Vector3f,
+) -> Vec<Arc<dyn SceneObject>> {
+    let material = Arc::new(Lambertian::from_constant(Color::white()));
+    (0..count)
+        .map(|i| {
+            let center = Point3::new(
+                (i % columns) as f64,
+                (i % 7) as f64 * 0.3,
+                (i / columns) as f64,
+            );
+            Arc::new(Sphere {
+                center: center + offset(i),
+                radius: 0.1 + (i % 5) as f64 * 0.1,
+                material: material.clone(),
+            }) as Arc<dyn SceneObject>
+        })
+        .collect()
+}
+
+/// `count` rays from `origin` going down, in rows of 30 fanning out along x, each row leaning
+/// further towards +z.
+pub(super) fn ray_fan(origin: Point3f, count: usize) -> Vec<Ray> {
+    (0..count)
+        .map(|i| {
+            let direction = vec3((i % 30) as f64 * 0.07 - 1.0, -1.0, (i / 30) as f64 * 0.1);
+            Ray::new(origin, direction)
+        })
+        .collect()
+}
+
+pub(super) fn hit_dist(hit: Option<SceneIntersection>) -> Option<f64> {
+    hit.map(|hit| hit.intersection.dist)
+}
+
+/// Checks that `structure` finds the same closest hits and occluded rays among `rays` as testing
+/// each of `objects`, and returns how many of the rays hit something.
+pub(super) fn assert_matches_list(
+    structure: &dyn SceneIntersectable,
+    objects: Vec<Arc<dyn SceneObject>>,
+    rays: &[Ray],
+) -> usize {
+    let list = SceneObjectList { objects };
+    let mut hits = 0;
+    for ray in rays {
+        let expected = hit_dist(list.intersect(ray, 0.001, f64::MAX));
+        hits += expected.is_some() as usize;
+        assert_eq!(
+            hit_dist(structure.intersect(ray, 0.001, f64::MAX)),
+            expected,
+            "{}",
+            ray
+        );
+        assert_eq!(
+            structure.occluded(ray, 0.001, f64::MAX),
+            expected.is_some(),
+            "{}",
+            ray
+        );
+    }
+    hits
+}
@@ -1,10 +1,16 @@
-use crate::tracer::{random_in_unit_sphere, Point3f, Ray, Vector3f};
+use crate::tracer::scene_file::{CameraDescription, SimpleCameraDescription};
+use crate::tracer::{random, random_in_unit_sphere, Point3f, Ray, Vector3f};
 use cgmath::*;
 use std::f64::consts::PI;
 use std::fmt;
 
 pub trait Camera: Sync + Send {
     fn get_ray(&self, u: f64, v: f64) -> Ray;
+
+    /// Scene file description of this camera, `None` when it can't be exported.
+    fn describe(&self) -> Option<CameraDescription> {
+        None
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -18,6 +24,16 @@ pub struct SimpleCamera {
     v: Vector3f,
     w: Vector3f,
     lens_radius: f64,
+    // Rays are cast at random times while the shutter is open
+    shutter_open: f64,
+    shutter_close: f64,
+
+    // Construction parameters, kept to describe the camera
+    look_at: Vector3f,
+    up: Vector3f,
+    vfof: f64,
+    aspect: f64,
+    focus_dist: f64,
 }
 
 impl SimpleCamera {
@@ -58,6 +74,22 @@ impl SimpleCamera {
             v,
             w,
             lens_radius,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            look_at,
+            up,
+            vfof,
+            aspect,
+            focus_dist,
+        }
+    }
+
+    /// Keeps the shutter open from `open` to `close`, blurring objects moving meanwhile.
+    pub fn with_shutter(self, open: f64, close: f64) -> SimpleCamera {
+        SimpleCamera {
+            shutter_open: open,
+            shutter_close: close,
+            ..self
         }
     }
 }
@@ -69,14 +101,34 @@ impl Camera for SimpleCamera {
         let offset_vec = vec3(offset, offset, offset);
 
         let origin = self.origin.to_vec() + offset_vec;
+        let time = if self.shutter_close > self.shutter_open {
+            self.shutter_open + random::<f64>() * (self.shutter_close - self.shutter_open)
+        } else {
+            self.shutter_open
+        };
 
-        Ray::new(
+        Ray::with_time(
             Point3::new(origin.x, origin.y, origin.z),
             self.lower_left_corner + (self.horizontal * u) + (self.vertical * v)
                 - self.origin.to_vec()
                 - offset_vec,
+            time,
         )
     }
+
+    fn describe(&self) -> Option<CameraDescription> {
+        Some(CameraDescription::Simple(SimpleCameraDescription {
+            look_from: self.origin.into(),
+            look_at: self.look_at.into(),
+            up: self.up.into(),
+            vfov: self.vfof,
+            aspect: Some(self.aspect),
+            aperture: self.lens_radius * 2_f64,
+            focus_dist: self.focus_dist,
+            shutter_open: self.shutter_open,
+            shutter_close: self.shutter_close,
+        }))
+    }
 }
 
 impl fmt::Display for SimpleCamera {
@@ -37,6 +37,12 @@ impl Color {
     }
 }
 
+impl From<Color> for [f64; 3] {
+    fn from(c: Color) -> [f64; 3] {
+        [c.red, c.green, c.blue]
+    }
+}
+
 impl Mul<Vector3f> for Color {
     type Output = Color;
 
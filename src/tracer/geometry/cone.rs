@@ -0,0 +1,206 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::geometry::quadric::{intersect_cap, solve_quadratic, sweep_angle, sweep_bounds};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{ConeDescription, MaterialRef, ObjectDescription};
+use crate::tracer::{Intersectable, Intersection, Point3f, Ray, SceneObject};
+use cgmath::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A cone with a base of `radius` on `center` and its apex `height` above it, along the y axis.
+///
+/// Clipping its top makes a frustum. It can be swept by less than a full turn and closed by
+/// caps. The uv coordinates go around the cone and up along it.
+pub struct Cone {
+    center: Point3f,
+    radius: f64,
+    height: f64,
+    y_min: f64,
+    y_max: f64,
+    phi_max: f64,
+    capped: bool,
+    material: Arc<dyn Material>,
+}
+
+impl Cone {
+    pub fn new(center: Point3f, radius: f64, height: f64, material: Arc<dyn Material>) -> Cone {
+        Cone {
+            center,
+            radius,
+            height,
+            y_min: 0.0,
+            y_max: height,
+            phi_max: 2.0 * PI,
+            capped: false,
+            material,
+        }
+    }
+
+    /// Keeps the part of the cone between `y_min` and `y_max` above its base.
+    pub fn clipped(self, y_min: f64, y_max: f64) -> Cone {
+        Cone {
+            y_min,
+            y_max,
+            ..self
+        }
+    }
+
+    /// Sweeps the cone from the +x axis towards +z up to `phi_max` radians.
+    pub fn with_phi_max(self, phi_max: f64) -> Cone {
+        Cone { phi_max, ..self }
+    }
+
+    /// Closes the base of the cone, and its top when clipped.
+    pub fn capped(self) -> Cone {
+        Cone {
+            capped: true,
+            ..self
+        }
+    }
+
+    /// Distance from the axis to the cone surface at height `y`.
+    fn radius_at(&self, y: f64) -> f64 {
+        self.radius * (1.0 - y / self.height)
+    }
+}
+
+impl Intersectable for Cone {
+    fn intersects(&self, ray: &Ray, dist_min: f64, mut dist_max: f64) -> Option<Intersection> {
+        let o = ray.origin - self.center;
+        let d = ray.direction;
+        let mut closest = None;
+
+        // x^2 + z^2 = k * (height - y)^2
+        let k = (self.radius / self.height).powi(2);
+        let h = self.height - o.y;
+        let a = d.x * d.x + d.z * d.z - k * d.y * d.y;
+        let b = 2.0 * (d.x * o.x + d.z * o.z + k * d.y * h);
+        let c = o.x * o.x + o.z * o.z - k * h * h;
+        if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+            for dist in [t0, t1] {
+                if dist <= dist_min || dist >= dist_max {
+                    continue;
+                }
+                let p = o + d * dist;
+                let phi = sweep_angle(p.x, p.z);
+                if p.y < self.y_min || p.y > self.y_max || phi > self.phi_max {
+                    continue;
+                }
+
+                dist_max = dist;
+                closest = Some(Intersection {
+                    dist,
+                    point: ray.point_at(dist),
+                    normal: vec3(p.x, k * (self.height - p.y), p.z).normalize(),
+                    uv: (
+                        phi / self.phi_max,
+                        (p.y - self.y_min) / (self.y_max - self.y_min),
+                    ),
+                    color: None,
+                    tangents: None,
+                });
+                break;
+            }
+        }
+
+        if self.capped {
+            for (y, normal_y) in [(self.y_min, -1.0), (self.y_max, 1.0)] {
+                let radius = self.radius_at(y);
+                if radius <= 0.0 {
+                    continue;
+                }
+                let cap = intersect_cap(o, d, y, radius, self.phi_max, dist_min, dist_max);
+                if let Some((dist, uv)) = cap {
+                    dist_max = dist;
+                    closest = Some(Intersection {
+                        dist,
+                        point: ray.point_at(dist),
+                        normal: vec3(0.0, normal_y, 0.0),
+                        uv,
+                        color: None,
+                        tangents: None,
+                    });
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+impl Boundable for Cone {
+    fn get_bounds(&self) -> AABB {
+        let r_min = if self.capped {
+            0.0
+        } else {
+            self.radius_at(self.y_max)
+        };
+        sweep_bounds(
+            self.center,
+            (r_min, self.radius_at(self.y_min)),
+            self.phi_max,
+            (self.y_min, self.y_max),
+        )
+    }
+}
+
+impl SceneObject for Cone {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Cone(ConeDescription {
+            center: self.center.into(),
+            radius: self.radius,
+            height: self.height,
+            y_min: self.y_min,
+            y_max: Some(self.y_max),
+            phi_max: self.phi_max.to_degrees(),
+            capped: self.capped,
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{Color, Vector3f};
+
+    #[test]
+    fn test_intersects() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let cone = Cone::new(Point3::new(0.0, 0.0, 0.0), 1.0, 1.0, material);
+
+        // Halfway up, the 45 degrees cone has a radius of 0.5
+        let ray = Ray::new(Point3::new(-5.0, 0.5, 0.0), Vector3f::unit_x());
+        let hit = cone.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 4.5).abs() < 1e-12);
+        let expected = vec3(-1.0, 1.0, 0.0).normalize();
+        assert!((hit.normal - expected).magnitude() < 1e-12);
+
+        // Only the cone itself, not its mirror above the apex
+        let ray = Ray::new(Point3::new(-5.0, 1.5, 0.0), Vector3f::unit_x());
+        assert!(cone.intersects(&ray, 0.001, f64::MAX).is_none());
+
+        // A capped frustum
+        let frustum = Cone::new(Point3::new(0.0, 0.0, 0.0), 1.0, 1.0, cone.material.clone())
+            .clipped(0.0, 0.5)
+            .capped();
+        let ray = Ray::new(Point3::new(0.2, 5.0, 0.0), -Vector3f::unit_y());
+        let hit = frustum.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 4.5).abs() < 1e-12);
+        assert_eq!(hit.normal, vec3(0.0, 1.0, 0.0));
+
+        let ray = Ray::new(Point3::new(0.2, -5.0, 0.0), Vector3f::unit_y());
+        let hit = frustum.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 5.0).abs() < 1e-12);
+        assert_eq!(hit.normal, vec3(0.0, -1.0, 0.0));
+
+        let bounds = frustum.get_bounds();
+        assert!((bounds.min - vec3(-1.0, 0.0, -1.0)).magnitude() < 1e-12);
+        assert!((bounds.max - vec3(1.0, 0.5, 1.0)).magnitude() < 1e-12);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::tracer::bounding_volumes::{BVHNode, Boundable, AABB};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{CsgDescription, CsgOperation, MaterialRef, ObjectDescription};
+use crate::tracer::{Intersectable, Intersection, Interval, Point3f, Ray, SceneObject};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+/// A solid combining two closed objects with a boolean `operation`, such as a sphere with a
+/// box carved out of it.
+///
+/// Both objects must enumerate the intervals rays spend inside them, see
+/// `Intersectable::intervals`, which combined objects do too so they can be nested. The
+/// materials of both objects are ignored, the whole solid is made of `material`.
+pub struct Csg {
+    operation: CsgOperation,
+    left: Arc<dyn SceneObject>,
+    right: Arc<dyn SceneObject>,
+    material: Arc<dyn Material>,
+}
+
+impl Csg {
+    pub fn new(
+        operation: CsgOperation,
+        left: Arc<dyn SceneObject>,
+        right: Arc<dyn SceneObject>,
+        material: Arc<dyn Material>,
+    ) -> Csg {
+        Csg {
+            operation,
+            left,
+            right,
+            material,
+        }
+    }
+
+    fn contains(&self, in_left: bool, in_right: bool) -> bool {
+        match self.operation {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// A point where a ray crosses the surface of one of the combined objects.
+struct Crossing {
+    hit: Intersection,
+    from_left: bool,
+    entering: bool,
+}
+
+impl Intersectable for Csg {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        self.intervals(ray)?
+            .into_iter()
+            .flat_map(|interval| vec![interval.entry, interval.exit])
+            .find(|hit| hit.dist > dist_min && hit.dist < dist_max)
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        let crossings = |intervals: Vec<Interval>, from_left: bool| {
+            intervals.into_iter().flat_map(move |interval| {
+                vec![
+                    Crossing {
+                        hit: interval.entry,
+                        from_left,
+                        entering: true,
+                    },
+                    Crossing {
+                        hit: interval.exit,
+                        from_left,
+                        entering: false,
+                    },
+                ]
+            })
+        };
+        let mut crossings: Vec<Crossing> = crossings(self.left.intervals(ray)?, true)
+            .chain(crossings(self.right.intervals(ray)?, false))
+            .collect();
+        // On touching surfaces, entering first merges the solids rather than splitting them
+        crossings.sort_by(|a, b| {
+            a.hit
+                .dist
+                .partial_cmp(&b.hit.dist)
+                .unwrap_or(Ordering::Equal)
+                .then(b.entering.cmp(&a.entering))
+        });
+
+        let (mut in_left, mut in_right) = (false, false);
+        let mut entry: Option<Intersection> = None;
+        let mut intervals = vec![];
+        for Crossing {
+            mut hit,
+            from_left,
+            entering,
+        } in crossings
+        {
+            if from_left {
+                in_left = entering;
+            } else {
+                in_right = entering;
+            }
+            // Carved out surfaces face into the removed object
+            if !from_left && self.operation == CsgOperation::Difference {
+                hit.normal = -hit.normal;
+            }
+
+            let inside = self.contains(in_left, in_right);
+            if inside && entry.is_none() {
+                entry = Some(hit);
+            } else if !inside {
+                if let Some(entry) = entry.take() {
+                    // Skips the empty intervals left between coincident surfaces
+                    if hit.dist > entry.dist {
+                        intervals.push(Interval { entry, exit: hit });
+                    }
+                }
+            }
+        }
+
+        Some(intervals)
+    }
+}
+
+impl Boundable for Csg {
+    fn get_bounds(&self) -> AABB {
+        let left = self.left.get_bounds();
+        match self.operation {
+            CsgOperation::Union => left.union(&self.right.get_bounds()),
+            CsgOperation::Intersection => left.intersection(&self.right.get_bounds()),
+            CsgOperation::Difference => left,
+        }
+    }
+}
+
+impl SceneObject for Csg {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn primitives(&self) -> u64 {
+        self.left.primitives() + self.right.primitives()
+    }
+
+    fn bvhs(&self) -> Vec<&BVHNode> {
+        let mut bvhs = self.left.bvhs();
+        bvhs.extend(self.right.bvhs());
+        bvhs
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Csg(CsgDescription {
+            operation: self.operation,
+            left: Box::new(self.left.describe()?),
+            right: Box::new(self.right.describe()?),
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::geometry::{Cuboid, Sphere};
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{Color, Vector3f};
+    use cgmath::*;
+
+    #[test]
+    fn test_operations() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let sphere: Arc<dyn SceneObject> = Arc::new(Sphere {
+            center: Point3::new(0.0, 0.0, 0.0),
+            radius: 1.0,
+            material: material.clone(),
+        });
+        let cuboid: Arc<dyn SceneObject> = Arc::new(Cuboid::new(
+            Point3::new(0.5, -2.0, -2.0),
+            Point3::new(2.0, 2.0, 2.0),
+            material.clone(),
+        ));
+        let csg = |operation| Csg::new(operation, sphere.clone(), cuboid.clone(), material.clone());
+        let spans = |csg: &Csg, ray: &Ray| -> Vec<(f64, f64)> {
+            csg.intervals(ray)
+                .unwrap()
+                .iter()
+                .map(|interval| (interval.entry.dist, interval.exit.dist))
+                .collect()
+        };
+
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3f::unit_x());
+        assert_eq!(spans(&csg(CsgOperation::Union), &ray), vec![(4.0, 7.0)]);
+        assert_eq!(
+            spans(&csg(CsgOperation::Intersection), &ray),
+            vec![(5.5, 6.0)]
+        );
+        assert_eq!(
+            spans(&csg(CsgOperation::Difference), &ray),
+            vec![(4.0, 5.5)]
+        );
+
+        // Cutting the sphere leaves a flat face, facing away from the box
+        let difference = csg(CsgOperation::Difference);
+        let ray = Ray::new(Point3::new(5.0, 0.0, 0.0), -Vector3f::unit_x());
+        let hit = difference.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 4.5).abs() < 1e-12);
+        assert_eq!(hit.normal, vec3(1.0, 0.0, 0.0));
+
+        // Nothing is left of the sphere beyond the cut
+        let ray = Ray::new(Point3::new(0.75, 5.0, 0.0), -Vector3f::unit_y());
+        assert!(difference.intersects(&ray, 0.001, f64::MAX).is_none());
+        assert_eq!(difference.get_bounds().max.x, 1.0);
+
+        // Combined objects can be combined again
+        let nested = Csg::new(
+            CsgOperation::Union,
+            Arc::new(difference),
+            cuboid.clone(),
+            material.clone(),
+        );
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3f::unit_x());
+        assert_eq!(spans(&nested, &ray), vec![(4.0, 7.0)]);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{BoxDescription, MaterialRef, ObjectDescription};
+use crate::tracer::{
+    Intersectable, Intersection, Interval, Point3f, Ray, SceneObject, Tangents, Vector3f,
+};
+use cgmath::*;
+use std::sync::Arc;
+
+/// An axis-aligned box between the `min` and `max` corners.
+///
+/// Each face has uv coordinates going from 0 to 1 along its two axes. Rotated boxes are
+/// instances of this one.
+pub struct Cuboid {
+    min: Point3f,
+    max: Point3f,
+    material: Arc<dyn Material>,
+}
+
+impl Cuboid {
+    pub fn new(a: Point3f, b: Point3f, material: Arc<dyn Material>) -> Cuboid {
+        Cuboid {
+            min: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+            material,
+        }
+    }
+
+    /// Hit on the face perpendicular to `axis` crossed by the ray at `dist`, with a normal
+    /// pointing out of the box.
+    fn get_intersection(&self, ray: &Ray, dist: f64, axis: usize, outward: f64) -> Intersection {
+        let point = ray.point_at(dist);
+        let mut normal = Vector3f::zero();
+        normal[axis] = outward;
+
+        let face_uv = |axis: usize| {
+            let axis = axis % 3;
+            (point[axis] - self.min[axis]) / (self.max[axis] - self.min[axis])
+        };
+        let face_tangent = |axis: usize| {
+            let axis = axis % 3;
+            let mut tangent = Vector3f::zero();
+            tangent[axis] = self.max[axis] - self.min[axis];
+            tangent
+        };
+        Intersection {
+            dist,
+            point,
+            normal,
+            uv: (face_uv(axis + 1), face_uv(axis + 2)),
+            color: None,
+            tangents: Some(Tangents {
+                dpdu: face_tangent(axis + 1),
+                dpdv: face_tangent(axis + 2),
+            }),
+        }
+    }
+}
+
+impl Cuboid {
+    /// Distances along the ray line where it enters and leaves the box, with the axes of the
+    /// faces crossed there.
+    fn slabs(&self, ray: &Ray) -> Option<((f64, usize), (f64, usize))> {
+        let inverse_direction = ray.get_inverse_direction();
+
+        // Slab test, keeping the axes through which the ray enters and leaves the box
+        let (mut near, mut near_axis) = (f64::NEG_INFINITY, 0);
+        let (mut far, mut far_axis) = (f64::INFINITY, 0);
+        for axis in 0..3 {
+            let t0 = (self.min[axis] - ray.origin[axis]) * inverse_direction[axis];
+            let t1 = (self.max[axis] - ray.origin[axis]) * inverse_direction[axis];
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+            if t0 > near {
+                near = t0;
+                near_axis = axis;
+            }
+            if t1 < far {
+                far = t1;
+                far_axis = axis;
+            }
+        }
+        if near > far {
+            return None;
+        }
+        Some(((near, near_axis), (far, far_axis)))
+    }
+}
+
+impl Intersectable for Cuboid {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        let ((near, near_axis), (far, far_axis)) = self.slabs(ray)?;
+
+        if near > dist_min && near < dist_max {
+            let outward = -ray.direction[near_axis].signum();
+            return Some(self.get_intersection(ray, near, near_axis, outward));
+        }
+        if far > dist_min && far < dist_max {
+            let outward = ray.direction[far_axis].signum();
+            return Some(self.get_intersection(ray, far, far_axis, outward));
+        }
+
+        None
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        match self.slabs(ray) {
+            Some(((near, _), (far, _))) => {
+                (near > dist_min && near < dist_max) || (far > dist_min && far < dist_max)
+            }
+            None => false,
+        }
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        let ((near, near_axis), (far, far_axis)) = match self.slabs(ray) {
+            Some(slabs) => slabs,
+            None => return Some(vec![]),
+        };
+        // Degenerate rays without a direction never cross a face
+        if !near.is_finite() || !far.is_finite() {
+            return Some(vec![]);
+        }
+
+        let entry_outward = -ray.direction[near_axis].signum();
+        let exit_outward = ray.direction[far_axis].signum();
+        Some(vec![Interval {
+            entry: self.get_intersection(ray, near, near_axis, entry_outward),
+            exit: self.get_intersection(ray, far, far_axis, exit_outward),
+        }])
+    }
+}
+
+impl Boundable for Cuboid {
+    fn get_bounds(&self) -> AABB {
+        AABB::new(self.min.to_vec(), self.max.to_vec())
+    }
+}
+
+impl SceneObject for Cuboid {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn primitives(&self) -> u64 {
+        6
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Box(BoxDescription {
+            min: self.min.into(),
+            max: self.max.into(),
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::Color;
+
+    #[test]
+    fn test_intersects() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let cuboid = Cuboid::new(
+            Point3::new(1.0, 1.0, 1.0),
+            Point3::new(-1.0, -1.0, -1.0),
+            material,
+        );
+
+        let ray = Ray::new(Point3::new(0.5, 0.0, -5.0), Vector3f::unit_z());
+        let hit = cuboid.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 4.0).abs() < 1e-12);
+        assert_eq!(hit.normal, vec3(0.0, 0.0, -1.0));
+        assert!((hit.uv.0 - 0.75).abs() < 1e-12 && (hit.uv.1 - 0.5).abs() < 1e-12);
+
+        // From the inside, the ray leaves through the opposite face
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), -Vector3f::unit_x());
+        let hit = cuboid.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 1.0).abs() < 1e-12);
+        assert_eq!(hit.normal, vec3(-1.0, 0.0, 0.0));
+
+        let ray = Ray::new(Point3::new(0.0, 2.0, -5.0), Vector3f::unit_z());
+        assert!(cuboid.intersects(&ray, 0.001, f64::MAX).is_none());
+    }
+}
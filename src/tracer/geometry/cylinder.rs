@@ -0,0 +1,178 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::geometry::quadric::{intersect_cap, solve_quadratic, sweep_angle, sweep_bounds};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{CylinderDescription, MaterialRef, ObjectDescription};
+use crate::tracer::{Intersectable, Intersection, Point3f, Ray, SceneObject};
+use cgmath::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A cylinder of `radius` standing on `center` along the y axis, up to `height`.
+///
+/// It can be swept by less than a full turn, and closed by caps. The uv coordinates go around
+/// the cylinder and up along it.
+pub struct Cylinder {
+    center: Point3f,
+    radius: f64,
+    height: f64,
+    phi_max: f64,
+    capped: bool,
+    material: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    pub fn new(center: Point3f, radius: f64, height: f64, material: Arc<dyn Material>) -> Cylinder {
+        Cylinder {
+            center,
+            radius,
+            height,
+            phi_max: 2.0 * PI,
+            capped: false,
+            material,
+        }
+    }
+
+    /// Sweeps the cylinder from the +x axis towards +z up to `phi_max` radians.
+    pub fn with_phi_max(self, phi_max: f64) -> Cylinder {
+        Cylinder { phi_max, ..self }
+    }
+
+    /// Closes both ends of the cylinder.
+    pub fn capped(self) -> Cylinder {
+        Cylinder {
+            capped: true,
+            ..self
+        }
+    }
+}
+
+impl Intersectable for Cylinder {
+    fn intersects(&self, ray: &Ray, dist_min: f64, mut dist_max: f64) -> Option<Intersection> {
+        let o = ray.origin - self.center;
+        let d = ray.direction;
+        let mut closest = None;
+
+        let a = d.x * d.x + d.z * d.z;
+        let b = 2.0 * (d.x * o.x + d.z * o.z);
+        let c = o.x * o.x + o.z * o.z - self.radius * self.radius;
+        if let Some((t0, t1)) = solve_quadratic(a, b, c).filter(|_| a != 0.0) {
+            for dist in [t0, t1] {
+                if dist <= dist_min || dist >= dist_max {
+                    continue;
+                }
+                let p = o + d * dist;
+                let phi = sweep_angle(p.x, p.z);
+                if p.y < 0.0 || p.y > self.height || phi > self.phi_max {
+                    continue;
+                }
+
+                dist_max = dist;
+                closest = Some(Intersection {
+                    dist,
+                    point: ray.point_at(dist),
+                    normal: vec3(p.x, 0.0, p.z) / self.radius,
+                    uv: (phi / self.phi_max, p.y / self.height),
+                    color: None,
+                    tangents: None,
+                });
+                break;
+            }
+        }
+
+        if self.capped {
+            for (y, normal_y) in [(0.0, -1.0), (self.height, 1.0)] {
+                let cap = intersect_cap(o, d, y, self.radius, self.phi_max, dist_min, dist_max);
+                if let Some((dist, uv)) = cap {
+                    dist_max = dist;
+                    closest = Some(Intersection {
+                        dist,
+                        point: ray.point_at(dist),
+                        normal: vec3(0.0, normal_y, 0.0),
+                        uv,
+                        color: None,
+                        tangents: None,
+                    });
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+impl Boundable for Cylinder {
+    fn get_bounds(&self) -> AABB {
+        let r_min = if self.capped { 0.0 } else { self.radius };
+        sweep_bounds(
+            self.center,
+            (r_min, self.radius),
+            self.phi_max,
+            (0.0, self.height),
+        )
+    }
+}
+
+impl SceneObject for Cylinder {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Cylinder(CylinderDescription {
+            center: self.center.into(),
+            radius: self.radius,
+            height: self.height,
+            phi_max: self.phi_max.to_degrees(),
+            capped: self.capped,
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{Color, Vector3f};
+
+    #[test]
+    fn test_intersects() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let cylinder = Cylinder::new(Point3::new(0.0, 1.0, 0.0), 1.0, 2.0, material);
+
+        let ray = Ray::new(Point3::new(-5.0, 2.0, 0.0), Vector3f::unit_x());
+        let hit = cylinder.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 4.0).abs() < 1e-12);
+        assert!((hit.normal - vec3(-1.0, 0.0, 0.0)).magnitude() < 1e-12);
+        assert!((hit.uv.0 - 0.5).abs() < 1e-12 && (hit.uv.1 - 0.5).abs() < 1e-12);
+
+        // Above the clipped height, and through the open ends
+        let ray = Ray::new(Point3::new(-5.0, 3.5, 0.0), Vector3f::unit_x());
+        assert!(cylinder.intersects(&ray, 0.001, f64::MAX).is_none());
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), -Vector3f::unit_y());
+        assert!(cylinder.intersects(&ray, 0.001, f64::MAX).is_none());
+
+        // Half a cylinder only has its +z side, caps close the ends
+        let half = Cylinder::new(
+            Point3::new(0.0, 1.0, 0.0),
+            1.0,
+            2.0,
+            cylinder.material.clone(),
+        )
+        .with_phi_max(PI)
+        .capped();
+        let ray = Ray::new(Point3::new(0.0, 2.0, -5.0), Vector3f::unit_z());
+        let hit = half.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 6.0).abs() < 1e-12);
+        assert!((hit.normal - vec3(0.0, 0.0, 1.0)).magnitude() < 1e-12);
+
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.5), -Vector3f::unit_y());
+        let hit = half.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 2.0).abs() < 1e-12);
+        assert_eq!(hit.normal, vec3(0.0, 1.0, 0.0));
+
+        let bounds = half.get_bounds();
+        assert!((bounds.min - vec3(-1.0, 1.0, 0.0)).magnitude() < 1e-12);
+        assert!((bounds.max - vec3(1.0, 3.0, 1.0)).magnitude() < 1e-12);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::geometry::quad::FLAT_BOUNDS_PADDING;
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{DiskDescription, MaterialRef, ObjectDescription};
+use crate::tracer::{
+    orthonormal_basis, Intersectable, Intersection, Point3f, Ray, SceneObject, Vector3f,
+};
+use cgmath::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A flat disk of `radius` around `center`, perpendicular to `normal`.
+///
+/// Its u coordinate goes around the disk and v from the center to the rim.
+pub struct Disk {
+    center: Point3f,
+    normal: Vector3f,
+    radius: f64,
+    tangent: Vector3f,
+    bitangent: Vector3f,
+    material: Arc<dyn Material>,
+}
+
+impl Disk {
+    pub fn new(
+        center: Point3f,
+        normal: Vector3f,
+        radius: f64,
+        material: Arc<dyn Material>,
+    ) -> Disk {
+        let normal = normal.normalize();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        Disk {
+            center,
+            normal,
+            radius,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+impl Disk {
+    /// Distance along `ray` where it crosses the disk, if between `dist_min` and `dist_max`,
+    /// with the offset of the hit from the center.
+    fn hit(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<(f64, Vector3f)> {
+        let denom = self.normal.dot(ray.direction);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let dist = self.normal.dot(self.center - ray.origin) / denom;
+        if dist <= dist_min || dist >= dist_max {
+            return None;
+        }
+
+        let offset = ray.point_at(dist) - self.center;
+        if offset.magnitude2() > self.radius * self.radius {
+            return None;
+        }
+        Some((dist, offset))
+    }
+}
+
+impl Intersectable for Disk {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        let (dist, offset) = self.hit(ray, dist_min, dist_max)?;
+        let phi = offset.dot(self.bitangent).atan2(offset.dot(self.tangent));
+        let u = if phi < 0.0 { phi + 2.0 * PI } else { phi } / (2.0 * PI);
+        Some(Intersection {
+            dist,
+            point: ray.point_at(dist),
+            normal: self.normal,
+            uv: (u, offset.magnitude() / self.radius),
+            color: None,
+            tangents: None,
+        })
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.hit(ray, dist_min, dist_max).is_some()
+    }
+}
+
+impl Boundable for Disk {
+    fn get_bounds(&self) -> AABB {
+        // The disk spans `radius * sin(angle between the axis and the normal)` along each axis
+        let extent = |axis: f64| self.radius * (1.0 - axis * axis).max(0.0).sqrt();
+        let extent = vec3(
+            extent(self.normal.x),
+            extent(self.normal.y),
+            extent(self.normal.z),
+        );
+        AABB::new(self.center.to_vec() - extent, self.center.to_vec() + extent)
+            .padded(FLAT_BOUNDS_PADDING)
+    }
+}
+
+impl SceneObject for Disk {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Disk(DiskDescription {
+            center: self.center.into(),
+            normal: self.normal.into(),
+            radius: self.radius,
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
+}
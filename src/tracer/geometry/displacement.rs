@@ -0,0 +1,161 @@
+use crate::tracer::geometry::MeshData;
+use crate::tracer::material::Texture;
+use crate::tracer::{Point3f, Vector3f};
+use cgmath::*;
+use std::collections::HashMap;
+
+impl MeshData {
+    /// This mesh with each triangle split into 4 smaller ones, `levels` times over.
+    ///
+    /// New vertices sit in the middle of the edges, with their attributes averaged from both
+    /// ends, so the shape doesn't change until it's displaced.
+    pub fn subdivided(&self, levels: u32) -> MeshData {
+        let mut mesh = MeshData {
+            positions: self.positions.clone(),
+            normals: self.normals.clone(),
+            uvs: self.uvs.clone(),
+            colors: self.colors.clone(),
+            indices: self.indices.clone(),
+            material: self.material.clone(),
+        };
+
+        for _ in 0..levels {
+            // Vertices shared by both triangles of each edge, keeping the mesh closed
+            let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+            let mut indices = Vec::with_capacity(mesh.indices.len() * 4);
+            let triangles = std::mem::take(&mut mesh.indices);
+            for [a, b, c] in triangles {
+                let mut midpoint = |i: u32, j: u32| {
+                    let key = (i.min(j), i.max(j));
+                    *midpoints
+                        .entry(key)
+                        .or_insert_with(|| mesh.push_midpoint(i as usize, j as usize))
+                };
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                indices.extend_from_slice(&[[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]);
+            }
+            mesh.indices = indices;
+        }
+
+        mesh
+    }
+
+    /// This mesh with every vertex moved along its normal by `scale` times the `height`
+    /// texture, averaged over its channels, and normals recomputed from the moved triangles.
+    ///
+    /// Vertices duplicated along uv seams can move apart and open cracks with uv textures.
+    /// Subdivide meshes first for the displacement to show details smaller than triangles.
+    pub fn displaced(&self, height: &dyn Texture, scale: f64) -> MeshData {
+        let normals = if self.normals.is_empty() {
+            self.smooth_normals()
+        } else {
+            self.normals.clone()
+        };
+
+        let positions = self
+            .positions
+            .iter()
+            .enumerate()
+            .map(|(idx, &position)| {
+                let (u, v) = self.uvs.get(idx).cloned().unwrap_or((0.0, 0.0));
+                let value = height.texture_value(u, v, position);
+                position + normals[idx] * scale * (value.x + value.y + value.z) / 3.0
+            })
+            .collect();
+
+        let mut mesh = MeshData {
+            positions,
+            normals: vec![],
+            uvs: self.uvs.clone(),
+            colors: self.colors.clone(),
+            indices: self.indices.clone(),
+            material: self.material.clone(),
+        };
+        mesh.normals = mesh.smooth_normals();
+        mesh
+    }
+
+    /// Vertex normals averaged from the normals of the triangles around each vertex, weighted
+    /// by their area.
+    pub fn smooth_normals(&self) -> Vec<Vector3f> {
+        let mut normals = vec![Vector3f::zero(); self.positions.len()];
+        for triangle in 0..self.triangle_count() {
+            let [p0, p1, p2] = self.triangle_positions(triangle);
+            let area_normal = (p1 - p0).cross(p2 - p0);
+            for &vertex in &self.indices[triangle] {
+                normals[vertex as usize] += area_normal;
+            }
+        }
+
+        normals
+            .into_iter()
+            .map(|normal| {
+                if normal.magnitude2() > 0.0 {
+                    normal.normalize()
+                } else {
+                    Vector3f::unit_y()
+                }
+            })
+            .collect()
+    }
+
+    /// Adds a vertex between vertices `i` and `j`, returning its index.
+    fn push_midpoint(&mut self, i: usize, j: usize) -> u32 {
+        let middle = |a: Vector3f, b: Vector3f| (a + b) / 2.0;
+        let (pi, pj) = (self.positions[i].to_vec(), self.positions[j].to_vec());
+        self.positions.push(Point3f::from_vec(middle(pi, pj)));
+        if !self.normals.is_empty() {
+            let normal = middle(self.normals[i], self.normals[j]);
+            self.normals.push(if normal.magnitude2() > 0.0 {
+                normal.normalize()
+            } else {
+                self.normals[i]
+            });
+        }
+        if !self.uvs.is_empty() {
+            let (ui, uj) = (self.uvs[i], self.uvs[j]);
+            self.uvs.push(((ui.0 + uj.0) / 2.0, (ui.1 + uj.1) / 2.0));
+        }
+        if !self.colors.is_empty() {
+            self.colors.push(middle(self.colors[i], self.colors[j]));
+        }
+        (self.positions.len() - 1) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::{Lambertian, SolidTexture};
+    use crate::tracer::Color;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_displaced() {
+        // A square of two triangles facing +y
+        let mesh = MeshData {
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 1.0),
+                Point3::new(0.0, 0.0, 1.0),
+            ],
+            normals: vec![],
+            uvs: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            colors: vec![],
+            indices: vec![[0, 2, 1], [0, 3, 2]],
+            material: Arc::new(Lambertian::from_constant(Color::white())),
+        };
+
+        // Edges are split once, the shared diagonal included
+        let subdivided = mesh.subdivided(2);
+        assert_eq!(subdivided.triangle_count(), 32);
+        assert_eq!(subdivided.positions.len(), 25);
+        assert!(subdivided.validate().is_ok());
+
+        let height = SolidTexture::new(Color::new(0.5, 0.5, 0.5));
+        let displaced = subdivided.displaced(&height, 2.0);
+        assert!(displaced.positions.iter().all(|p| p.y == 1.0));
+        assert!(displaced.normals.iter().all(|&n| n == Vector3f::unit_y()));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::tracer::material::noises::PerlinNoise;
+use crate::tracer::scene_file::{
+    HeightMapDescription, ImageHeightMapDescription, NoiseHeightMapDescription,
+};
+use image::ImageResult;
+use std::path::{Path, PathBuf};
+
+/// A grid of heights between 0 and 1, with x varying fastest then z.
+pub struct HeightMap {
+    resolution: [usize; 2],
+    heights: Vec<f32>,
+    source: Option<Source>,
+}
+
+/// Where heights come from, kept to describe the map.
+enum Source {
+    Noise { noise: Box<PerlinNoise>, scale: f64 },
+    Image(PathBuf),
+}
+
+impl HeightMap {
+    /// # Panics
+    ///
+    /// If the grid is less than 2x2 or `heights` doesn't hold exactly one value per sample.
+    pub fn new(resolution: [usize; 2], heights: Vec<f32>) -> HeightMap {
+        assert!(
+            resolution[0] >= 2 && resolution[1] >= 2,
+            "height maps have at least 2x2 samples"
+        );
+        assert_eq!(
+            heights.len(),
+            resolution[0] * resolution[1],
+            "height maps have one height per sample"
+        );
+        HeightMap {
+            resolution,
+            heights,
+            source: None,
+        }
+    }
+
+    /// Samples `noise` over `[0, scale]` along both axes, like the `noise` command images.
+    pub fn from_noise(noise: PerlinNoise, resolution: [usize; 2], scale: f64) -> HeightMap {
+        let [width, depth] = resolution;
+        let mut heights = Vec::with_capacity(width * depth);
+        for z in 0..depth {
+            for x in 0..width {
+                let u = x as f64 / (width - 1).max(1) as f64;
+                let v = z as f64 / (depth - 1).max(1) as f64;
+                let n = noise.noise2(u * scale, v * scale);
+                heights.push((0.5 * (n + 1.0)).clamp(0.0, 1.0) as f32);
+            }
+        }
+        HeightMap {
+            source: Some(Source::Noise {
+                noise: Box::new(noise),
+                scale,
+            }),
+            ..HeightMap::new(resolution, heights)
+        }
+    }
+
+    /// Loads the grayscale of the image file at `path`, one sample per pixel with rows going
+    /// along z. Images smaller than 2x2 can't be loaded.
+    pub fn open(path: &Path) -> ImageResult<HeightMap> {
+        let image = image::open(path)?.to_luma16();
+        let (width, height) = image.dimensions();
+        let resolution = [width as usize, height as usize];
+        if resolution[0] < 2 || resolution[1] < 2 {
+            return Err(image::ImageError::Limits(
+                image::error::LimitError::from_kind(image::error::LimitErrorKind::DimensionError),
+            ));
+        }
+
+        let heights = image
+            .pixels()
+            .map(|pixel| pixel[0] as f32 / u16::MAX as f32)
+            .collect();
+        Ok(HeightMap {
+            source: Some(Source::Image(path.to_path_buf())),
+            ..HeightMap::new(resolution, heights)
+        })
+    }
+
+    pub fn resolution(&self) -> [usize; 2] {
+        self.resolution
+    }
+
+    /// Height of the sample at column `x` and row `z`.
+    pub fn height(&self, x: usize, z: usize) -> f64 {
+        self.heights[z * self.resolution[0] + x] as f64
+    }
+
+    /// Scene file description of this map, `None` when it wasn't loaded from a file or noise.
+    pub fn describe(&self) -> Option<HeightMapDescription> {
+        match self.source.as_ref()? {
+            Source::Noise { noise, scale } => {
+                Some(HeightMapDescription::Noise(NoiseHeightMapDescription {
+                    resolution: self.resolution,
+                    scale: *scale,
+                    octaves: noise.octaves(),
+                    frequency: noise.frequency(),
+                    persistence: noise.persistence(),
+                    lacunarity: noise.lacunarity(),
+                }))
+            }
+            Source::Image(path) => Some(HeightMapDescription::Image(ImageHeightMapDescription {
+                path: path.clone(),
+            })),
+        }
+    }
+}
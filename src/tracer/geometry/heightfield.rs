@@ -0,0 +1,373 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::geometry::quad::FLAT_BOUNDS_PADDING;
+use crate::tracer::geometry::{intersect_triangle, HeightMap};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{HeightfieldDescription, MaterialRef, ObjectDescription};
+use crate::tracer::{Intersectable, Intersection, Point3f, Ray, SceneObject, Vector3f};
+use cgmath::*;
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+/// Terrain stretching a height map over `size`, from its `corner` with the lowest x, y and z.
+/// Heights of 1 rise to `size.y` above the corner.
+///
+/// Each cell between 4 samples is split into two triangles shaded with normals interpolated
+/// from the slopes at the samples. Rays find the cells they cross by going down a quadtree of
+/// the lowest and highest heights over blocks of cells, rather than through a BVH of triangles.
+/// The uv coordinates go from 0 to 1 along x and from 1 to 0 along z, so an image texture
+/// lines up with the image height map it was made for.
+pub struct Heightfield {
+    map: HeightMap,
+    corner: Point3f,
+    size: Vector3f,
+    material: Arc<dyn Material>,
+    // Lowest and highest heights of blocks of 2^n x 2^n cells, from single cells to the whole map
+    levels: Vec<MinMaxLevel>,
+}
+
+struct MinMaxLevel {
+    width: usize,
+    depth: usize,
+    ranges: Vec<(f32, f32)>,
+}
+
+/// A hit with one of the triangles of a cell, between the samples at `corners`.
+struct CellHit {
+    dist: f64,
+    corners: [(usize, usize); 3],
+    barycentric: [f64; 3],
+}
+
+impl MinMaxLevel {
+    fn range(&self, x: usize, z: usize) -> (f32, f32) {
+        self.ranges[z * self.width + x]
+    }
+}
+
+impl Heightfield {
+    pub fn new(
+        map: HeightMap,
+        corner: Point3f,
+        size: Vector3f,
+        material: Arc<dyn Material>,
+    ) -> Heightfield {
+        let levels = Heightfield::build_levels(&map);
+        Heightfield {
+            map,
+            corner,
+            size,
+            material,
+            levels,
+        }
+    }
+
+    fn build_levels(map: &HeightMap) -> Vec<MinMaxLevel> {
+        let [width, depth] = map.resolution();
+        let (width, depth) = (width - 1, depth - 1);
+        let mut ranges = Vec::with_capacity(width * depth);
+        for z in 0..depth {
+            for x in 0..width {
+                let heights = [
+                    map.height(x, z),
+                    map.height(x + 1, z),
+                    map.height(x, z + 1),
+                    map.height(x + 1, z + 1),
+                ];
+                let min = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                ranges.push((min as f32, max as f32));
+            }
+        }
+
+        let mut levels = vec![MinMaxLevel {
+            width,
+            depth,
+            ranges,
+        }];
+        loop {
+            let below = levels.last().unwrap();
+            if below.width == 1 && below.depth == 1 {
+                return levels;
+            }
+
+            let (width, depth) = (below.width.div_ceil(2), below.depth.div_ceil(2));
+            let mut ranges = Vec::with_capacity(width * depth);
+            for z in 0..depth {
+                for x in 0..width {
+                    let mut range = (f32::INFINITY, f32::NEG_INFINITY);
+                    for (child_x, child_z) in Heightfield::children(below, x, z) {
+                        let (min, max) = below.range(child_x, child_z);
+                        range = (range.0.min(min), range.1.max(max));
+                    }
+                    ranges.push(range);
+                }
+            }
+            levels.push(MinMaxLevel {
+                width,
+                depth,
+                ranges,
+            });
+        }
+    }
+
+    /// The blocks of the level `below` making up the block at `x`, `z` of the level above.
+    fn children(below: &MinMaxLevel, x: usize, z: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (width, depth) = (below.width, below.depth);
+        [(0, 0), (1, 0), (0, 1), (1, 1)]
+            .iter()
+            .map(move |(dx, dz)| (2 * x + dx, 2 * z + dz))
+            .filter(move |&(x, z)| x < width && z < depth)
+    }
+
+    fn cell_size(&self) -> (f64, f64) {
+        let [width, depth] = self.map.resolution();
+        (
+            self.size.x / (width - 1) as f64,
+            self.size.z / (depth - 1) as f64,
+        )
+    }
+
+    fn vertex(&self, x: usize, z: usize) -> Point3f {
+        let (dx, dz) = self.cell_size();
+        self.corner
+            + vec3(
+                x as f64 * dx,
+                self.map.height(x, z) * self.size.y,
+                z as f64 * dz,
+            )
+    }
+
+    /// Normal at the sample at `x`, `z`, from the slopes towards its neighbours.
+    fn vertex_normal(&self, x: usize, z: usize) -> Vector3f {
+        let [width, depth] = self.map.resolution();
+        let (dx, dz) = self.cell_size();
+        let slope = |(x0, z0): (usize, usize), (x1, z1): (usize, usize), step: f64| {
+            let rise = (self.map.height(x1, z1) - self.map.height(x0, z0)) * self.size.y;
+            rise / step
+        };
+
+        let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+        let (back, front) = (z.saturating_sub(1), (z + 1).min(depth - 1));
+        let slope_x = slope((left, z), (right, z), (right - left) as f64 * dx);
+        let slope_z = slope((x, back), (x, front), (front - back) as f64 * dz);
+        vec3(-slope_x, 1.0, -slope_z).normalize()
+    }
+
+    /// Samples at the corners of the two triangles of the cell at `x`, `z`.
+    fn cell_triangles(x: usize, z: usize) -> [[(usize, usize); 3]; 2] {
+        [
+            [(x, z), (x + 1, z + 1), (x + 1, z)],
+            [(x, z), (x, z + 1), (x + 1, z + 1)],
+        ]
+    }
+
+    /// Nearest hit with the triangles of the cell at `x`, `z`.
+    fn intersect_cell(
+        &self,
+        ray: &Ray,
+        x: usize,
+        z: usize,
+        dist_min: f64,
+        dist_max: f64,
+    ) -> Option<CellHit> {
+        let mut closest = None;
+        let mut dist_max = dist_max;
+        for corners in &Heightfield::cell_triangles(x, z) {
+            let positions = corners.map(|(x, z)| self.vertex(x, z));
+            if let Some((dist, barycentric)) =
+                intersect_triangle(ray, positions, dist_min, dist_max)
+            {
+                dist_max = dist;
+                closest = Some(CellHit {
+                    dist,
+                    corners: *corners,
+                    barycentric,
+                });
+            }
+        }
+        closest
+    }
+
+    /// World bounds of the block at `x`, `z` of `level`.
+    fn block_bounds(&self, level: usize, x: usize, z: usize) -> AABB {
+        let cells = &self.levels[0];
+        let (dx, dz) = self.cell_size();
+        let (min, max) = self.levels[level].range(x, z);
+        let x_range = (x << level, ((x + 1) << level).min(cells.width));
+        let z_range = (z << level, ((z + 1) << level).min(cells.depth));
+        AABB::new(
+            self.corner.to_vec()
+                + vec3(
+                    x_range.0 as f64 * dx,
+                    min as f64 * self.size.y,
+                    z_range.0 as f64 * dz,
+                ),
+            self.corner.to_vec()
+                + vec3(
+                    x_range.1 as f64 * dx,
+                    max as f64 * self.size.y,
+                    z_range.1 as f64 * dz,
+                ),
+        )
+        .padded(FLAT_BOUNDS_PADDING)
+    }
+
+    /// Hit of `ray` with the cells between `dist_min` and `dist_max`, the nearest one or, with
+    /// `any_hit`, the first one found.
+    fn find_cell(&self, ray: &Ray, dist_min: f64, dist_max: f64, any_hit: bool) -> Option<CellHit> {
+        let top = self.levels.len() - 1;
+        let (near, _) = self
+            .block_bounds(top, 0, 0)
+            .clip_ray(ray, dist_min, dist_max)?;
+
+        // Blocks to visit with the distance where the ray enters them, nearest last
+        let mut stack = vec![(near, top, 0, 0)];
+        let mut closest = None;
+        let mut dist_max = dist_max;
+        while let Some((near, level, x, z)) = stack.pop() {
+            if near > dist_max {
+                continue;
+            }
+
+            if level == 0 {
+                if let Some(hit) = self.intersect_cell(ray, x, z, dist_min, dist_max) {
+                    if any_hit {
+                        return Some(hit);
+                    }
+                    dist_max = hit.dist;
+                    closest = Some(hit);
+                }
+                continue;
+            }
+
+            let mut children: Vec<_> = Heightfield::children(&self.levels[level - 1], x, z)
+                .filter_map(|(x, z)| {
+                    let bounds = self.block_bounds(level - 1, x, z);
+                    let (near, _) = bounds.clip_ray(ray, dist_min, dist_max)?;
+                    Some((near, level - 1, x, z))
+                })
+                .collect();
+            children.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+            stack.extend(children);
+        }
+        closest
+    }
+}
+
+impl Intersectable for Heightfield {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        let CellHit {
+            dist,
+            corners,
+            barycentric,
+        } = self.find_cell(ray, dist_min, dist_max, false)?;
+        let normal = corners
+            .iter()
+            .zip(&barycentric)
+            .map(|(&(x, z), weight)| self.vertex_normal(x, z) * *weight)
+            .sum::<Vector3f>()
+            .normalize();
+        let point = ray.point_at(dist);
+        let local = point - self.corner;
+        Some(Intersection {
+            dist,
+            point,
+            normal,
+            uv: (local.x / self.size.x, 1.0 - local.z / self.size.z),
+            color: None,
+            tangents: None,
+        })
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.find_cell(ray, dist_min, dist_max, true).is_some()
+    }
+}
+
+impl Boundable for Heightfield {
+    fn get_bounds(&self) -> AABB {
+        self.block_bounds(self.levels.len() - 1, 0, 0)
+    }
+}
+
+impl SceneObject for Heightfield {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn primitives(&self) -> u64 {
+        let cells = &self.levels[0];
+        (2 * cells.width * cells.depth) as u64
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Heightfield(HeightfieldDescription {
+            height_map: self.map.describe()?,
+            corner: self.corner.into(),
+            size: self.size.into(),
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::noises::PerlinNoise;
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{random, seed_rng, Color};
+
+    #[test]
+    fn test_intersects() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+
+        // A slope rising along z
+        let map = HeightMap::new([2, 2], vec![0.0, 0.0, 1.0, 1.0]);
+        let slope = Heightfield::new(
+            map,
+            Point3::new(0.0, 0.0, 0.0),
+            vec3(1.0, 1.0, 1.0),
+            material.clone(),
+        );
+        let ray = Ray::new(Point3::new(0.5, 5.0, 0.25), -Vector3f::unit_y());
+        let hit = slope.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 4.75).abs() < 1e-12);
+        assert!((hit.normal - vec3(0.0, 1.0, -1.0).normalize()).magnitude() < 1e-12);
+        assert!((hit.uv.0 - 0.5).abs() < 1e-12 && (hit.uv.1 - 0.75).abs() < 1e-12);
+
+        // Going down the quadtree finds the same hits as testing every cell
+        seed_rng(3);
+        let map = HeightMap::from_noise(PerlinNoise::default(), [13, 9], 3.0);
+        let terrain = Heightfield::new(
+            map,
+            Point3::new(-2.0, 0.0, -1.0),
+            vec3(4.0, 1.0, 2.0),
+            material,
+        );
+        for _ in 0..500 {
+            let origin = Point3::new(
+                4.0 * random::<f64>() - 2.0,
+                2.0,
+                2.0 * random::<f64>() - 1.0,
+            );
+            let direction = vec3(
+                random::<f64>() - 0.5,
+                -random::<f64>(),
+                random::<f64>() - 0.5,
+            );
+            let ray = Ray::new(origin, direction);
+
+            let mut expected = None;
+            for z in 0..8 {
+                for x in 0..12 {
+                    let dist_max = expected.unwrap_or(f64::MAX);
+                    if let Some(hit) = terrain.intersect_cell(&ray, x, z, 0.001, dist_max) {
+                        expected = Some(hit.dist);
+                    }
+                }
+            }
+            let hit = terrain.intersects(&ray, 0.001, f64::MAX);
+            assert_eq!(hit.map(|hit| hit.dist), expected);
+        }
+    }
+}
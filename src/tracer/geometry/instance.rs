@@ -0,0 +1,475 @@
+use crate::tracer::bounding_volumes::{BVHNode, Boundable, AABB};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{
+    InstanceDescription, MatrixDescription, MotionDescription, ObjectDescription, ObjectRef,
+    TransformDescription,
+};
+use crate::tracer::{
+    Intersectable, Intersection, Interval, Point3f, Ray, SceneIntersectable, SceneObject, Tangents,
+};
+use cgmath::*;
+use std::sync::Arc;
+
+/// A scene object placed in the scene through an affine transform.
+///
+/// Instances only hold a reference to their object, so a mesh can be instanced many times
+/// while its geometry is stored once. Instances are the leaves of the top-level BVH of a scene:
+/// they move rays into object space, where they traverse the bottom-level BVH of their object
+/// (see `SceneObject::blas`), built once and shared by all its instances.
+pub struct Instance {
+    object: Arc<dyn SceneObject>,
+    blas: Option<Arc<BVHNode>>,
+    placement: Placement,
+    motion: Option<Motion>,
+    bounds: AABB,
+}
+
+impl Instance {
+    /// Places `object` in the scene with the `transform` from object to world space.
+    ///
+    /// # Panics
+    ///
+    /// If `transform` isn't invertible.
+    pub fn new(object: Arc<dyn SceneObject>, transform: Matrix4<f64>) -> Instance {
+        let placement = Placement::new(transform).expect("instance transforms must be invertible");
+        let bounds = transform_bounds(&object.get_bounds(), &transform);
+
+        Instance {
+            blas: object.blas().cloned(),
+            object,
+            placement,
+            motion: None,
+            bounds,
+        }
+    }
+
+    /// Places `object` with the `start` transform at `time0`, moving to the `end` transform at
+    /// `time1`.
+    ///
+    /// Both transforms are decomposed into a scale, a rotation and a translation which are
+    /// interpolated separately, so the object turns rather than squashes. Shear is dropped.
+    ///
+    /// # Panics
+    ///
+    /// If either transform isn't invertible.
+    pub fn moving(
+        object: Arc<dyn SceneObject>,
+        start: Matrix4<f64>,
+        end: Matrix4<f64>,
+        time0: f64,
+        time1: f64,
+    ) -> Instance {
+        let motion = Motion {
+            start: Decomposed::new(&start),
+            end: Decomposed::new(&end),
+            time0,
+            time1,
+        };
+        let placement =
+            Placement::new(motion.start.matrix()).expect("instance transforms must be invertible");
+        Placement::new(motion.end.matrix()).expect("instance transforms must be invertible");
+        let bounds = motion.bounds(&object.get_bounds());
+
+        Instance {
+            blas: object.blas().cloned(),
+            object,
+            placement,
+            motion: Some(motion),
+            bounds,
+        }
+    }
+
+    pub fn object(&self) -> &Arc<dyn SceneObject> {
+        &self.object
+    }
+
+    /// Transform from object to world space, at the start of the motion for moving instances.
+    pub fn transform(&self) -> &Matrix4<f64> {
+        &self.placement.transform
+    }
+}
+
+impl Instance {
+    /// Calls `f` with the placement of the object at the time of `ray` and the ray in object
+    /// space.
+    fn in_object_space<T>(&self, ray: &Ray, f: impl FnOnce(&Placement, &Ray) -> T) -> Option<T> {
+        let moved;
+        let placement = match &self.motion {
+            Some(motion) => {
+                moved = motion.placement(ray.time)?;
+                &moved
+            }
+            None => &self.placement,
+        };
+
+        // The direction isn't normalized so distances along both rays are the same
+        let object_ray = Ray::with_time(
+            placement.inverse.transform_point(ray.origin),
+            placement.inverse.transform_vector(ray.direction),
+            ray.time,
+        );
+        Some(f(placement, &object_ray))
+    }
+
+    fn object_intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        match &self.blas {
+            Some(blas) => blas
+                .intersect(ray, dist_min, dist_max)
+                .map(|hit| hit.intersection),
+            None => self.object.intersects(ray, dist_min, dist_max),
+        }
+    }
+
+    fn object_occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        match &self.blas {
+            Some(blas) => blas.occluded(ray, dist_min, dist_max),
+            None => self.object.occluded(ray, dist_min, dist_max),
+        }
+    }
+}
+
+impl Intersectable for Instance {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        self.in_object_space(ray, |placement, object_ray| {
+            let hit = self.object_intersects(object_ray, dist_min, dist_max)?;
+            Some(placement.to_world(hit))
+        })?
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.in_object_space(ray, |_, object_ray| {
+            self.object_occluded(object_ray, dist_min, dist_max)
+        })
+        .unwrap_or(false)
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        let intervals = self.in_object_space(ray, |placement, object_ray| {
+            let intervals = self.object.intervals(object_ray)?;
+            let intervals = intervals.into_iter().map(|interval| Interval {
+                entry: placement.to_world(interval.entry),
+                exit: placement.to_world(interval.exit),
+            });
+            Some(intervals.collect())
+        });
+        match intervals {
+            Some(intervals) => intervals,
+            // The object is flattened by a degenerate transform, leaving no volume inside
+            None => self.object.intervals(ray).map(|_| vec![]),
+        }
+    }
+}
+
+impl Boundable for Instance {
+    fn get_bounds(&self) -> AABB {
+        self.bounds
+    }
+}
+
+impl SceneObject for Instance {
+    fn get_material(&self, point: Point3f) -> Box<Arc<dyn Material>> {
+        self.object
+            .get_material(self.placement.inverse.transform_point(point))
+    }
+
+    fn primitives(&self) -> u64 {
+        self.object.primitives()
+    }
+
+    fn bvhs(&self) -> Vec<&BVHNode> {
+        self.object.bvhs()
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        let matrix = |transform: Matrix4<f64>| {
+            let rows: [[f64; 4]; 4] = transform.transpose().into();
+            vec![TransformDescription::Matrix(MatrixDescription { rows })]
+        };
+        Some(ObjectDescription::Instance(InstanceDescription {
+            object: ObjectRef::Inline(Box::new(self.object.describe()?)),
+            transform: matrix(self.placement.transform),
+            motion: self.motion.as_ref().map(|motion| MotionDescription {
+                transform: matrix(motion.end.matrix()),
+                time0: motion.time0,
+                time1: motion.time1,
+            }),
+        }))
+    }
+}
+
+/// An object to world transform with the inverses used to intersect rays.
+struct Placement {
+    transform: Matrix4<f64>,
+    inverse: Matrix4<f64>,
+    // Transforms object space normals to world space
+    normal_transform: Matrix4<f64>,
+}
+
+impl Placement {
+    fn new(transform: Matrix4<f64>) -> Option<Placement> {
+        let inverse = transform.invert()?;
+        Some(Placement {
+            transform,
+            inverse,
+            normal_transform: inverse.transpose(),
+        })
+    }
+
+    /// Moves `hit`, found in object space, to world space.
+    fn to_world(&self, mut hit: Intersection) -> Intersection {
+        hit.point = self.transform.transform_point(hit.point);
+        hit.normal = self
+            .normal_transform
+            .transform_vector(hit.normal)
+            .normalize();
+        hit.tangents = hit.tangents.map(|tangents| Tangents {
+            dpdu: self.transform.transform_vector(tangents.dpdu),
+            dpdv: self.transform.transform_vector(tangents.dpdv),
+        });
+        hit
+    }
+}
+
+/// An affine transform split into its scale, then rotation, then translation.
+#[derive(Copy, Clone, Debug)]
+struct Decomposed {
+    scale: Vector3<f64>,
+    rotation: Quaternion<f64>,
+    translation: Vector3<f64>,
+}
+
+impl Decomposed {
+    fn new(transform: &Matrix4<f64>) -> Decomposed {
+        let linear = Matrix3::from_cols(
+            transform.x.truncate(),
+            transform.y.truncate(),
+            transform.z.truncate(),
+        );
+        let mut scale = vec3(
+            linear.x.magnitude(),
+            linear.y.magnitude(),
+            linear.z.magnitude(),
+        );
+        // A mirroring transform is a rotation with a negative scale
+        if linear.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+        let rotation = if scale.x * scale.y * scale.z == 0.0 {
+            Matrix3::identity()
+        } else {
+            Matrix3::from_cols(linear.x / scale.x, linear.y / scale.y, linear.z / scale.z)
+        };
+
+        Decomposed {
+            scale,
+            rotation: Quaternion::from(rotation).normalize(),
+            translation: transform.w.truncate(),
+        }
+    }
+
+    fn matrix(&self) -> Matrix4<f64> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+
+    fn lerp(&self, other: &Decomposed, t: f64) -> Decomposed {
+        // Both quaternions represent the same rotation, take the shortest way
+        let other_rotation = if self.rotation.dot(other.rotation) < 0.0 {
+            -other.rotation
+        } else {
+            other.rotation
+        };
+        Decomposed {
+            scale: self.scale.lerp(other.scale, t),
+            rotation: self.rotation.slerp(other_rotation, t),
+            translation: self.translation.lerp(other.translation, t),
+        }
+    }
+}
+
+/// The transform of an instance moving between `time0` and `time1`.
+struct Motion {
+    start: Decomposed,
+    end: Decomposed,
+    time0: f64,
+    time1: f64,
+}
+
+impl Motion {
+    // Transforms sampled along the motion to bound it
+    const BOUNDS_STEPS: u32 = 32;
+
+    fn at(&self, time: f64) -> Decomposed {
+        if self.time1 <= self.time0 {
+            return self.start;
+        }
+        let t = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.start.lerp(&self.end, t)
+    }
+
+    fn placement(&self, time: f64) -> Option<Placement> {
+        Placement::new(self.at(time).matrix())
+    }
+
+    /// World space bounds of the object space `bounds` over the whole motion.
+    fn bounds(&self, bounds: &AABB) -> AABB {
+        let steps = Motion::BOUNDS_STEPS;
+        let mut result = transform_bounds(bounds, &self.start.matrix());
+        for step in 1..=steps {
+            let transform = self.start.lerp(&self.end, step as f64 / steps as f64);
+            result = result.union(&transform_bounds(bounds, &transform.matrix()));
+        }
+
+        // Between two samples, points turn by at most `angle / steps` and stray from the
+        // sampled positions by at most the sagitta of that arc.
+        let cos_half_angle = self.start.rotation.dot(self.end.rotation).abs().min(1.0);
+        let angle = 2.0 * cos_half_angle.acos();
+        let max_scale = |scale: Vector3<f64>| scale.x.abs().max(scale.y.abs()).max(scale.z.abs());
+        let farthest = vec3(
+            bounds.min.x.abs().max(bounds.max.x.abs()),
+            bounds.min.y.abs().max(bounds.max.y.abs()),
+            bounds.min.z.abs().max(bounds.max.z.abs()),
+        );
+        let radius =
+            farthest.magnitude() * max_scale(self.start.scale).max(max_scale(self.end.scale));
+        let sagitta = radius * (1.0 - (angle / steps as f64 / 2.0).cos());
+        let padding = vec3(sagitta, sagitta, sagitta);
+        AABB::new(result.min - padding, result.max + padding)
+    }
+}
+
+/// World space bounds of the object space `bounds`, as the bounds of its transformed corners.
+fn transform_bounds(bounds: &AABB, transform: &Matrix4<f64>) -> AABB {
+    if !bounds.is_finite() {
+        return AABB::infinite();
+    }
+
+    let corner = |idx: usize| {
+        let x = if idx & 1 == 0 {
+            bounds.min.x
+        } else {
+            bounds.max.x
+        };
+        let y = if idx & 2 == 0 {
+            bounds.min.y
+        } else {
+            bounds.max.y
+        };
+        let z = if idx & 4 == 0 {
+            bounds.min.z
+        } else {
+            bounds.max.z
+        };
+        transform.transform_point(Point3::new(x, y, z)).to_vec()
+    };
+
+    (1..8).fold(AABB::new(corner(0), corner(0)), |acc, idx| {
+        let p = corner(idx);
+        acc.union(&AABB::new(p, p))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::bounding_volumes::SceneBVH;
+    use crate::tracer::geometry::{MeshData, Sphere, TriangleMesh};
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{Color, Vector3f};
+
+    #[test]
+    fn test_transformed_sphere() {
+        let sphere = Arc::new(Sphere {
+            center: Point3::new(0.0, 0.0, 0.0),
+            radius: 1.0,
+            material: Arc::new(Lambertian::from_constant(Color::white())),
+        });
+        // Stretched along x then moved to x = 10
+        let transform = Matrix4::from_translation(vec3(10.0, 0.0, 0.0))
+            * Matrix4::from_nonuniform_scale(2.0, 1.0, 1.0);
+        let instance = Instance::new(sphere, transform);
+
+        let bounds = instance.get_bounds();
+        assert_eq!(bounds.min, vec3(8.0, -1.0, -1.0));
+        assert_eq!(bounds.max, vec3(12.0, 1.0, 1.0));
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3f::unit_x());
+        let hit = instance.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 8.0).abs() < 1e-9);
+        assert!((hit.point - Point3::new(8.0, 0.0, 0.0)).magnitude() < 1e-9);
+        assert!((hit.normal - vec3(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn test_rotating_instance() {
+        let sphere = Arc::new(Sphere {
+            center: Point3::new(2.0, 0.0, 0.0),
+            radius: 0.5,
+            material: Arc::new(Lambertian::from_constant(Color::white())),
+        });
+        // Half a turn around the y axis, from x = 2 to x = -2 through z = -2
+        let end = Matrix4::from_angle_y(Deg(180.0));
+        let instance = Instance::moving(sphere, Matrix4::identity(), end, 0.0, 1.0);
+
+        let bounds = instance.get_bounds();
+        assert!(bounds.min.x <= -2.5 && bounds.max.x >= 2.5);
+        assert!(bounds.min.z <= -2.5);
+
+        let ray = Ray::with_time(Point3::new(0.0, 0.0, -10.0), Vector3f::unit_z(), 0.5);
+        let hit = instance.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.point - Point3::new(0.0, 0.0, -2.5)).magnitude() < 1e-9);
+        assert!((hit.normal - vec3(0.0, 0.0, -1.0)).magnitude() < 1e-9);
+
+        let ray = Ray::with_time(Point3::new(0.0, 0.0, -10.0), Vector3f::unit_z(), 0.0);
+        assert!(instance.intersects(&ray, 0.001, f64::MAX).is_none());
+    }
+
+    #[test]
+    fn test_shared_blas() {
+        // A unit square facing -z
+        let mesh: Arc<dyn SceneObject> = Arc::new(TriangleMesh::new(MeshData {
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals: vec![],
+            uvs: vec![],
+            colors: vec![],
+            indices: vec![[0, 2, 1], [0, 3, 2]],
+            material: Arc::new(Lambertian::from_constant(Color::white())),
+        }));
+        let near = Arc::new(Instance::new(
+            mesh.clone(),
+            Matrix4::from_translation(vec3(-2.0, 0.0, 1.0)),
+        ));
+        let far = Arc::new(Instance::new(
+            mesh.clone(),
+            Matrix4::from_translation(vec3(2.0, 0.0, 3.0)) * Matrix4::from_scale(2.0),
+        ));
+
+        let blas = mesh.blas().unwrap();
+        assert!(Arc::ptr_eq(near.blas.as_ref().unwrap(), blas));
+        assert!(Arc::ptr_eq(far.blas.as_ref().unwrap(), blas));
+
+        let scene = SceneBVH::build(vec![near.clone(), far.clone()]);
+        let ray = Ray::new(Point3::new(-1.5, 0.5, -1.0), Vector3f::unit_z());
+        let hit = scene.intersect(&ray, 0.001, f64::MAX).unwrap();
+        assert!(Arc::ptr_eq(&hit.object, &(near as Arc<dyn SceneObject>)));
+        assert!((hit.intersection.point - Point3::new(-1.5, 0.5, 1.0)).magnitude() < 1e-9);
+        assert!((hit.intersection.normal - vec3(0.0, 0.0, -1.0)).magnitude() < 1e-9);
+
+        // Only inside the second square once scaled
+        let ray = Ray::new(Point3::new(3.5, 1.5, -1.0), Vector3f::unit_z());
+        let hit = scene.intersect(&ray, 0.001, f64::MAX).unwrap();
+        assert!(Arc::ptr_eq(&hit.object, &(far as Arc<dyn SceneObject>)));
+        assert!((hit.intersection.dist - 4.0).abs() < 1e-9);
+        assert!(scene.occluded(&ray, 0.001, 5.0));
+        assert!(!scene.occluded(&ray, 0.001, 3.0));
+
+        let ray = Ray::new(Point3::new(0.0, 0.5, -1.0), Vector3f::unit_z());
+        assert!(scene.intersect(&ray, 0.001, f64::MAX).is_none());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::tracer::bounding_volumes::{BVHNode, Boundable, AABB};
+use crate::tracer::geometry::Triangle;
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{MaterialRef, MeshDescription, ObjectDescription};
+use crate::tracer::{
+    Intersectable, Intersection, Point3f, Ray, SceneIntersectable, SceneObject, Tangents, Vector3f,
+};
+use cgmath::*;
+use std::sync::Arc;
+
+/// Vertex buffers and triangle indices shared by all the triangles of a mesh.
+///
+/// `normals`, `uvs` and `colors` are either empty or hold one entry per position. Each triangle
+/// indexes the same vertex in all three buffers.
+pub struct MeshData {
+    pub positions: Vec<Point3f>,
+    pub normals: Vec<Vector3f>,
+    pub uvs: Vec<(f64, f64)>,
+    pub colors: Vec<Vector3f>,
+    pub indices: Vec<[u32; 3]>,
+    pub material: Arc<dyn Material>,
+}
+
+impl MeshData {
+    /// Checks that the buffers are consistent, returning a description of the first problem.
+    pub fn validate(&self) -> Result<(), String> {
+        let vertices = self.positions.len();
+        if !self.normals.is_empty() && self.normals.len() != vertices {
+            return Err(format!(
+                "mesh has {} normals for {} vertices",
+                self.normals.len(),
+                vertices
+            ));
+        }
+        if !self.uvs.is_empty() && self.uvs.len() != vertices {
+            return Err(format!(
+                "mesh has {} uvs for {} vertices",
+                self.uvs.len(),
+                vertices
+            ));
+        }
+        if !self.colors.is_empty() && self.colors.len() != vertices {
+            return Err(format!(
+                "mesh has {} colors for {} vertices",
+                self.colors.len(),
+                vertices
+            ));
+        }
+        if self.indices.is_empty() {
+            return Err("mesh has no triangles".to_string());
+        }
+
+        for (idx, triangle) in self.indices.iter().enumerate() {
+            if triangle.iter().any(|&v| v as usize >= vertices) {
+                return Err(format!(
+                    "triangle {} references a vertex out of {} vertices",
+                    idx, vertices
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn triangle_positions(&self, triangle: usize) -> [Point3f; 3] {
+        let [i0, i1, i2] = self.indices[triangle];
+        [
+            self.positions[i0 as usize],
+            self.positions[i1 as usize],
+            self.positions[i2 as usize],
+        ]
+    }
+
+    /// Builds the intersection at barycentric coordinates `(b0, b1, b2)` of a triangle,
+    /// interpolating the vertex normals, uvs and colors when the mesh has them.
+    pub fn get_intersection(&self, triangle: usize, dist: f64, b: [f64; 3]) -> Intersection {
+        let [i0, i1, i2] = self.indices[triangle];
+        let (i0, i1, i2) = (i0 as usize, i1 as usize, i2 as usize);
+        let [p0, p1, p2] = self.triangle_positions(triangle);
+
+        let point = Point3f::from_vec(p0.to_vec() * b[0] + p1.to_vec() * b[1] + p2.to_vec() * b[2]);
+
+        let normal = if self.normals.is_empty() {
+            (p1 - p0).cross(p2 - p0).normalize()
+        } else {
+            (self.normals[i0] * b[0] + self.normals[i1] * b[1] + self.normals[i2] * b[2])
+                .normalize()
+        };
+
+        // Without uvs, same parametrization as a triangle with uvs (0, 0), (1, 0), (1, 1)
+        let (uv0, uv1, uv2) = if self.uvs.is_empty() {
+            ((0.0, 0.0), (1.0, 0.0), (1.0, 1.0))
+        } else {
+            (self.uvs[i0], self.uvs[i1], self.uvs[i2])
+        };
+        let uv = (
+            uv0.0 * b[0] + uv1.0 * b[1] + uv2.0 * b[2],
+            uv0.1 * b[0] + uv1.1 * b[1] + uv2.1 * b[2],
+        );
+
+        // Solves for the derivatives along the edges meeting at the third vertex
+        let (du02, dv02) = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+        let (du12, dv12) = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+        let (dp02, dp12) = (p0 - p2, p1 - p2);
+        let determinant = du02 * dv12 - dv02 * du12;
+        let tangents = if determinant.abs() > 1e-12 {
+            Some(Tangents {
+                dpdu: (dp02 * dv12 - dp12 * dv02) / determinant,
+                dpdv: (dp12 * du02 - dp02 * du12) / determinant,
+            })
+        } else {
+            None
+        };
+
+        let color = if self.colors.is_empty() {
+            None
+        } else {
+            Some(self.colors[i0] * b[0] + self.colors[i1] * b[1] + self.colors[i2] * b[2])
+        };
+
+        Intersection {
+            dist,
+            point,
+            normal,
+            uv,
+            color,
+            tangents,
+        }
+    }
+}
+
+/// An indexed triangle mesh intersected through its own BVH over its triangles.
+///
+/// To mix the triangles with the rest of the scene in a single BVH instead, use
+/// `TriangleMesh::triangles`.
+pub struct TriangleMesh {
+    data: Arc<MeshData>,
+    bvh: Arc<BVHNode>,
+}
+
+impl TriangleMesh {
+    /// # Panics
+    ///
+    /// If `data` isn't valid, see `MeshData::validate`.
+    pub fn new(data: MeshData) -> TriangleMesh {
+        if let Err(error) = data.validate() {
+            panic!("invalid mesh: {}", error);
+        }
+
+        let data = Arc::new(data);
+        let bvh = Arc::new(BVHNode::build(Self::build_triangles(&data)));
+        TriangleMesh { data, bvh }
+    }
+
+    pub fn data(&self) -> &Arc<MeshData> {
+        &self.data
+    }
+
+    /// The mesh triangles as individual scene objects, sharing the mesh buffers.
+    pub fn triangles(&self) -> Vec<Arc<dyn SceneObject>> {
+        Self::build_triangles(&self.data)
+    }
+
+    fn build_triangles(data: &Arc<MeshData>) -> Vec<Arc<dyn SceneObject>> {
+        (0..data.triangle_count())
+            .map(|idx| Arc::new(Triangle::from_mesh(data.clone(), idx)) as Arc<dyn SceneObject>)
+            .collect()
+    }
+}
+
+impl Intersectable for TriangleMesh {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        self.bvh
+            .intersect(ray, dist_min, dist_max)
+            .map(|hit| hit.intersection)
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.bvh.occluded(ray, dist_min, dist_max)
+    }
+}
+
+impl Boundable for TriangleMesh {
+    fn get_bounds(&self) -> AABB {
+        self.bvh.get_bounds()
+    }
+}
+
+impl SceneObject for TriangleMesh {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.data.material.clone())
+    }
+
+    fn primitives(&self) -> u64 {
+        self.data.triangle_count() as u64
+    }
+
+    fn bvhs(&self) -> Vec<&BVHNode> {
+        vec![&self.bvh]
+    }
+
+    fn blas(&self) -> Option<&Arc<BVHNode>> {
+        Some(&self.bvh)
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        let data = &self.data;
+        Some(ObjectDescription::Mesh(MeshDescription {
+            positions: data.positions.iter().map(|&p| p.into()).collect(),
+            normals: data.normals.iter().map(|&n| n.into()).collect(),
+            uvs: data.uvs.iter().map(|&(u, v)| [u, v]).collect(),
+            colors: data.colors.iter().map(|&c| c.into()).collect(),
+            indices: data.indices.clone(),
+            material: MaterialRef::Inline(Box::new(data.material.describe()?)),
+            // Already applied to the positions
+            displacement: None,
+        }))
+    }
+}
@@ -1,3 +1,35 @@
+mod cone;
+mod csg;
+mod cuboid;
+mod cylinder;
+mod disk;
+mod displacement;
+mod height_map;
+mod heightfield;
+mod instance;
+mod mesh;
+mod moving_sphere;
+mod paraboloid;
+mod plane;
+mod quad;
+mod quadric;
 mod sphere;
+mod torus;
+mod triangle;
 
+pub use cone::*;
+pub use csg::*;
+pub use cuboid::*;
+pub use cylinder::*;
+pub use disk::*;
+pub use height_map::*;
+pub use heightfield::*;
+pub use instance::*;
+pub use mesh::*;
+pub use moving_sphere::*;
+pub use paraboloid::*;
+pub use plane::*;
+pub use quad::*;
 pub use sphere::*;
+pub use torus::*;
+pub use triangle::*;
@@ -0,0 +1,141 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::geometry::sphere::{sphere_tangents, sphere_uv};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{MaterialRef, MovingSphereDescription, ObjectDescription};
+use crate::tracer::{Intersectable, Intersection, Point3f, Ray, SceneObject};
+use cgmath::*;
+use std::sync::Arc;
+
+/// A sphere moving in a straight line from `center0` at `time0` to `center1` at `time1`.
+///
+/// It stays still before `time0` and after `time1`.
+pub struct MovingSphere {
+    pub center0: Point3f,
+    pub center1: Point3f,
+    pub time0: f64,
+    pub time1: f64,
+    pub radius: f64,
+    pub material: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn center(&self, time: f64) -> Point3f {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+        let t = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.center0 + (self.center1 - self.center0) * t
+    }
+
+    fn get_intersection(&self, ray: &Ray, center: Point3f, dist: f64) -> Intersection {
+        let point = ray.point_at(dist);
+        let normal = (point - center) / self.radius;
+
+        Intersection {
+            dist,
+            point,
+            normal,
+            uv: sphere_uv(normal),
+            color: None,
+            tangents: Some(sphere_tangents(point - center)),
+        }
+    }
+}
+
+impl Intersectable for MovingSphere {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        let center = self.center(ray.time);
+        self.hit_dist(ray, center, dist_min, dist_max)
+            .map(|t| self.get_intersection(ray, center, t))
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.hit_dist(ray, self.center(ray.time), dist_min, dist_max)
+            .is_some()
+    }
+}
+
+impl MovingSphere {
+    /// Distance to the first hit of `ray` between `dist_min` and `dist_max`, with the sphere
+    /// at `center`.
+    fn hit_dist(&self, ray: &Ray, center: Point3f, dist_min: f64, dist_max: f64) -> Option<f64> {
+        let oc = ray.origin - center;
+        let a = ray.direction.dot(ray.direction);
+        let b = oc.dot(ray.direction);
+        let c = oc.dot(oc) - self.radius * self.radius;
+        let discriminant = (b * b) - (a * c);
+        if discriminant < 0f64 {
+            return None;
+        }
+
+        let t = (-b - discriminant.sqrt()) / a;
+        if t < dist_max && t > dist_min {
+            return Some(t);
+        }
+
+        let t = (-b + discriminant.sqrt()) / a;
+        if t < dist_max && t > dist_min {
+            return Some(t);
+        }
+
+        None
+    }
+}
+
+impl SceneObject for MovingSphere {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::MovingSphere(MovingSphereDescription {
+            center0: self.center0.into(),
+            center1: self.center1.into(),
+            time0: self.time0,
+            time1: self.time1,
+            radius: self.radius,
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
+}
+
+impl Boundable for MovingSphere {
+    /// Bounds of the sphere over its whole motion from `time0` to `time1`.
+    fn get_bounds(&self) -> AABB {
+        let radius = vec3(self.radius, self.radius, self.radius);
+        let bounds =
+            |center: Point3f| AABB::new(center.to_vec() - radius, center.to_vec() + radius);
+        bounds(self.center0).union(&bounds(self.center1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{Color, Vector3f};
+
+    #[test]
+    fn test_motion() {
+        let sphere = MovingSphere {
+            center0: Point3::new(0.0, 0.0, 0.0),
+            center1: Point3::new(0.0, 4.0, 0.0),
+            time0: 0.0,
+            time1: 1.0,
+            radius: 1.0,
+            material: Arc::new(Lambertian::from_constant(Color::white())),
+        };
+
+        let bounds = sphere.get_bounds();
+        assert_eq!(bounds.min, vec3(-1.0, -1.0, -1.0));
+        assert_eq!(bounds.max, vec3(1.0, 5.0, 1.0));
+
+        // Halfway through, the sphere is centered on y = 2
+        let ray = Ray::with_time(Point3::new(-10.0, 2.0, 0.0), Vector3f::unit_x(), 0.5);
+        let hit = sphere.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 9.0).abs() < 1e-9);
+
+        let ray = Ray::with_time(Point3::new(-10.0, 2.0, 0.0), Vector3f::unit_x(), 0.0);
+        assert!(sphere.intersects(&ray, 0.001, f64::MAX).is_none());
+    }
+}
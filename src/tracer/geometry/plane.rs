@@ -0,0 +1,88 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{MaterialRef, ObjectDescription, PlaneDescription};
+use crate::tracer::{
+    orthonormal_basis, Intersectable, Intersection, Point3f, Ray, SceneObject, Vector3f,
+};
+use cgmath::*;
+use std::sync::Arc;
+
+/// An infinite plane through `point`, perpendicular to `normal`.
+///
+/// Its uv coordinates are distances along two axes of the plane, so textures repeat every unit.
+/// Planes have infinite bounds and are kept out of the BVH.
+pub struct Plane {
+    point: Point3f,
+    normal: Vector3f,
+    tangent: Vector3f,
+    bitangent: Vector3f,
+    material: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Point3f, normal: Vector3f, material: Arc<dyn Material>) -> Plane {
+        let normal = normal.normalize();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        Plane {
+            point,
+            normal,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+
+    /// Distance along `ray` where it crosses the plane, if between `dist_min` and `dist_max`.
+    fn hit_dist(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<f64> {
+        let denom = self.normal.dot(ray.direction);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let dist = self.normal.dot(self.point - ray.origin) / denom;
+        if dist <= dist_min || dist >= dist_max {
+            return None;
+        }
+        Some(dist)
+    }
+}
+
+impl Intersectable for Plane {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        let dist = self.hit_dist(ray, dist_min, dist_max)?;
+        let point = ray.point_at(dist);
+        let offset = point - self.point;
+        Some(Intersection {
+            dist,
+            point,
+            normal: self.normal,
+            uv: (offset.dot(self.tangent), offset.dot(self.bitangent)),
+            color: None,
+            tangents: None,
+        })
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.hit_dist(ray, dist_min, dist_max).is_some()
+    }
+}
+
+impl Boundable for Plane {
+    fn get_bounds(&self) -> AABB {
+        AABB::infinite()
+    }
+}
+
+impl SceneObject for Plane {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Plane(PlaneDescription {
+            point: self.point.into(),
+            normal: self.normal.into(),
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
+}
@@ -0,0 +1,170 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{MaterialRef, ObjectDescription, QuadDescription};
+use crate::tracer::{Intersectable, Intersection, Point3f, Ray, SceneObject, Tangents, Vector3f};
+use cgmath::*;
+use std::sync::Arc;
+
+/// Thickness given to the bounds of flat objects
+pub(super) const FLAT_BOUNDS_PADDING: f64 = 1e-4;
+
+/// A parallelogram with a `corner` and two edges `u` and `v` leaving from it.
+///
+/// Axis-aligned rectangles are quads with edges along the axes. The uv coordinates go from 0
+/// to 1 along both edges, and the normal is the direction of `u` x `v`.
+pub struct Quad {
+    corner: Point3f,
+    u: Vector3f,
+    v: Vector3f,
+    normal: Vector3f,
+    // Scaled normal projecting hit points onto the edges
+    w: Vector3f,
+    material: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(corner: Point3f, u: Vector3f, v: Vector3f, material: Arc<dyn Material>) -> Quad {
+        let n = u.cross(v);
+        Quad {
+            corner,
+            u,
+            v,
+            normal: n.normalize(),
+            w: n / n.magnitude2(),
+            material,
+        }
+    }
+
+    /// A rectangle in the plane `z = k`, facing +z.
+    pub fn xy_rect(x: (f64, f64), y: (f64, f64), k: f64, material: Arc<dyn Material>) -> Quad {
+        Quad::new(
+            Point3::new(x.0, y.0, k),
+            vec3(x.1 - x.0, 0.0, 0.0),
+            vec3(0.0, y.1 - y.0, 0.0),
+            material,
+        )
+    }
+
+    /// A rectangle in the plane `y = k`, facing +y.
+    pub fn xz_rect(x: (f64, f64), z: (f64, f64), k: f64, material: Arc<dyn Material>) -> Quad {
+        Quad::new(
+            Point3::new(x.0, k, z.0),
+            vec3(0.0, 0.0, z.1 - z.0),
+            vec3(x.1 - x.0, 0.0, 0.0),
+            material,
+        )
+    }
+
+    /// A rectangle in the plane `x = k`, facing +x.
+    pub fn yz_rect(y: (f64, f64), z: (f64, f64), k: f64, material: Arc<dyn Material>) -> Quad {
+        Quad::new(
+            Point3::new(k, y.0, z.0),
+            vec3(0.0, y.1 - y.0, 0.0),
+            vec3(0.0, 0.0, z.1 - z.0),
+            material,
+        )
+    }
+}
+
+impl Quad {
+    /// Distance along `ray` where it crosses the quad, if between `dist_min` and `dist_max`,
+    /// with the uv coordinates of the hit.
+    fn hit(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<(f64, (f64, f64))> {
+        let denom = self.normal.dot(ray.direction);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let dist = self.normal.dot(self.corner - ray.origin) / denom;
+        if dist <= dist_min || dist >= dist_max {
+            return None;
+        }
+
+        let point = ray.point_at(dist);
+        let offset = point - self.corner;
+        let alpha = self.w.dot(offset.cross(self.v));
+        let beta = self.w.dot(self.u.cross(offset));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        Some((dist, (alpha, beta)))
+    }
+}
+
+impl Intersectable for Quad {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        let (dist, uv) = self.hit(ray, dist_min, dist_max)?;
+        Some(Intersection {
+            dist,
+            point: ray.point_at(dist),
+            normal: self.normal,
+            uv,
+            color: None,
+            tangents: Some(Tangents {
+                dpdu: self.u,
+                dpdv: self.v,
+            }),
+        })
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.hit(ray, dist_min, dist_max).is_some()
+    }
+}
+
+impl Boundable for Quad {
+    fn get_bounds(&self) -> AABB {
+        let corners = [
+            self.corner + self.u,
+            self.corner + self.v,
+            self.corner + self.u + self.v,
+        ];
+        let start = self.corner.to_vec();
+        corners
+            .iter()
+            .fold(AABB::new(start, start), |acc, p| {
+                acc.union(&AABB::new(p.to_vec(), p.to_vec()))
+            })
+            .padded(FLAT_BOUNDS_PADDING)
+    }
+}
+
+impl SceneObject for Quad {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Quad(QuadDescription {
+            corner: self.corner.into(),
+            u: self.u.into(),
+            v: self.v.into(),
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::Color;
+
+    #[test]
+    fn test_intersects() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let quad = Quad::xz_rect((0.0, 2.0), (0.0, 4.0), 1.0, material);
+        assert!((quad.normal - Vector3f::unit_y()).magnitude() < 1e-12);
+
+        let ray = Ray::new(Point3::new(1.0, 5.0, 1.0), -Vector3f::unit_y());
+        let hit = quad.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 4.0).abs() < 1e-12);
+        assert!((hit.uv.0 - 0.25).abs() < 1e-12 && (hit.uv.1 - 0.5).abs() < 1e-12);
+
+        let ray = Ray::new(Point3::new(3.0, 5.0, 1.0), -Vector3f::unit_y());
+        assert!(quad.intersects(&ray, 0.001, f64::MAX).is_none());
+
+        let bounds = quad.get_bounds();
+        assert!(bounds.max.y - bounds.min.y > 0.0);
+    }
+}
@@ -0,0 +1,116 @@
+//! Helpers shared by the quadrics, which are all built around the y axis going through their
+//! `center` and swept by an angle `phi` from the +x axis towards +z.
+
+use crate::tracer::bounding_volumes::AABB;
+use crate::tracer::{Point3f, Vector3f};
+use cgmath::*;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+/// Real roots of `a * t^2 + b * t + c`, smallest first, computed without catastrophic
+/// cancellation. A zero `a` gives the root of the linear equation twice.
+pub(super) fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a == 0.0 {
+        if b == 0.0 {
+            return None;
+        }
+        let t = -c / b;
+        return Some((t, t));
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (t0, t1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    Some(if t0 < t1 { (t0, t1) } else { (t1, t0) })
+}
+
+/// Angle of the point `(x, z)` around the y axis, between 0 and 2 pi.
+pub(super) fn sweep_angle(x: f64, z: f64) -> f64 {
+    let phi = z.atan2(x);
+    if phi < 0.0 {
+        phi + 2.0 * PI
+    } else {
+        phi
+    }
+}
+
+/// Bounds of the points swept around the y axis of `center` up to `phi_max`, at distances
+/// from the axis between `r_min` and `r_max` and heights between `y_min` and `y_max`.
+pub(super) fn sweep_bounds(
+    center: Point3f,
+    (r_min, r_max): (f64, f64),
+    phi_max: f64,
+    (y_min, y_max): (f64, f64),
+) -> AABB {
+    // Extreme points of an arc are at its ends or where it crosses an axis
+    let mut angles = vec![0.0, phi_max];
+    angles.extend(
+        (1..4)
+            .map(|quarter| quarter as f64 * FRAC_PI_2)
+            .filter(|&phi| phi < phi_max),
+    );
+
+    let mut min = vec3(f64::INFINITY, y_min, f64::INFINITY);
+    let mut max = vec3(f64::NEG_INFINITY, y_max, f64::NEG_INFINITY);
+    for radius in [r_min, r_max] {
+        for phi in angles.iter() {
+            let (x, z) = (radius * phi.cos(), radius * phi.sin());
+            min.x = min.x.min(x);
+            min.z = min.z.min(z);
+            max.x = max.x.max(x);
+            max.z = max.z.max(z);
+        }
+    }
+
+    AABB::new(center.to_vec() + min, center.to_vec() + max)
+}
+
+/// Hit of the ray going from `origin` along `direction`, both relative to the quadric center,
+/// with the flat cap at height `y` covering the sweep up to `radius`.
+///
+/// Returns the distance along the ray and the cap uv coordinates.
+pub(super) fn intersect_cap(
+    origin: Vector3f,
+    direction: Vector3f,
+    y: f64,
+    radius: f64,
+    phi_max: f64,
+    dist_min: f64,
+    dist_max: f64,
+) -> Option<(f64, (f64, f64))> {
+    if direction.y == 0.0 {
+        return None;
+    }
+
+    let dist = (y - origin.y) / direction.y;
+    if dist <= dist_min || dist >= dist_max {
+        return None;
+    }
+
+    let p = origin + direction * dist;
+    let distance2 = p.x * p.x + p.z * p.z;
+    if distance2 > radius * radius {
+        return None;
+    }
+    let phi = sweep_angle(p.x, p.z);
+    if phi > phi_max {
+        return None;
+    }
+
+    Some((dist, (phi / phi_max, distance2.sqrt() / radius)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sweep_bounds() {
+        let bounds = sweep_bounds(Point3::new(0.0, 0.0, 0.0), (1.0, 2.0), PI, (0.0, 1.0));
+        assert!((bounds.min - vec3(-2.0, 0.0, 0.0)).magnitude() < 1e-12);
+        assert!((bounds.max - vec3(2.0, 1.0, 2.0)).magnitude() < 1e-12);
+    }
+}
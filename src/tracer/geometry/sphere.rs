@@ -1,6 +1,9 @@
 use crate::tracer::bounding_volumes::{Boundable, AABB};
 use crate::tracer::material::Material;
-use crate::tracer::{Intersectable, Intersection, Point3f, Ray, SceneObject, Vector3f};
+use crate::tracer::scene_file::{MaterialRef, ObjectDescription, SphereDescription};
+use crate::tracer::{
+    Intersectable, Intersection, Interval, Point3f, Ray, SceneObject, Tangents, Vector3f,
+};
 use cgmath::*;
 use std::f64::consts::{FRAC_PI_2, PI};
 use std::sync::Arc;
@@ -14,54 +17,101 @@ pub struct Sphere {
 }
 
 impl Sphere {
+    /// Distance to the first hit of `ray` between `dist_min` and `dist_max`.
+    fn hit_dist(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<f64> {
+        let oc = ray.origin - self.center;
+        let a = ray.direction.dot(ray.direction);
+        let b = oc.dot(ray.direction);
+        let c = oc.dot(oc) - self.radius * self.radius;
+        let discriminant = (b * b) - (a * c);
+        if discriminant < 0f64 {
+            return None;
+        }
+
+        let t = (-b - discriminant.sqrt()) / a;
+        if t < dist_max && t > dist_min {
+            return Some(t);
+        }
+
+        let t = (-b + discriminant.sqrt()) / a;
+        if t < dist_max && t > dist_min {
+            return Some(t);
+        }
+
+        None
+    }
+
     fn get_intersection(&self, ray: &Ray, dist: f64) -> Intersection {
         let point = ray.point_at(dist);
         let normal = (point.to_vec() - self.center.to_vec()) / self.radius;
 
-        let uv = self.get_uv(normal);
+        let uv = sphere_uv(normal);
         Intersection {
             dist,
             point,
             normal,
             uv,
+            color: None,
+            tangents: Some(sphere_tangents(point - self.center)),
         }
     }
+}
 
-    fn get_uv(&self, normal: Vector3f) -> (f64, f64) {
-        //        float phi = atan2(p.z(), p.x());
-        //        float theta = asin(p.y());
-        //        u = 1-(phi + M_PI) / (2*M_PI);
-        //        v = (theta + M_PI/2) / M_PI;
-        let phi = normal.z.atan2(normal.x);
-        let theta = normal.y.asin();
-        let u = 1.0 - ((phi + PI) / (TWO_PI));
-        let v = (theta + FRAC_PI_2) / PI;
-        (u, v)
-    }
+/// Spherical uv coordinates of the point of a unit sphere with the given `normal`.
+pub(super) fn sphere_uv(normal: Vector3f) -> (f64, f64) {
+    //        float phi = atan2(p.z(), p.x());
+    //        float theta = asin(p.y());
+    //        u = 1-(phi + M_PI) / (2*M_PI);
+    //        v = (theta + M_PI/2) / M_PI;
+    let phi = normal.z.atan2(normal.x);
+    let theta = normal.y.asin();
+    let u = 1.0 - ((phi + PI) / (TWO_PI));
+    let v = (theta + FRAC_PI_2) / PI;
+    (u, v)
+}
+
+/// Derivatives of the `sphere_uv` coordinates at the `offset` from the center of a sphere.
+pub(super) fn sphere_tangents(offset: Vector3f) -> Tangents {
+    // u goes around the y axis and v from the bottom pole to the top one
+    let ring_radius = (offset.x * offset.x + offset.z * offset.z).sqrt();
+    let dpdu = vec3(offset.z, 0.0, -offset.x) * TWO_PI;
+    let dpdv = if ring_radius > 0.0 {
+        vec3(
+            -offset.y * offset.x / ring_radius,
+            ring_radius,
+            -offset.y * offset.z / ring_radius,
+        ) * PI
+    } else {
+        // At the poles, where u isn't defined
+        vec3(0.0, 0.0, 0.0)
+    };
+    Tangents { dpdu, dpdv }
 }
 
 impl Intersectable for Sphere {
     fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        self.hit_dist(ray, dist_min, dist_max)
+            .map(|t| self.get_intersection(ray, t))
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.hit_dist(ray, dist_min, dist_max).is_some()
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
         let oc = ray.origin - self.center;
         let a = ray.direction.dot(ray.direction);
         let b = oc.dot(ray.direction);
         let c = oc.dot(oc) - self.radius * self.radius;
         let discriminant = (b * b) - (a * c);
         if discriminant < 0f64 {
-            return None;
-        }
-
-        let t = (-b - discriminant.sqrt()) / a;
-        if t < dist_max && t > dist_min {
-            return Some(self.get_intersection(ray, t));
-        }
-
-        let t = (-b + discriminant.sqrt()) / a;
-        if t < dist_max && t > dist_min {
-            return Some(self.get_intersection(ray, t));
+            return Some(vec![]);
         }
 
-        None
+        Some(vec![Interval {
+            entry: self.get_intersection(ray, (-b - discriminant.sqrt()) / a),
+            exit: self.get_intersection(ray, (-b + discriminant.sqrt()) / a),
+        }])
     }
 }
 
@@ -69,6 +119,14 @@ impl SceneObject for Sphere {
     fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
         Box::new(self.material.clone())
     }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Sphere(SphereDescription {
+            center: self.center.into(),
+            radius: self.radius,
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
 }
 
 impl Boundable for Sphere {
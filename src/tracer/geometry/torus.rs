@@ -0,0 +1,243 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::geometry::quadric::{solve_quadratic, sweep_angle, sweep_bounds};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{MaterialRef, ObjectDescription, TorusDescription};
+use crate::tracer::{Intersectable, Intersection, Point3f, Ray, SceneObject};
+use cgmath::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A torus around the y axis of `center`: a tube of `minor_radius` following a circle of
+/// `major_radius` in the xz plane.
+///
+/// It can be swept by less than a full turn. The u coordinate goes around the y axis and v
+/// around the tube.
+pub struct Torus {
+    center: Point3f,
+    major_radius: f64,
+    minor_radius: f64,
+    phi_max: f64,
+    material: Arc<dyn Material>,
+}
+
+impl Torus {
+    pub fn new(
+        center: Point3f,
+        major_radius: f64,
+        minor_radius: f64,
+        material: Arc<dyn Material>,
+    ) -> Torus {
+        Torus {
+            center,
+            major_radius,
+            minor_radius,
+            phi_max: 2.0 * PI,
+            material,
+        }
+    }
+
+    /// Sweeps the torus from the +x axis towards +z up to `phi_max` radians.
+    pub fn with_phi_max(self, phi_max: f64) -> Torus {
+        Torus { phi_max, ..self }
+    }
+}
+
+impl Intersectable for Torus {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        // Solving with a unit direction from the point of the ray closest to the center keeps
+        // the quartic well conditioned however far the ray starts
+        let length = ray.direction.magnitude();
+        let d = ray.direction / length;
+        let start = -(ray.origin - self.center).dot(d);
+        let o = ray.origin - self.center + d * start;
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + z^2)
+        let r2 = self.major_radius * self.major_radius;
+        let od = o.dot(d);
+        let k = o.magnitude2() + r2 - self.minor_radius * self.minor_radius;
+        let roots = solve_quartic(
+            4.0 * od,
+            2.0 * k + 4.0 * od * od - 4.0 * r2 * (d.x * d.x + d.z * d.z),
+            4.0 * k * od - 8.0 * r2 * (o.x * d.x + o.z * d.z),
+            k * k - 4.0 * r2 * (o.x * o.x + o.z * o.z),
+        );
+
+        for root in roots {
+            let dist = (root + start) / length;
+            if dist <= dist_min || dist >= dist_max {
+                continue;
+            }
+            let p = o + d * root;
+            let phi = sweep_angle(p.x, p.z);
+            if phi > self.phi_max {
+                continue;
+            }
+
+            // The normal points away from the closest point on the circle inside the tube
+            let radial = (p.x * p.x + p.z * p.z).sqrt();
+            let core = vec3(p.x, 0.0, p.z) * (self.major_radius / radial);
+            let theta = sweep_angle(radial - self.major_radius, p.y);
+            return Some(Intersection {
+                dist,
+                point: ray.point_at(dist),
+                normal: (p - core).normalize(),
+                uv: (phi / self.phi_max, theta / (2.0 * PI)),
+                color: None,
+                tangents: None,
+            });
+        }
+
+        None
+    }
+}
+
+/// Real roots of `t^4 + a * t^3 + b * t^2 + c * t + d`, smallest first.
+///
+/// Ferrari's method reduces the quartic to two quadratics, then a couple of Newton iterations
+/// polish the roots.
+fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // Depressed quartic y^4 + p * y^2 + q * y + r with t = y - a / 4
+    let shift = a / 4.0;
+    let a2 = a * a;
+    let p = b - 3.0 * a2 / 8.0;
+    let q = c - a * b / 2.0 + a2 * a / 8.0;
+    let r = d - a * c / 4.0 + a2 * b / 16.0 - 3.0 * a2 * a2 / 256.0;
+
+    let mut roots = Vec::with_capacity(4);
+    if q.abs() < 1e-12 {
+        // Biquadratic: a quadratic in y^2
+        if let Some((z0, z1)) = solve_quadratic(1.0, p, r) {
+            for z in [z0, z1] {
+                if z >= 0.0 {
+                    roots.push(z.sqrt());
+                    roots.push(-z.sqrt());
+                }
+            }
+        }
+    } else {
+        // Any positive root m of the resolvent cubic splits the quartic into
+        // (y^2 + s * y + p / 2 + m - q / (2 * s)) * (y^2 - s * y + p / 2 + m + q / (2 * s))
+        // with s^2 = 2 * m
+        let m = solve_cubic(p, p * p / 4.0 - r, -q * q / 8.0)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if m > 0.0 {
+            let s = (2.0 * m).sqrt();
+            let offset = q / (2.0 * s);
+            let constant = p / 2.0 + m;
+            for (linear, constant) in [(s, constant - offset), (-s, constant + offset)] {
+                if let Some((y0, y1)) = solve_quadratic(1.0, linear, constant) {
+                    roots.push(y0);
+                    roots.push(y1);
+                }
+            }
+        }
+    }
+
+    let polynomial = |t: f64| (((t + a) * t + b) * t + c) * t + d;
+    let derivative = |t: f64| ((4.0 * t + 3.0 * a) * t + 2.0 * b) * t + c;
+    let mut roots: Vec<f64> = roots
+        .into_iter()
+        .map(|y| {
+            let mut t = y - shift;
+            for _ in 0..2 {
+                let slope = derivative(t);
+                if slope != 0.0 {
+                    t -= polynomial(t) / slope;
+                }
+            }
+            t
+        })
+        .collect();
+    roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    roots
+}
+
+/// Real roots of `t^3 + a * t^2 + b * t + c`.
+fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // Depressed cubic y^3 + p * y + q with t = y - a / 3
+    let shift = a / 3.0;
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+
+    let discriminant = q * q / 4.0 + p * p * p / 27.0;
+    if discriminant > 0.0 {
+        // A single real root (Cardano)
+        let sqrt = discriminant.sqrt();
+        vec![(-q / 2.0 + sqrt).cbrt() + (-q / 2.0 - sqrt).cbrt() - shift]
+    } else if p == 0.0 {
+        vec![-shift]
+    } else {
+        // Three real roots (trigonometric method)
+        let radius = 2.0 * (-p / 3.0).sqrt();
+        let angle = (3.0 * q / (p * radius)).clamp(-1.0, 1.0).acos() / 3.0;
+        (0..3)
+            .map(|k| radius * (angle - 2.0 * PI * k as f64 / 3.0).cos() - shift)
+            .collect()
+    }
+}
+
+impl Boundable for Torus {
+    fn get_bounds(&self) -> AABB {
+        sweep_bounds(
+            self.center,
+            (
+                self.major_radius - self.minor_radius,
+                self.major_radius + self.minor_radius,
+            ),
+            self.phi_max,
+            (-self.minor_radius, self.minor_radius),
+        )
+    }
+}
+
+impl SceneObject for Torus {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Torus(TorusDescription {
+            center: self.center.into(),
+            major_radius: self.major_radius,
+            minor_radius: self.minor_radius,
+            phi_max: self.phi_max.to_degrees(),
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{Color, Vector3f};
+
+    #[test]
+    fn test_intersects() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let torus = Torus::new(Point3::new(0.0, 1.0, 0.0), 2.0, 0.5, material);
+
+        // Through the hole, the ray hits the outside of the tube first then its inside
+        let ray = Ray::new(Point3::new(-10.0, 1.0, 0.0), Vector3f::unit_x() * 2.0);
+        let hit = torus.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 3.75).abs() < 1e-9);
+        assert!((hit.normal - vec3(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+
+        let hit = torus.intersects(&ray, 4.0, f64::MAX).unwrap();
+        assert!((hit.dist - 4.25).abs() < 1e-9);
+        assert!((hit.normal - vec3(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+
+        // Grazing the top of the tube, and passing over it
+        let ray = Ray::new(Point3::new(2.0, 10.0, 0.0), -Vector3f::unit_y());
+        let hit = torus.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 8.5).abs() < 1e-9);
+        assert!((hit.normal - vec3(0.0, 1.0, 0.0)).magnitude() < 1e-9);
+        let ray = Ray::new(Point3::new(-10.0, 1.6, 0.0), Vector3f::unit_x());
+        assert!(torus.intersects(&ray, 0.001, f64::MAX).is_none());
+
+        let bounds = torus.get_bounds();
+        assert!((bounds.min - vec3(-2.5, 0.5, -2.5)).magnitude() < 1e-12);
+        assert!((bounds.max - vec3(2.5, 1.5, 2.5)).magnitude() < 1e-12);
+    }
+}
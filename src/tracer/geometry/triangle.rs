@@ -0,0 +1,219 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::geometry::MeshData;
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{MaterialRef, ObjectDescription, TriangleDescription};
+use crate::tracer::{Intersectable, Intersection, Point3f, Ray, SceneObject};
+use cgmath::*;
+use std::sync::Arc;
+
+/// A single triangle of a `MeshData`.
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    index: usize,
+}
+
+impl Triangle {
+    /// A standalone triangle, stored as a mesh of its own.
+    pub fn new(v0: Point3f, v1: Point3f, v2: Point3f, material: Arc<dyn Material>) -> Triangle {
+        let mesh = MeshData {
+            positions: vec![v0, v1, v2],
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            colors: Vec::new(),
+            indices: vec![[0, 1, 2]],
+            material,
+        };
+        Triangle::from_mesh(Arc::new(mesh), 0)
+    }
+
+    pub fn from_mesh(mesh: Arc<MeshData>, index: usize) -> Triangle {
+        Triangle { mesh, index }
+    }
+}
+
+/// Watertight ray/triangle intersection (Woop, Benthin and Wald, "Watertight Ray/Triangle
+/// Intersection", JCGT 2013): rays going through a shared edge or vertex hit one of the
+/// triangles sharing it, so meshes don't leak.
+///
+/// Returns the distance along the ray and the barycentric coordinates of the hit.
+pub fn intersect_triangle(
+    ray: &Ray,
+    p: [Point3f; 3],
+    dist_min: f64,
+    dist_max: f64,
+) -> Option<(f64, [f64; 3])> {
+    let dir = ray.direction;
+
+    // Permute the axes so that the ray travels mostly along z
+    let abs_dir = vec3(dir.x.abs(), dir.y.abs(), dir.z.abs());
+    let kz = if abs_dir.x > abs_dir.y {
+        if abs_dir.x > abs_dir.z {
+            0
+        } else {
+            2
+        }
+    } else if abs_dir.y > abs_dir.z {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if dir[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    // Shear transforming the ray direction to +z
+    let sx = dir[kx] / dir[kz];
+    let sy = dir[ky] / dir[kz];
+    let sz = 1.0 / dir[kz];
+
+    let a = p[0] - ray.origin;
+    let b = p[1] - ray.origin;
+    let c = p[2] - ray.origin;
+
+    let ax = a[kx] - sx * a[kz];
+    let ay = a[ky] - sy * a[kz];
+    let bx = b[kx] - sx * b[kz];
+    let by = b[ky] - sy * b[kz];
+    let cx = c[kx] - sx * c[kz];
+    let cy = c[ky] - sy * c[kz];
+
+    // Scaled barycentric coordinates
+    let u = cx * by - cy * bx;
+    let v = ax * cy - ay * cx;
+    let w = bx * ay - by * ax;
+
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    let t_scaled = u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz];
+    let dist = t_scaled / det;
+    // Also rejects the NaN distances of degenerate rays without a direction
+    if !(dist > dist_min && dist < dist_max) {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    Some((dist, [u * inv_det, v * inv_det, w * inv_det]))
+}
+
+impl Intersectable for Triangle {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        let positions = self.mesh.triangle_positions(self.index);
+        intersect_triangle(ray, positions, dist_min, dist_max)
+            .map(|(dist, b)| self.mesh.get_intersection(self.index, dist, b))
+    }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        let positions = self.mesh.triangle_positions(self.index);
+        intersect_triangle(ray, positions, dist_min, dist_max).is_some()
+    }
+}
+
+impl Boundable for Triangle {
+    fn get_bounds(&self) -> AABB {
+        let [p0, p1, p2] = self.mesh.triangle_positions(self.index);
+        AABB::new(
+            vec3(
+                p0.x.min(p1.x).min(p2.x),
+                p0.y.min(p1.y).min(p2.y),
+                p0.z.min(p1.z).min(p2.z),
+            ),
+            vec3(
+                p0.x.max(p1.x).max(p2.x),
+                p0.y.max(p1.y).max(p2.y),
+                p0.z.max(p1.z).max(p2.z),
+            ),
+        )
+    }
+}
+
+impl SceneObject for Triangle {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.mesh.material.clone())
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        let mesh = &self.mesh;
+        let indices = mesh.indices[self.index].map(|i| i as usize);
+
+        Some(ObjectDescription::Triangle(TriangleDescription {
+            vertices: indices.map(|i| mesh.positions[i].into()),
+            normals: if mesh.normals.is_empty() {
+                None
+            } else {
+                Some(indices.map(|i| mesh.normals[i].into()))
+            },
+            uvs: if mesh.uvs.is_empty() {
+                None
+            } else {
+                Some(indices.map(|i| [mesh.uvs[i].0, mesh.uvs[i].1]))
+            },
+            colors: if mesh.colors.is_empty() {
+                None
+            } else {
+                Some(indices.map(|i| mesh.colors[i].into()))
+            },
+            material: MaterialRef::Inline(Box::new(mesh.material.describe()?)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::Vector3f;
+
+    fn intersect(origin: Point3f, direction: Vector3f) -> Option<(f64, [f64; 3])> {
+        let p = [
+            Point3f::new(0.0, 0.0, 0.0),
+            Point3f::new(1.0, 0.0, 0.0),
+            Point3f::new(0.0, 1.0, 0.0),
+        ];
+        intersect_triangle(&Ray::new(origin, direction), p, 0.0001, f64::MAX)
+    }
+
+    #[test]
+    fn test_intersect_triangle() {
+        let (dist, b) = intersect(Point3f::new(0.25, 0.25, 2.0), vec3(0.0, 0.0, -1.0)).unwrap();
+        assert!((dist - 2.0).abs() < 1e-12);
+        assert!((b[0] - 0.5).abs() < 1e-12);
+        assert!((b[1] - 0.25).abs() < 1e-12);
+        assert!((b[2] - 0.25).abs() < 1e-12);
+
+        // Back faces are hit as well
+        let hit = intersect(Point3f::new(0.25, 0.25, -2.0), vec3(0.0, 0.0, 1.0));
+        assert!(hit.is_some());
+
+        let miss = intersect(Point3f::new(0.75, 0.75, 2.0), vec3(0.0, 0.0, -1.0));
+        assert!(miss.is_none());
+    }
+
+    #[test]
+    fn test_shared_edge_is_watertight() {
+        // Two triangles sharing the diagonal of the unit square
+        let square = [
+            Point3f::new(0.0, 0.0, 0.0),
+            Point3f::new(1.0, 0.0, 0.0),
+            Point3f::new(1.0, 1.0, 0.0),
+            Point3f::new(0.0, 1.0, 0.0),
+        ];
+        let t1 = [square[0], square[1], square[2]];
+        let t2 = [square[0], square[2], square[3]];
+
+        for i in 1..100 {
+            let s = i as f64 / 100.0;
+            let ray = Ray::new(Point3f::new(s, s, 1.0), -Vector3f::unit_z());
+            let hit1 = intersect_triangle(&ray, t1, 0.0, f64::MAX);
+            let hit2 = intersect_triangle(&ray, t2, 0.0, f64::MAX);
+            assert!(hit1.is_some() || hit2.is_some());
+        }
+    }
+}
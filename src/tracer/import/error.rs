@@ -0,0 +1,75 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Errors raised while importing a model file.
+#[derive(Debug)]
+pub enum ImportError {
+    Io {
+        file: PathBuf,
+        error: io::Error,
+    },
+    /// Malformed content at `line` (1-based) of `file`
+    Parse {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// Well-formed content that can't be turned into scene objects
+    Invalid {
+        file: PathBuf,
+        message: String,
+    },
+}
+
+impl ImportError {
+    pub fn io(file: &Path, error: io::Error) -> ImportError {
+        ImportError::Io {
+            file: file.to_path_buf(),
+            error,
+        }
+    }
+
+    pub fn parse(file: &Path, line: usize, message: impl Into<String>) -> ImportError {
+        ImportError::Parse {
+            file: file.to_path_buf(),
+            line,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid(file: &Path, message: impl Into<String>) -> ImportError {
+        ImportError::Invalid {
+            file: file.to_path_buf(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Io { file, error } => {
+                write!(f, "failed to read {}: {}", file.display(), error)
+            }
+            ImportError::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file.display(), line, message),
+            ImportError::Invalid { file, message } => {
+                write!(f, "{}: {}", file.display(), message)
+            }
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,751 @@
+use crate::tracer::geometry::{Instance, MeshData, TriangleMesh};
+use crate::tracer::import::ImportError;
+use crate::tracer::material::{
+    Dielectric, DiffuseLight, ImageTexture, Lambertian, Material, Metal, SolidTexture, Texture,
+    VertexColorTexture,
+};
+use crate::tracer::scene_file::{CameraDescription, SimpleCameraDescription};
+use crate::tracer::{Color, Point3f, SceneObject, Vector3f};
+use cgmath::*;
+use gltf::camera::Projection;
+use gltf::mesh::Mode;
+use gltf::{Document, Gltf, Mesh, Node, Primitive};
+use log::warn;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The renderable content of a glTF file.
+pub struct GltfScene {
+    /// A mesh per primitive of every mesh node, instanced in world space so nodes using the
+    /// same mesh share its geometry
+    pub objects: Vec<Arc<dyn SceneObject>>,
+    /// The perspective cameras of the scene, in world space
+    pub cameras: Vec<CameraDescription>,
+}
+
+impl GltfScene {
+    /// The camera to render the scene from, the first perspective camera in node order.
+    pub fn camera(&self) -> Option<&CameraDescription> {
+        self.cameras.first()
+    }
+}
+
+/// Reads the glTF (`.gltf`) or binary glTF (`.glb`) file at `path`.
+///
+/// The default scene, or the first one, is imported with its node transforms applied. Buffers
+/// and images may be embedded in the file, in base64 data URIs or in files next to it; other
+/// URIs are rejected so nothing is ever fetched from the network.
+///
+/// Metallic-roughness materials are mapped onto the closest helios material:
+/// - an emissive material gives a `DiffuseLight`
+/// - a transmissive (`KHR_materials_transmission`) or blended transparent material gives a
+///   `Dielectric` of refractive index `KHR_materials_ior`
+/// - a mostly metallic material gives a `Metal`, as fuzzy as it is rough
+/// - anything else gives a `Lambertian` textured with its base color texture, or colored by
+///   its vertex colors or base color
+pub fn load_gltf(path: &Path) -> Result<GltfScene, ImportError> {
+    let bytes = fs::read(path).map_err(|error| ImportError::io(path, error))?;
+    parse_gltf(&bytes, path)
+}
+
+/// Parses the glTF or binary glTF file `bytes`, read from `path`, see `load_gltf`.
+pub fn parse_gltf(bytes: &[u8], path: &Path) -> Result<GltfScene, ImportError> {
+    let Gltf { document, blob } =
+        Gltf::from_slice(bytes).map_err(|error| ImportError::invalid(path, error.to_string()))?;
+
+    let mut importer = GltfImporter::new(path, &document);
+    importer.load_buffers(blob)?;
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| ImportError::invalid(path, "file has no scene"))?;
+    for node in scene.nodes() {
+        importer.node(&node, Matrix4::identity())?;
+    }
+
+    Ok(GltfScene {
+        objects: importer.objects,
+        cameras: importer.cameras,
+    })
+}
+
+struct GltfImporter<'a> {
+    path: &'a Path,
+    document: &'a Document,
+
+    buffers: Vec<Vec<u8>>,
+    meshes: HashMap<usize, Vec<Arc<dyn SceneObject>>>,
+    textures: HashMap<usize, Arc<dyn Texture>>,
+    // Materials by material index, and whether the primitive has vertex colors
+    materials: HashMap<(Option<usize>, bool), Arc<dyn Material>>,
+
+    objects: Vec<Arc<dyn SceneObject>>,
+    cameras: Vec<CameraDescription>,
+}
+
+impl<'a> GltfImporter<'a> {
+    fn new(path: &'a Path, document: &'a Document) -> GltfImporter<'a> {
+        GltfImporter {
+            path,
+            document,
+            buffers: Vec::new(),
+            meshes: HashMap::new(),
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            objects: Vec::new(),
+            cameras: Vec::new(),
+        }
+    }
+
+    fn load_buffers(&mut self, mut blob: Option<Vec<u8>>) -> Result<(), ImportError> {
+        for buffer in self.document.buffers() {
+            let data = match buffer.source() {
+                gltf::buffer::Source::Bin => blob
+                    .take()
+                    .ok_or_else(|| ImportError::invalid(self.path, "missing binary chunk"))?,
+                gltf::buffer::Source::Uri(uri) => self.read_uri(uri)?,
+            };
+
+            if data.len() < buffer.length() {
+                return Err(ImportError::invalid(
+                    self.path,
+                    format!("buffer {} is shorter than declared", buffer.index()),
+                ));
+            }
+            self.buffers.push(data);
+        }
+
+        Ok(())
+    }
+
+    /// Reads a base64 data URI, or a file relative to the glTF file.
+    fn read_uri(&self, uri: &str) -> Result<Vec<u8>, ImportError> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let encoded = match data.split_once(";base64,") {
+                Some((_, encoded)) => encoded,
+                None => {
+                    return Err(ImportError::invalid(
+                        self.path,
+                        "only base64 data URIs are supported",
+                    ))
+                }
+            };
+            return base64::decode(encoded).map_err(|error| {
+                ImportError::invalid(self.path, format!("invalid base64 data: {}", error))
+            });
+        }
+
+        let relative = uri.strip_prefix("file://").unwrap_or(uri);
+        if relative.contains("://") {
+            return Err(ImportError::invalid(
+                self.path,
+                format!("`{}` isn't a local file", uri),
+            ));
+        }
+
+        let path = self.resolve_path(relative);
+        fs::read(&path).map_err(|error| ImportError::io(&path, error))
+    }
+
+    fn resolve_path(&self, relative: &str) -> PathBuf {
+        let base_dir = self.path.parent().unwrap_or_else(|| Path::new(""));
+        base_dir.join(percent_decode(relative))
+    }
+
+    fn node(&mut self, node: &Node, parent: Matrix4<f64>) -> Result<(), ImportError> {
+        let transform = parent * to_matrix(node.transform().matrix());
+
+        if let Some(camera) = node.camera() {
+            match camera.projection() {
+                Projection::Perspective(perspective) => {
+                    self.cameras.push(camera_description(
+                        &transform,
+                        perspective.yfov(),
+                        perspective.aspect_ratio(),
+                    ));
+                }
+                Projection::Orthographic(_) => {
+                    warn!("{}: skipping orthographic camera", self.path.display());
+                }
+            }
+        }
+
+        if let Some(mesh) = node.mesh() {
+            if transform.determinant() == 0.0 {
+                warn!("{}: skipping mesh of a flattened node", self.path.display());
+            } else {
+                for object in self.mesh(&mesh)? {
+                    if transform == Matrix4::identity() {
+                        self.objects.push(object);
+                    } else {
+                        self.objects
+                            .push(Arc::new(Instance::new(object, transform)));
+                    }
+                }
+            }
+        }
+
+        for child in node.children() {
+            self.node(&child, transform)?;
+        }
+
+        Ok(())
+    }
+
+    /// The primitives of `mesh`, built once and shared by all the nodes using it.
+    fn mesh(&mut self, mesh: &Mesh) -> Result<Vec<Arc<dyn SceneObject>>, ImportError> {
+        if let Some(objects) = self.meshes.get(&mesh.index()) {
+            return Ok(objects.clone());
+        }
+
+        let mut objects = Vec::new();
+        for primitive in mesh.primitives() {
+            if let Some(object) = self.primitive(&primitive)? {
+                objects.push(object);
+            }
+        }
+
+        self.meshes.insert(mesh.index(), objects.clone());
+        Ok(objects)
+    }
+
+    fn primitive(
+        &mut self,
+        primitive: &Primitive,
+    ) -> Result<Option<Arc<dyn SceneObject>>, ImportError> {
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+
+        let positions: Vec<Point3f> = match reader.read_positions() {
+            Some(positions) => positions
+                .map(|[x, y, z]| Point3::new(x as f64, y as f64, z as f64))
+                .collect(),
+            None => return Ok(None),
+        };
+
+        let normals: Vec<Vector3f> = reader
+            .read_normals()
+            .map(|normals| {
+                normals
+                    .map(|[x, y, z]| vec3(x as f64, y as f64, z as f64).normalize())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let base_color_uv = primitive
+            .material()
+            .pbr_metallic_roughness()
+            .base_color_texture()
+            .map_or(0, |info| info.tex_coord());
+        // glTF uvs go down the image, helios uvs go up
+        let uvs: Vec<(f64, f64)> = reader
+            .read_tex_coords(base_color_uv)
+            .map(|uvs| {
+                uvs.into_f32()
+                    .map(|[u, v]| (u as f64, 1.0 - v as f64))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let colors: Vec<Vector3f> = reader
+            .read_colors(0)
+            .map(|colors| {
+                colors
+                    .into_rgb_f32()
+                    .map(|[r, g, b]| vec3(r as f64, g as f64, b as f64))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let vertices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        let indices: Vec<[u32; 3]> = match primitive.mode() {
+            Mode::Triangles => vertices
+                .chunks_exact(3)
+                .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                .collect(),
+            Mode::TriangleStrip => (2..vertices.len())
+                .map(|idx| {
+                    // Every other triangle is flipped to keep the strip winding consistent
+                    if idx % 2 == 0 {
+                        [vertices[idx - 2], vertices[idx - 1], vertices[idx]]
+                    } else {
+                        [vertices[idx - 1], vertices[idx - 2], vertices[idx]]
+                    }
+                })
+                .collect(),
+            Mode::TriangleFan => (2..vertices.len())
+                .map(|idx| [vertices[0], vertices[idx - 1], vertices[idx]])
+                .collect(),
+            mode => {
+                warn!("{}: skipping {:?} primitive", self.path.display(), mode);
+                return Ok(None);
+            }
+        };
+        if indices.is_empty() {
+            return Ok(None);
+        }
+
+        let material = self.material(&primitive.material(), !colors.is_empty())?;
+        let data = MeshData {
+            positions,
+            normals,
+            uvs,
+            colors,
+            indices,
+            material,
+        };
+        data.validate()
+            .map_err(|error| ImportError::invalid(self.path, error))?;
+
+        Ok(Some(Arc::new(TriangleMesh::new(data))))
+    }
+
+    fn material(
+        &mut self,
+        material: &gltf::Material,
+        vertex_colors: bool,
+    ) -> Result<Arc<dyn Material>, ImportError> {
+        let key = (material.index(), vertex_colors);
+        if let Some(material) = self.materials.get(&key) {
+            return Ok(material.clone());
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, alpha] = pbr.base_color_factor();
+        let base_color = Color::new(r as f64, g as f64, b as f64);
+
+        let [r, g, b] = material.emissive_factor();
+        let emissive = Color::new(r as f64, g as f64, b as f64)
+            * material.emissive_strength().unwrap_or(1.0) as f64;
+
+        let transmission = material
+            .transmission()
+            .map_or(0.0, |transmission| transmission.transmission_factor());
+        let blended = material.alpha_mode() == gltf::material::AlphaMode::Blend && alpha < 1.0;
+
+        let built: Arc<dyn Material> = if emissive.red.max(emissive.green).max(emissive.blue) > 0.0
+        {
+            Arc::new(DiffuseLight::new(Arc::new(SolidTexture::new(emissive))))
+        } else if transmission > 0.0 || blended {
+            Arc::new(Dielectric::new(material.ior().unwrap_or(1.5) as f64))
+        } else if pbr.metallic_factor() >= 0.5 {
+            Arc::new(Metal::new(
+                base_color.to_vec3f(),
+                pbr.roughness_factor() as f64,
+            ))
+        } else {
+            let albedo: Arc<dyn Texture> = match pbr.base_color_texture() {
+                Some(info) => self.texture(&info.texture())?,
+                None if vertex_colors => Arc::new(VertexColorTexture::new(base_color)),
+                None => Arc::new(SolidTexture::new(base_color)),
+            };
+            Arc::new(Lambertian::new(albedo))
+        };
+
+        self.materials.insert(key, built.clone());
+        Ok(built)
+    }
+
+    fn texture(&mut self, texture: &gltf::Texture) -> Result<Arc<dyn Texture>, ImportError> {
+        let image = texture.source();
+        if let Some(texture) = self.textures.get(&image.index()) {
+            return Ok(texture.clone());
+        }
+
+        let load_error = |error: image::ImageError| {
+            ImportError::invalid(
+                self.path,
+                format!("failed to load image {}: {}", image.index(), error),
+            )
+        };
+
+        let loaded = match image.source() {
+            gltf::image::Source::View { view, .. } => {
+                let buffer = &self.buffers[view.buffer().index()];
+                let bytes = buffer
+                    .get(view.offset()..view.offset() + view.length())
+                    .ok_or_else(|| {
+                        ImportError::invalid(self.path, "image view out of its buffer")
+                    })?;
+                ImageTexture::new(
+                    image::load_from_memory(bytes)
+                        .map_err(load_error)?
+                        .to_rgb8(),
+                )
+            }
+            gltf::image::Source::Uri { uri, .. } if uri.starts_with("data:") => {
+                let bytes = self.read_uri(uri)?;
+                ImageTexture::new(
+                    image::load_from_memory(&bytes)
+                        .map_err(load_error)?
+                        .to_rgb8(),
+                )
+            }
+            gltf::image::Source::Uri { uri, .. } => {
+                let relative = uri.strip_prefix("file://").unwrap_or(uri);
+                if relative.contains("://") {
+                    return Err(ImportError::invalid(
+                        self.path,
+                        format!("`{}` isn't a local file", uri),
+                    ));
+                }
+                ImageTexture::open(&self.resolve_path(relative)).map_err(load_error)?
+            }
+        };
+
+        let loaded: Arc<dyn Texture> = Arc::new(loaded);
+        self.textures.insert(image.index(), loaded.clone());
+        Ok(loaded)
+    }
+}
+
+/// A camera at the origin of `transform`, looking down its -z axis with its y axis up.
+fn camera_description(
+    transform: &Matrix4<f64>,
+    yfov: f32,
+    aspect: Option<f32>,
+) -> CameraDescription {
+    let look_from = transform.transform_point(Point3::origin());
+    let forward = transform.transform_vector(-Vector3::unit_z()).normalize();
+    let up = transform.transform_vector(Vector3::unit_y()).normalize();
+
+    CameraDescription::Simple(SimpleCameraDescription {
+        look_from: look_from.into(),
+        look_at: (look_from + forward).into(),
+        up: up.into(),
+        vfov: (yfov as f64).to_degrees(),
+        aspect: aspect.map(|aspect| aspect as f64),
+        aperture: 0.0,
+        focus_dist: 1.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    })
+}
+
+fn to_matrix(columns: [[f32; 4]; 4]) -> Matrix4<f64> {
+    let column = |c: [f32; 4]| vec4(c[0] as f64, c[1] as f64, c[2] as f64, c[3] as f64);
+    Matrix4::from_cols(
+        column(columns[0]),
+        column(columns[1]),
+        column(columns[2]),
+        column(columns[3]),
+    )
+}
+
+/// Decodes the `%XX` escapes of a URI path.
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let escaped = bytes
+            .get(idx + 1..idx + 3)
+            .filter(|_| bytes[idx] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            None => {
+                decoded.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::Ray;
+    use serde_json::{json, Value};
+
+    const GLTF: &str = r#"{
+        "asset": {"version": "2.0"},
+        "scenes": [{"nodes": [0]}],
+        "nodes": [
+            {"translation": [0, 0, -5], "mesh": 0, "children": [1]},
+            {"translation": [0, 0, 10], "camera": 0}
+        ],
+        "cameras": [{"type": "perspective", "perspective": {"yfov": 1.0, "znear": 0.1}}],
+        "meshes": [{"primitives": [{"attributes": {"POSITION": 0}}]}],
+        "accessors": [{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                       "min": [0, 0, 0], "max": [1, 1, 0]}],
+        "bufferViews": [{"buffer": 0, "byteLength": 36}],
+        "buffers": [{"byteLength": 36,
+                     "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"}]
+    }"#;
+
+    /// The positions and uvs of the triangle (0, 0, 0), (1, 0, 0), (0, 1, 0).
+    fn triangle_buffer() -> Vec<u8> {
+        let floats: [f32; 15] = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, // positions
+            0.0, 0.0, 1.0, 0.0, 0.0, 1.0, // uvs
+        ];
+        floats
+            .iter()
+            .flat_map(|float| float.to_le_bytes())
+            .collect()
+    }
+
+    /// A document with a mesh node of the triangle, its buffer left for the caller to fill in.
+    fn triangle_document() -> Value {
+        json!({
+            "asset": {"version": "2.0"},
+            "scenes": [{"nodes": [0]}],
+            "nodes": [{"mesh": 0}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0, "TEXCOORD_0": 1}}]}],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                 "min": [0, 0, 0], "max": [1, 1, 0]},
+                {"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2"}
+            ],
+            "bufferViews": [
+                {"buffer": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 24}
+            ],
+            "buffers": [{"byteLength": 60}]
+        })
+    }
+
+    fn data_uri(mime: &str, bytes: &[u8]) -> String {
+        format!("data:{};base64,{}", mime, base64::encode(bytes))
+    }
+
+    fn parse(document: &Value) -> GltfScene {
+        let bytes = serde_json::to_vec(document).unwrap();
+        parse_gltf(&bytes, Path::new("test.gltf")).unwrap()
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("helios-gltf-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_gltf() {
+        let scene = parse_gltf(GLTF.as_bytes(), Path::new("test.gltf")).unwrap();
+
+        assert_eq!(scene.objects.len(), 1);
+        let bounds = scene.objects[0].get_bounds();
+        assert_eq!(bounds.min, vec3(0.0, 0.0, -5.0));
+        assert_eq!(bounds.max, vec3(1.0, 1.0, -5.0));
+
+        // The camera is a child of the mesh node, looking down -z
+        match &scene.cameras[..] {
+            [CameraDescription::Simple(camera)] => {
+                assert_eq!(camera.look_from, [0.0, 0.0, 5.0]);
+                assert_eq!(camera.look_at, [0.0, 0.0, 4.0]);
+            }
+            _ => panic!("expected a camera"),
+        }
+    }
+
+    #[test]
+    fn test_materials() {
+        // 2x2 image, red and green on its top row, blue and white on its bottom one
+        let mut png = Vec::new();
+        let pixels = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+        image::png::PngEncoder::new(&mut png)
+            .encode(&pixels, 2, 2, image::ColorType::Rgb8)
+            .unwrap();
+
+        let mut document = triangle_document();
+        document["buffers"][0]["uri"] =
+            data_uri("application/octet-stream", &triangle_buffer()).into();
+        document["images"] = json!([{"uri": data_uri("image/png", &png)}]);
+        document["textures"] = json!([{"source": 0}]);
+        document["materials"] = json!([
+            {"pbrMetallicRoughness": {"baseColorFactor": [1, 0.5, 0, 1], "metallicFactor": 0.25}},
+            {"pbrMetallicRoughness": {"baseColorFactor": [0.5, 0.5, 0.5, 1], "metallicFactor": 1,
+                                      "roughnessFactor": 0.25}},
+            {"emissiveFactor": [1, 0.5, 0.25]},
+            {"pbrMetallicRoughness": {"baseColorTexture": {"index": 0}, "metallicFactor": 0}},
+            {"pbrMetallicRoughness": {"baseColorFactor": [1, 1, 1, 0.5]}, "alphaMode": "BLEND"}
+        ]);
+        let primitive = document["meshes"][0]["primitives"][0].clone();
+        document["meshes"][0]["primitives"] = (0..5)
+            .map(|material| {
+                let mut primitive = primitive.clone();
+                primitive["material"] = material.into();
+                primitive
+            })
+            .collect();
+
+        let scene = parse(&document);
+        assert_eq!(scene.objects.len(), 5);
+        let describe = |idx: usize| {
+            let material = scene.objects[idx].get_material(Point3::origin());
+            serde_json::to_value(material.describe()).unwrap()
+        };
+
+        // Mostly dielectric materials are lambertian, mostly metallic ones are as fuzzy as they
+        // are rough
+        assert_eq!(
+            describe(0),
+            json!({"type": "lambertian", "albedo": [1.0, 0.5, 0.0]})
+        );
+        assert_eq!(
+            describe(1),
+            json!({"type": "metal", "albedo": [0.5, 0.5, 0.5], "fuzz": 0.25})
+        );
+        assert_eq!(
+            describe(2),
+            json!({"type": "diffuse_light", "emit": [1.0, 0.5, 0.25]})
+        );
+        assert_eq!(
+            describe(4),
+            json!({"type": "dielectric", "refractive_index": 1.5})
+        );
+
+        // glTF uvs start at the top left of the image
+        let textured = &scene.objects[3];
+        let albedo = |x: f64, y: f64| {
+            let ray = Ray::new(Point3::new(x, y, 1.0), -Vector3f::unit_z());
+            let hit = textured.intersects(&ray, 0.001, f64::MAX).unwrap();
+            let material = textured.get_material(hit.point);
+            material.scatter(&ray, &hit).unwrap().attenuation
+        };
+        assert_eq!(albedo(0.1, 0.1), vec3(1.0, 0.0, 0.0));
+        assert_eq!(albedo(0.8, 0.1), vec3(0.0, 1.0, 0.0));
+        assert_eq!(albedo(0.1, 0.8), vec3(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_glb() {
+        let mut document = triangle_document();
+        document["nodes"][0]["translation"] = json!([0, 0, -5]);
+        let mut json = serde_json::to_vec(&document).unwrap();
+        let mut bin = triangle_buffer();
+        // Chunks are 4 bytes aligned, json padded with spaces and binary data with zeros
+        json.resize(json.len().div_ceil(4) * 4, b' ');
+        bin.resize(bin.len().div_ceil(4) * 4, 0);
+
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = Vec::new();
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+
+        let scene = parse_gltf(&glb, Path::new("test.glb")).unwrap();
+        assert_eq!(scene.objects.len(), 1);
+        let bounds = scene.objects[0].get_bounds();
+        assert_eq!(bounds.min, vec3(0.0, 0.0, -5.0));
+        assert_eq!(bounds.max, vec3(1.0, 1.0, -5.0));
+
+        // A binary buffer without the binary chunk
+        let missing = parse_gltf(
+            &serde_json::to_vec(&document).unwrap(),
+            Path::new("test.gltf"),
+        );
+        assert!(matches!(missing, Err(ImportError::Invalid { .. })));
+    }
+
+    #[test]
+    fn test_external_buffer() {
+        let dir = test_dir("buffer");
+        fs::write(dir.join("the triangle.bin"), triangle_buffer()).unwrap();
+
+        // Relative URIs are percent encoded and resolved next to the glTF file
+        let mut document = triangle_document();
+        document["buffers"][0]["uri"] = "the%20triangle.bin".into();
+        let path = dir.join("test.gltf");
+        fs::write(&path, serde_json::to_vec(&document).unwrap()).unwrap();
+        let scene = load_gltf(&path).unwrap();
+        assert_eq!(scene.objects[0].get_bounds().max, vec3(1.0, 1.0, 0.0));
+
+        document["buffers"][0]["uri"] = "missing.bin".into();
+        fs::write(&path, serde_json::to_vec(&document).unwrap()).unwrap();
+        assert!(matches!(load_gltf(&path), Err(ImportError::Io { .. })));
+
+        // Nothing is fetched from the network
+        document["buffers"][0]["uri"] = "https://example.com/triangle.bin".into();
+        fs::write(&path, serde_json::to_vec(&document).unwrap()).unwrap();
+        assert!(matches!(load_gltf(&path), Err(ImportError::Invalid { .. })));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_node_transforms() {
+        let mut document = triangle_document();
+        document["buffers"][0]["uri"] =
+            data_uri("application/octet-stream", &triangle_buffer()).into();
+        document["scenes"] = json!([{"nodes": [0, 2, 3]}]);
+        document["nodes"] = json!([
+            {"translation": [1, 0, 0], "scale": [2, 2, 2], "children": [1]},
+            {"translation": [0, 0, 1], "mesh": 0},
+            {"scale": [1, 0, 1], "mesh": 0},
+            {"matrix": [0, 1, 0, 0, -1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1], "mesh": 0}
+        ]);
+
+        // The flattened node is skipped, the others instance the same mesh
+        let scene = parse(&document);
+        assert_eq!(scene.objects.len(), 2);
+
+        // Children are transformed by their parents, translated then scaled
+        let bounds = scene.objects[0].get_bounds();
+        assert_eq!(bounds.min, vec3(1.0, 0.0, 2.0));
+        assert_eq!(bounds.max, vec3(3.0, 2.0, 2.0));
+
+        // Column major matrices, here a quarter turn around z
+        let ray = Ray::new(Point3::new(-0.25, 0.25, 1.0), -Vector3f::unit_z());
+        let hit = scene.objects[1].intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.point - Point3::new(-0.25, 0.25, 0.0)).magnitude() < 1e-9);
+        let ray = Ray::new(Point3::new(0.25, 0.25, 1.0), -Vector3f::unit_z());
+        assert!(scene.objects[1].intersects(&ray, 0.001, f64::MAX).is_none());
+    }
+
+    #[test]
+    fn test_camera_pick() {
+        let mut document = triangle_document();
+        document["buffers"][0]["uri"] =
+            data_uri("application/octet-stream", &triangle_buffer()).into();
+        document["scenes"] = json!([{"nodes": [1, 2, 0, 3]}]);
+        document["nodes"] = json!([
+            {"mesh": 0},
+            {"camera": 0},
+            {"translation": [0, 0, 5], "children": [4]},
+            {"translation": [0, 0, 10], "camera": 2},
+            {"rotation": [0, 0.70710677, 0, 0.70710677], "camera": 1}
+        ]);
+        document["cameras"] = json!([
+            {"type": "orthographic",
+             "orthographic": {"xmag": 1, "ymag": 1, "znear": 0.1, "zfar": 10}},
+            {"type": "perspective", "perspective": {"yfov": 0.5, "aspectRatio": 2, "znear": 0.1}},
+            {"type": "perspective", "perspective": {"yfov": 1.0, "znear": 0.1}}
+        ]);
+
+        // The orthographic camera is skipped, the first perspective one in node order is
+        // rendered from, even nested deeper than the next one
+        let scene = parse(&document);
+        assert_eq!(scene.cameras.len(), 2);
+        let camera = match scene.camera() {
+            Some(CameraDescription::Simple(camera)) => camera,
+            _ => panic!("expected a camera"),
+        };
+        assert_eq!(camera.look_from, [0.0, 0.0, 5.0]);
+        // Turned a quarter around y, from looking down -z to looking down -x
+        assert!((camera.look_at[0] + 1.0).abs() < 1e-6);
+        assert!((camera.look_at[2] - 5.0).abs() < 1e-6);
+        assert_eq!(camera.vfov, 0.5f64.to_degrees());
+        assert_eq!(camera.aspect, Some(2.0));
+    }
+}
@@ -0,0 +1,12 @@
+mod error;
+mod gltf;
+mod mtl;
+mod obj;
+mod ply;
+mod vol;
+
+pub use self::gltf::*;
+pub use error::*;
+pub use obj::*;
+pub use ply::*;
+pub use vol::*;
@@ -0,0 +1,296 @@
+use super::obj::parse_floats;
+use crate::tracer::import::ImportError;
+use crate::tracer::material::{
+    Dielectric, DiffuseLight, ImageTexture, Lambertian, Material, Metal, SolidTexture, Texture,
+};
+use crate::tracer::Color;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Image textures loaded so far, shared between all the materials using the same image.
+pub type TextureCache = HashMap<PathBuf, Arc<dyn Texture>>;
+
+/// The statements of an MTL material that helios uses.
+#[derive(Default)]
+struct MtlMaterial {
+    diffuse: Option<Color>,
+    specular: Option<Color>,
+    emissive: Option<Color>,
+    refractive_index: Option<f64>,
+    dissolve: Option<f64>,
+    shininess: Option<f64>,
+    illum: Option<u32>,
+    diffuse_map: Option<PathBuf>,
+}
+
+impl MtlMaterial {
+    /// Maps the material onto the closest helios material:
+    /// - an emissive `Ke` gives a `DiffuseLight`
+    /// - a transparent material (`d` < 1, `Tr` > 0 or `illum` 4, 6, 7 or 9) gives a
+    ///   `Dielectric` of refractive index `Ni`
+    /// - a specular `Ks` brighter than `Kd`, or `illum` 3, gives a `Metal` whose fuzz
+    ///   decreases with the shininess `Ns`
+    /// - anything else gives a `Lambertian` textured with `map_Kd`, or colored by `Kd`
+    fn build(
+        &self,
+        file: &Path,
+        textures: &mut TextureCache,
+    ) -> Result<Arc<dyn Material>, ImportError> {
+        let diffuse = self.diffuse.unwrap_or_else(|| Color::new(0.8, 0.8, 0.8));
+
+        if let Some(emissive) = self.emissive.filter(|c| brightness(*c) > 0.0) {
+            return Ok(Arc::new(DiffuseLight::new(Arc::new(SolidTexture::new(
+                emissive,
+            )))));
+        }
+
+        let transparent = self.dissolve.is_some_and(|d| d < 1.0)
+            || matches!(self.illum, Some(4) | Some(6) | Some(7) | Some(9));
+        if transparent {
+            let refractive_index = self.refractive_index.filter(|n| *n > 0.0).unwrap_or(1.5);
+            return Ok(Arc::new(Dielectric::new(refractive_index)));
+        }
+
+        let specular = self.specular.unwrap_or_else(Color::black);
+        if self.illum == Some(3) || brightness(specular) > brightness(diffuse) {
+            let albedo = if brightness(specular) > 0.0 {
+                specular
+            } else {
+                diffuse
+            };
+            let shininess = self.shininess.unwrap_or(0.0).clamp(0.0, 1000.0);
+            let fuzz = 1.0 - (shininess / 1000.0).sqrt();
+            return Ok(Arc::new(Metal::new(albedo.to_vec3f(), fuzz)));
+        }
+
+        let albedo = match &self.diffuse_map {
+            Some(path) => match textures.get(path) {
+                Some(texture) => texture.clone(),
+                None => {
+                    let texture: Arc<dyn Texture> =
+                        Arc::new(ImageTexture::open(path).map_err(|error| {
+                            ImportError::invalid(
+                                file,
+                                format!("failed to load texture {}: {}", path.display(), error),
+                            )
+                        })?);
+                    textures.insert(path.clone(), texture.clone());
+                    texture
+                }
+            },
+            None => Arc::new(SolidTexture::new(diffuse)),
+        };
+        Ok(Arc::new(Lambertian::new(albedo)))
+    }
+}
+
+/// Reads the materials of the MTL file at `path`, by name.
+pub fn load_mtl(
+    path: &Path,
+    textures: &mut TextureCache,
+) -> Result<HashMap<String, Arc<dyn Material>>, ImportError> {
+    let source = fs::read_to_string(path).map_err(|error| ImportError::io(path, error))?;
+    parse_mtl(&source, path, textures)
+}
+
+/// Parses MTL `source`, read from `path`. Paths of texture maps are relative to `path`.
+pub fn parse_mtl(
+    source: &str,
+    path: &Path,
+    textures: &mut TextureCache,
+) -> Result<HashMap<String, Arc<dyn Material>>, ImportError> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut parsed: Vec<(String, MtlMaterial)> = Vec::new();
+    for (idx, line) in source.lines().enumerate() {
+        let line_number = idx + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let statement = match tokens.next() {
+            Some(statement) => statement,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if statement == "newmtl" {
+            if args.is_empty() {
+                return Err(ImportError::parse(
+                    path,
+                    line_number,
+                    "missing material name",
+                ));
+            }
+            parsed.push((args.join(" "), MtlMaterial::default()));
+            continue;
+        }
+
+        let material = match parsed.last_mut() {
+            Some((_, material)) => material,
+            None => {
+                return Err(ImportError::parse(
+                    path,
+                    line_number,
+                    format!("`{}` before any `newmtl`", statement),
+                ))
+            }
+        };
+
+        match statement {
+            "Kd" => material.diffuse = Some(parse_color(&args, path, line_number)?),
+            "Ks" => material.specular = Some(parse_color(&args, path, line_number)?),
+            "Ke" => material.emissive = Some(parse_color(&args, path, line_number)?),
+            "Ni" => material.refractive_index = Some(parse_scalar(&args, path, line_number)?),
+            "Ns" => material.shininess = Some(parse_scalar(&args, path, line_number)?),
+            "d" => material.dissolve = Some(parse_scalar(&args, path, line_number)?),
+            "Tr" => material.dissolve = Some(1.0 - parse_scalar(&args, path, line_number)?),
+            "illum" => material.illum = Some(parse_scalar(&args, path, line_number)? as u32),
+            "map_Kd" => {
+                let file_name = map_file_name(&args)
+                    .ok_or_else(|| ImportError::parse(path, line_number, "missing texture file"))?;
+                material.diffuse_map = Some(base_dir.join(file_name.replace('\\', "/")));
+            }
+            // Other statements have no helios equivalent
+            _ => {}
+        }
+    }
+
+    parsed
+        .into_iter()
+        .map(|(name, material)| Ok((name, material.build(path, textures)?)))
+        .collect()
+}
+
+fn parse_color(args: &[&str], file: &Path, line: usize) -> Result<Color, ImportError> {
+    match parse_floats(args, file, line)?.as_slice() {
+        [r, g, b] => Ok(Color::new(*r, *g, *b)),
+        // A single value is a gray level
+        [gray] => Ok(Color::new(*gray, *gray, *gray)),
+        _ => Err(ImportError::parse(
+            file,
+            line,
+            "expected an `r g b` color, spectral and CIEXYZ colors aren't supported",
+        )),
+    }
+}
+
+fn parse_scalar(args: &[&str], file: &Path, line: usize) -> Result<f64, ImportError> {
+    match parse_floats(args, file, line)?.as_slice() {
+        [value] => Ok(*value),
+        _ => Err(ImportError::parse(file, line, "expected a single value")),
+    }
+}
+
+/// The file name of a texture map statement, following its options.
+fn map_file_name(args: &[&str]) -> Option<String> {
+    let mut idx = 0;
+    while idx < args.len() {
+        let option_args = match args[idx] {
+            "-blendu" | "-blendv" | "-cc" | "-clamp" | "-imfchan" | "-texres" | "-boost"
+            | "-bm" => 1,
+            "-mm" => 2,
+            // Up to three numbers
+            "-o" | "-s" | "-t" => args[idx + 1..]
+                .iter()
+                .take(3)
+                .take_while(|arg| arg.parse::<f64>().is_ok())
+                .count(),
+            _ => break,
+        };
+        idx += 1 + option_args;
+    }
+
+    if idx < args.len() {
+        Some(args[idx..].join(" "))
+    } else {
+        None
+    }
+}
+
+fn brightness(color: Color) -> f64 {
+    color.red.max(color.green).max(color.blue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const MTL: &str = "
+        newmtl red
+        Kd 1 0 0
+        newmtl lamp
+        Kd 1 1 1
+        Ke 4 4 2
+        newmtl glass
+        Kd 1 1 1
+        d 0.2
+        Ni 1.33
+        newmtl frosted
+        illum 7
+        newmtl mirror
+        Kd 0.1 0.1 0.1
+        Ks 0.9 0.8 0.7
+        Ns 250
+        newmtl brass
+        Kd 0.6 0.5 0.2
+        illum 3
+        newmtl wood
+        Kd 0.5 0.5 0.5
+        map_Kd -s 2 2 wood.png
+        newmtl floor
+        map_Kd wood.png
+    ";
+
+    #[test]
+    fn test_materials() {
+        let dir = std::env::temp_dir().join(format!("helios-mtl-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        image::RgbImage::new(2, 2)
+            .save(dir.join("wood.png"))
+            .unwrap();
+
+        let path = dir.join("test.mtl");
+        let mut textures = TextureCache::new();
+        let materials = parse_mtl(MTL, &path, &mut textures).unwrap();
+        let describe = |name: &str| serde_json::to_value(materials[name].describe()).unwrap();
+
+        assert_eq!(
+            describe("red"),
+            json!({"type": "lambertian", "albedo": [1.0, 0.0, 0.0]})
+        );
+        assert_eq!(
+            describe("lamp"),
+            json!({"type": "diffuse_light", "emit": [4.0, 4.0, 2.0]})
+        );
+        assert_eq!(
+            describe("glass"),
+            json!({"type": "dielectric", "refractive_index": 1.33})
+        );
+        assert_eq!(
+            describe("frosted"),
+            json!({"type": "dielectric", "refractive_index": 1.5})
+        );
+        // Ns 250 of 1000 leaves half of the fuzz
+        assert_eq!(
+            describe("mirror"),
+            json!({"type": "metal", "albedo": [0.9, 0.8, 0.7], "fuzz": 0.5})
+        );
+        assert_eq!(
+            describe("brass"),
+            json!({"type": "metal", "albedo": [0.6, 0.5, 0.2], "fuzz": 1.0})
+        );
+
+        let texture = json!({"type": "image", "path": dir.join("wood.png")});
+        assert_eq!(
+            describe("wood"),
+            json!({"type": "lambertian", "albedo": texture})
+        );
+        assert_eq!(textures.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let error = parse_mtl("Kd 1 0 0", &path, &mut textures);
+        assert!(matches!(error, Err(ImportError::Parse { line: 1, .. })));
+    }
+}
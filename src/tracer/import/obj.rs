@@ -0,0 +1,411 @@
+use super::mtl::{load_mtl, TextureCache};
+use crate::tracer::geometry::{MeshData, TriangleMesh};
+use crate::tracer::import::ImportError;
+use crate::tracer::material::{Lambertian, Material};
+use crate::tracer::{Color, Point3f, SceneObject, Vector3f};
+use cgmath::*;
+use log::warn;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The faces of an OBJ group sharing the same material.
+pub struct ObjObject {
+    /// Name of the `g`/`o` group, `default` for faces outside of any group
+    pub name: String,
+    /// Name of the MTL material, `None` when the faces don't use one
+    pub material: Option<String>,
+    pub mesh: TriangleMesh,
+}
+
+/// Reads the Wavefront OBJ file at `path`, along with the MTL files it references.
+///
+/// Every group is imported as a separate mesh, split further when its faces use several
+/// materials. Polygons are triangulated as fans, and faces without normals, or with a normal
+/// of zero length, are shaded with their geometric normal. See `MtlMaterial::build` for how
+/// MTL materials are mapped.
+pub fn load_obj(path: &Path) -> Result<Vec<ObjObject>, ImportError> {
+    let source = fs::read_to_string(path).map_err(|error| ImportError::io(path, error))?;
+    parse_obj(&source, path)
+}
+
+/// Parses OBJ `source`, read from `path`. Paths of MTL files are relative to `path`.
+pub fn parse_obj(source: &str, path: &Path) -> Result<Vec<ObjObject>, ImportError> {
+    let mut parser = ObjParser::new(path);
+    for (idx, line) in source.lines().enumerate() {
+        parser.parse_line(line, idx + 1)?;
+    }
+    parser.finish()
+}
+
+/// Every OBJ object as a scene object.
+pub fn obj_scene_objects(objects: Vec<ObjObject>) -> Vec<Arc<dyn SceneObject>> {
+    objects
+        .into_iter()
+        .map(|object| Arc::new(object.mesh) as Arc<dyn SceneObject>)
+        .collect()
+}
+
+/// A face corner as its position, texture coordinate and normal indices
+type ObjVertex = (usize, Option<usize>, Option<usize>);
+
+/// Faces of a group using a single material, with vertices deduplicated into a mesh
+struct MeshBuilder {
+    name: String,
+    material: Option<String>,
+    vertices: HashMap<ObjVertex, u32>,
+    positions: Vec<Point3f>,
+    normals: Vec<Option<Vector3f>>,
+    uvs: Vec<Option<(f64, f64)>>,
+    indices: Vec<[u32; 3]>,
+}
+
+impl MeshBuilder {
+    fn new(name: &str, material: Option<&str>) -> MeshBuilder {
+        MeshBuilder {
+            name: name.to_string(),
+            material: material.map(str::to_string),
+            vertices: HashMap::new(),
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn vertex(&mut self, vertex: ObjVertex, parser: &ObjParser) -> u32 {
+        let positions = &mut self.positions;
+        let normals = &mut self.normals;
+        let uvs = &mut self.uvs;
+        *self.vertices.entry(vertex).or_insert_with(|| {
+            let (position, uv, normal) = vertex;
+            positions.push(parser.positions[position]);
+            uvs.push(uv.map(|idx| parser.uvs[idx]));
+            normals.push(normal.and_then(|idx| parser.normals[idx]));
+            (positions.len() - 1) as u32
+        })
+    }
+
+    fn build(self, material: Arc<dyn Material>) -> MeshData {
+        // Meshes hold normals and uvs for all of their vertices or none of them
+        let normals = self.normals.into_iter().collect::<Option<Vec<_>>>();
+        let uvs = self.uvs.into_iter().collect::<Option<Vec<_>>>();
+
+        MeshData {
+            positions: self.positions,
+            normals: normals.unwrap_or_default(),
+            uvs: uvs.unwrap_or_default(),
+            colors: Vec::new(),
+            indices: self.indices,
+            material,
+        }
+    }
+}
+
+struct ObjParser<'a> {
+    path: &'a Path,
+
+    positions: Vec<Point3f>,
+    uvs: Vec<(f64, f64)>,
+    // `None` for normals of zero length
+    normals: Vec<Option<Vector3f>>,
+
+    materials: HashMap<String, Arc<dyn Material>>,
+    textures: TextureCache,
+
+    group: String,
+    material: Option<String>,
+    meshes: Vec<MeshBuilder>,
+    // Index in `meshes` of the mesh of each group and material
+    mesh_indices: HashMap<(String, Option<String>), usize>,
+}
+
+impl<'a> ObjParser<'a> {
+    fn new(path: &'a Path) -> ObjParser<'a> {
+        ObjParser {
+            path,
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+            group: "default".to_string(),
+            material: None,
+            meshes: Vec::new(),
+            mesh_indices: HashMap::new(),
+        }
+    }
+
+    fn parse_line(&mut self, line: &str, line_number: usize) -> Result<(), ImportError> {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let statement = match tokens.next() {
+            Some(statement) => statement,
+            None => return Ok(()),
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match statement {
+            "v" => match parse_floats(&args, self.path, line_number)?.as_slice() {
+                // An optional w coordinate may follow
+                [x, y, z] | [x, y, z, _] => self.positions.push(Point3::new(*x, *y, *z)),
+                _ => {
+                    return Err(ImportError::parse(
+                        self.path,
+                        line_number,
+                        "expected `v x y z`",
+                    ))
+                }
+            },
+            "vt" => match parse_floats(&args, self.path, line_number)?.as_slice() {
+                [u] => self.uvs.push((*u, 0.0)),
+                [u, v] | [u, v, _] => self.uvs.push((*u, *v)),
+                _ => {
+                    return Err(ImportError::parse(
+                        self.path,
+                        line_number,
+                        "expected `vt u v`",
+                    ))
+                }
+            },
+            "vn" => match parse_floats(&args, self.path, line_number)?.as_slice() {
+                [x, y, z] => {
+                    let normal = vec3(*x, *y, *z);
+                    if normal.magnitude2().is_normal() {
+                        self.normals.push(Some(normal.normalize()));
+                    } else {
+                        warn!(
+                            "{}:{}: normal has no direction, using the face normal",
+                            self.path.display(),
+                            line_number
+                        );
+                        self.normals.push(None);
+                    }
+                }
+                _ => {
+                    return Err(ImportError::parse(
+                        self.path,
+                        line_number,
+                        "expected `vn x y z`",
+                    ))
+                }
+            },
+            "f" => self.parse_face(&args, line_number)?,
+            "g" | "o" => {
+                self.group = if args.is_empty() {
+                    "default".to_string()
+                } else {
+                    args.join(" ")
+                };
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                if !self.materials.contains_key(&name) {
+                    warn!(
+                        "{}:{}: unknown material `{}`, using the default material",
+                        self.path.display(),
+                        line_number,
+                        name
+                    );
+                }
+                self.material = Some(name);
+            }
+            "mtllib" => {
+                let base_dir = self.path.parent().unwrap_or_else(|| Path::new(""));
+                for file_name in args {
+                    let mtl_path = base_dir.join(file_name);
+                    let materials = load_mtl(&mtl_path, &mut self.textures)?;
+                    self.materials.extend(materials);
+                }
+            }
+            // Smoothing groups, lines, points and free-form geometry aren't rendered
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn parse_face(&mut self, args: &[&str], line_number: usize) -> Result<(), ImportError> {
+        if args.len() < 3 {
+            return Err(ImportError::parse(
+                self.path,
+                line_number,
+                "faces need at least 3 vertices",
+            ));
+        }
+
+        let corners = args
+            .iter()
+            .map(|arg| self.parse_vertex(arg, line_number))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let key = (self.group.clone(), self.material.clone());
+        let mesh_idx = match self.mesh_indices.get(&key) {
+            Some(idx) => *idx,
+            None => {
+                self.meshes
+                    .push(MeshBuilder::new(&self.group, self.material.as_deref()));
+                self.mesh_indices.insert(key, self.meshes.len() - 1);
+                self.meshes.len() - 1
+            }
+        };
+
+        // Take the mesh out to add vertices while reading the parser's buffers
+        let mut mesh = std::mem::replace(&mut self.meshes[mesh_idx], MeshBuilder::new("", None));
+        let indices: Vec<u32> = corners
+            .into_iter()
+            .map(|corner| mesh.vertex(corner, self))
+            .collect();
+        for idx in 1..indices.len() - 1 {
+            mesh.indices
+                .push([indices[0], indices[idx], indices[idx + 1]]);
+        }
+        self.meshes[mesh_idx] = mesh;
+
+        Ok(())
+    }
+
+    /// Parses a `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner.
+    fn parse_vertex(&self, arg: &str, line_number: usize) -> Result<ObjVertex, ImportError> {
+        let mut indices = arg.split('/');
+        let position = indices.next().unwrap_or("");
+        let uv = indices.next().filter(|idx| !idx.is_empty());
+        let normal = indices.next().filter(|idx| !idx.is_empty());
+
+        let position = self.resolve_index(position, self.positions.len(), line_number)?;
+        let uv = uv
+            .map(|idx| self.resolve_index(idx, self.uvs.len(), line_number))
+            .transpose()?;
+        let normal = normal
+            .map(|idx| self.resolve_index(idx, self.normals.len(), line_number))
+            .transpose()?;
+        Ok((position, uv, normal))
+    }
+
+    /// Converts a 1-based index, or a negative index relative to the end of the `count`
+    /// elements defined so far, to a 0-based index.
+    fn resolve_index(
+        &self,
+        index: &str,
+        count: usize,
+        line_number: usize,
+    ) -> Result<usize, ImportError> {
+        let index: i64 = index.parse().map_err(|_| {
+            ImportError::parse(self.path, line_number, format!("invalid index `{}`", index))
+        })?;
+
+        let resolved = if index > 0 {
+            index - 1
+        } else {
+            count as i64 + index
+        };
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(ImportError::parse(
+                self.path,
+                line_number,
+                format!("index {} is out of the {} elements defined", index, count),
+            ));
+        }
+
+        Ok(resolved as usize)
+    }
+
+    fn finish(self) -> Result<Vec<ObjObject>, ImportError> {
+        let default_material: Arc<dyn Material> =
+            Arc::new(Lambertian::from_constant(Color::new(0.8, 0.8, 0.8)));
+
+        let path = self.path;
+        let materials = self.materials;
+        self.meshes
+            .into_iter()
+            .map(|mesh| {
+                let material = mesh
+                    .material
+                    .as_ref()
+                    .and_then(|name| materials.get(name))
+                    .unwrap_or(&default_material)
+                    .clone();
+                let name = mesh.name.clone();
+                let material_name = mesh.material.clone();
+
+                let data = mesh.build(material);
+                data.validate().map_err(|error| {
+                    ImportError::invalid(path, format!("group `{}`: {}", name, error))
+                })?;
+
+                Ok(ObjObject {
+                    name,
+                    material: material_name,
+                    mesh: TriangleMesh::new(data),
+                })
+            })
+            .collect()
+    }
+}
+
+pub(super) fn parse_floats(
+    args: &[&str],
+    file: &Path,
+    line: usize,
+) -> Result<Vec<f64>, ImportError> {
+    args.iter()
+        .map(|arg| {
+            arg.parse()
+                .map_err(|_| ImportError::parse(file, line, format!("invalid number `{}`", arg)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::{Intersectable, Ray};
+
+    const OBJ: &str = "
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        vn 0 0 1
+        g quad
+        f 1//1 2//1 3//1 4//1
+        g triangle
+        f -3 -2 -1
+    ";
+
+    #[test]
+    fn test_parse_obj() {
+        let objects = parse_obj(OBJ, Path::new("test.obj")).unwrap();
+        assert_eq!(objects.len(), 2);
+
+        let quad = objects[0].mesh.data();
+        assert_eq!(objects[0].name, "quad");
+        assert_eq!(quad.indices, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(quad.normals.len(), 4);
+
+        // Negative indices are relative to the last vertex, and normals are left out
+        let triangle = objects[1].mesh.data();
+        assert_eq!(objects[1].name, "triangle");
+        assert_eq!(triangle.positions[0], Point3::new(1.0, 0.0, 0.0));
+        assert!(triangle.normals.is_empty());
+    }
+
+    #[test]
+    fn test_zero_normal() {
+        let obj = OBJ.replace("vn 0 0 1", "vn 0 0 0");
+        let objects = parse_obj(&obj, Path::new("test.obj")).unwrap();
+        let quad = &objects[0].mesh;
+        assert!(quad.data().normals.is_empty());
+
+        let ray = Ray::new(Point3::new(0.5, 0.5, 1.0), -Vector3f::unit_z());
+        let hit = quad.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert_eq!(hit.normal, Vector3f::unit_z());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse_obj("v 0 0 0\nf 1 2 3", Path::new("test.obj"));
+        assert!(matches!(error, Err(ImportError::Parse { line: 2, .. })));
+    }
+}
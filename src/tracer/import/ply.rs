@@ -0,0 +1,629 @@
+use crate::tracer::geometry::{MeshData, TriangleMesh};
+use crate::tracer::import::ImportError;
+use crate::tracer::material::{Lambertian, Material, VertexColorTexture};
+use crate::tracer::{Color, Point3f, Vector3f};
+use cgmath::*;
+use std::fs;
+use std::path::Path;
+use std::str::SplitAsciiWhitespace;
+use std::sync::Arc;
+
+/// Reads the PLY file at `path`, in the ascii or binary format, as a triangle mesh.
+///
+/// Vertex positions are read along with the normals, uvs (`u`/`v`, `s`/`t` or `texture_u`/
+/// `texture_v`) and `red`/`green`/`blue` colors when present. Polygons are triangulated as
+/// fans, other elements are skipped.
+///
+/// Without a `material`, the mesh is a `Lambertian` colored by its vertex colors, or gray when
+/// it has none.
+pub fn load_ply(
+    path: &Path,
+    material: Option<Arc<dyn Material>>,
+) -> Result<TriangleMesh, ImportError> {
+    let bytes = fs::read(path).map_err(|error| ImportError::io(path, error))?;
+    let data = parse_ply(&bytes, path, material)?;
+    Ok(TriangleMesh::new(data))
+}
+
+/// Parses the PLY file `bytes`, read from `path`, see `load_ply`.
+pub fn parse_ply(
+    bytes: &[u8],
+    path: &Path,
+    material: Option<Arc<dyn Material>>,
+) -> Result<MeshData, ImportError> {
+    let (header, body) = parse_header(bytes, path)?;
+
+    // Counts come from the header and may be anything, so buffers are only reserved for as
+    // many elements as the body has room for
+    let body_len = body.len();
+    let capacity = |element: &Element| {
+        element
+            .count
+            .min(body_len / element.min_size(header.format))
+    };
+    let vertex_count = header
+        .elements
+        .iter()
+        .find(|element| element.name == "vertex")
+        .map_or(0, |element| element.count);
+
+    let mut body = match header.format {
+        Format::Ascii => {
+            let text = std::str::from_utf8(body)
+                .map_err(|_| ImportError::invalid(path, "ascii body isn't valid text"))?;
+            Body::Ascii(text.split_ascii_whitespace())
+        }
+        Format::BinaryLittleEndian => Body::Binary {
+            data: body,
+            offset: 0,
+            big_endian: false,
+        },
+        Format::BinaryBigEndian => Body::Binary {
+            data: body,
+            offset: 0,
+            big_endian: true,
+        },
+    };
+
+    let mut vertices = VertexBuffers::default();
+    let mut indices: Vec<[u32; 3]> = Vec::new();
+
+    for element in &header.elements {
+        let read_error = |idx: usize, message: String| {
+            ImportError::invalid(path, format!("{} {}: {}", element.name, idx, message))
+        };
+
+        match element.name.as_str() {
+            "vertex" => {
+                let layout = VertexLayout::new(element);
+                if layout.position.iter().any(Option::is_none) {
+                    return Err(ImportError::invalid(
+                        path,
+                        "vertices need x, y and z properties",
+                    ));
+                }
+                vertices.reserve(capacity(element), &layout);
+
+                let mut values = vec![0.0; element.properties.len()];
+                for idx in 0..element.count {
+                    for (value, property) in values.iter_mut().zip(&element.properties) {
+                        *value = match property {
+                            Property::Scalar { ty, .. } => {
+                                body.read(*ty).map_err(|e| read_error(idx, e))?
+                            }
+                            Property::List { count, item, .. } => {
+                                body.skip_list(*count, *item)
+                                    .map_err(|e| read_error(idx, e))?;
+                                0.0
+                            }
+                        };
+                    }
+                    vertices.push(&layout, &values);
+                }
+            }
+            "face" => {
+                let list = element.properties.iter().position(|property| {
+                    matches!(property, Property::List { name, .. }
+                        if name == "vertex_indices" || name == "vertex_index")
+                });
+                let list = list.ok_or_else(|| {
+                    ImportError::invalid(path, "faces need a vertex_indices list")
+                })?;
+                indices.reserve(capacity(element));
+
+                let mut face: Vec<u32> = Vec::new();
+                for idx in 0..element.count {
+                    for (property_idx, property) in element.properties.iter().enumerate() {
+                        match property {
+                            Property::Scalar { ty, .. } => {
+                                body.read(*ty).map_err(|e| read_error(idx, e))?;
+                            }
+                            Property::List { count, item, .. } if property_idx == list => {
+                                let len = body.read(*count).map_err(|e| read_error(idx, e))?;
+                                face.clear();
+                                for _ in 0..len as usize {
+                                    let vertex =
+                                        body.read(*item).map_err(|e| read_error(idx, e))?;
+                                    if !(vertex >= 0.0
+                                        && vertex < vertex_count as f64
+                                        && vertex.fract() == 0.0)
+                                    {
+                                        return Err(read_error(
+                                            idx,
+                                            format!(
+                                                "vertex index {} is out of the {} vertices",
+                                                vertex, vertex_count
+                                            ),
+                                        ));
+                                    }
+                                    face.push(vertex as u32);
+                                }
+                            }
+                            Property::List { count, item, .. } => {
+                                body.skip_list(*count, *item)
+                                    .map_err(|e| read_error(idx, e))?;
+                            }
+                        }
+                    }
+
+                    if face.len() < 3 {
+                        return Err(read_error(idx, "faces need at least 3 vertices".into()));
+                    }
+                    for corner in 1..face.len() - 1 {
+                        indices.push([face[0], face[corner], face[corner + 1]]);
+                    }
+                }
+            }
+            _ => {
+                for idx in 0..element.count {
+                    for property in &element.properties {
+                        match property {
+                            Property::Scalar { ty, .. } => {
+                                body.read(*ty).map_err(|e| read_error(idx, e))?;
+                            }
+                            Property::List { count, item, .. } => {
+                                body.skip_list(*count, *item)
+                                    .map_err(|e| read_error(idx, e))?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let material = material.unwrap_or_else(|| {
+        let gray = Color::new(0.8, 0.8, 0.8);
+        if vertices.colors.is_empty() {
+            Arc::new(Lambertian::from_constant(gray))
+        } else {
+            Arc::new(Lambertian::new(Arc::new(VertexColorTexture::new(gray))))
+        }
+    });
+
+    let data = MeshData {
+        positions: vertices.positions,
+        normals: vertices.normals,
+        uvs: vertices.uvs,
+        colors: vertices.colors,
+        indices,
+        material,
+    };
+    data.validate()
+        .map_err(|error| ImportError::invalid(path, error))?;
+    Ok(data)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Value mapping to a full color channel: integer colors span their type's range
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::I8 => i8::MAX as f64,
+            Scalar::U8 => u8::MAX as f64,
+            Scalar::I16 => i16::MAX as f64,
+            Scalar::U16 => u16::MAX as f64,
+            Scalar::I32 => i32::MAX as f64,
+            Scalar::U32 => u32::MAX as f64,
+            Scalar::F32 | Scalar::F64 => 1.0,
+        }
+    }
+}
+
+enum Property {
+    Scalar {
+        name: String,
+        ty: Scalar,
+    },
+    List {
+        name: String,
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    /// Fewest bytes an element can take in the body: an empty list is its count alone, and
+    /// ascii values are at least a digit and a separator.
+    fn min_size(&self, format: Format) -> usize {
+        let size: usize = self
+            .properties
+            .iter()
+            .map(|property| match (format, property) {
+                (Format::Ascii, _) => 2,
+                (_, Property::Scalar { ty, .. }) => ty.size(),
+                (_, Property::List { count, .. }) => count.size(),
+            })
+            .sum();
+        size.max(1)
+    }
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+/// Parses the header, returning it along with the body following it.
+fn parse_header<'a>(bytes: &'a [u8], path: &Path) -> Result<(Header, &'a [u8]), ImportError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    let mut offset = 0;
+    let mut line_number = 0;
+    loop {
+        let end = match bytes[offset..].iter().position(|&b| b == b'\n') {
+            Some(end) => offset + end,
+            None => return Err(ImportError::invalid(path, "missing end_header")),
+        };
+        let line = String::from_utf8_lossy(&bytes[offset..end]);
+        let line = line.trim();
+        offset = end + 1;
+        line_number += 1;
+
+        let parse_error = |message: &str| ImportError::parse(path, line_number, message);
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        if line_number == 1 {
+            if line != "ply" {
+                return Err(parse_error("not a PLY file"));
+            }
+            continue;
+        }
+
+        match tokens.as_slice() {
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(parse_error("unknown format")),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| parse_error("invalid element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| parse_error("property before any element"))?;
+                element.properties.push(Property::List {
+                    name: name.to_string(),
+                    count: Scalar::parse(count).ok_or_else(|| parse_error("unknown type"))?,
+                    item: Scalar::parse(item).ok_or_else(|| parse_error("unknown type"))?,
+                });
+            }
+            ["property", ty, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| parse_error("property before any element"))?;
+                element.properties.push(Property::Scalar {
+                    name: name.to_string(),
+                    ty: Scalar::parse(ty).ok_or_else(|| parse_error("unknown type"))?,
+                });
+            }
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(parse_error("invalid header line")),
+        }
+    }
+
+    let format = format.ok_or_else(|| ImportError::invalid(path, "missing format"))?;
+    Ok((Header { format, elements }, &bytes[offset..]))
+}
+
+/// Reads the values of the elements following the header
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary {
+        data: &'a [u8],
+        offset: usize,
+        big_endian: bool,
+    },
+}
+
+impl<'a> Body<'a> {
+    fn read(&mut self, ty: Scalar) -> Result<f64, String> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens.next().ok_or("unexpected end of file")?;
+                token
+                    .parse()
+                    .map_err(|_| format!("invalid number `{}`", token))
+            }
+            Body::Binary {
+                data,
+                offset,
+                big_endian,
+            } => {
+                let size = ty.size();
+                if *offset + size > data.len() {
+                    return Err("unexpected end of file".to_string());
+                }
+
+                let mut bytes = [0; 8];
+                bytes[..size].copy_from_slice(&data[*offset..*offset + size]);
+                *offset += size;
+                if *big_endian {
+                    bytes[..size].reverse();
+                }
+
+                Ok(match ty {
+                    Scalar::I8 => bytes[0] as i8 as f64,
+                    Scalar::U8 => bytes[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    Scalar::I32 => {
+                        i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+                    }
+                    Scalar::U32 => {
+                        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+                    }
+                    Scalar::F32 => {
+                        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+                    }
+                    Scalar::F64 => f64::from_le_bytes(bytes),
+                })
+            }
+        }
+    }
+
+    fn skip_list(&mut self, count: Scalar, item: Scalar) -> Result<(), String> {
+        let len = self.read(count)? as usize;
+        for _ in 0..len {
+            self.read(item)?;
+        }
+        Ok(())
+    }
+}
+
+/// Indices of the vertex properties helios uses
+struct VertexLayout {
+    position: [Option<usize>; 3],
+    normal: Option<[usize; 3]>,
+    uv: Option<[usize; 2]>,
+    color: Option<([usize; 3], [f64; 3])>,
+}
+
+impl VertexLayout {
+    fn new(element: &Element) -> VertexLayout {
+        let find = |names: &[&str]| {
+            element.properties.iter().position(|property| {
+                matches!(property, Property::Scalar { name, .. } if names.contains(&name.as_str()))
+            })
+        };
+        let color_scale = |idx: usize| match &element.properties[idx] {
+            Property::Scalar { ty, .. } => ty.color_scale(),
+            Property::List { .. } => 1.0,
+        };
+
+        let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+
+        let normal = match (find(&["nx"]), find(&["ny"]), find(&["nz"])) {
+            (Some(x), Some(y), Some(z)) => Some([x, y, z]),
+            _ => None,
+        };
+
+        let uv = match (
+            find(&["u", "s", "texture_u", "texture_s"]),
+            find(&["v", "t", "texture_v", "texture_t"]),
+        ) {
+            (Some(u), Some(v)) => Some([u, v]),
+            _ => None,
+        };
+
+        let color = match (
+            find(&["red", "r"]),
+            find(&["green", "g"]),
+            find(&["blue", "b"]),
+        ) {
+            (Some(r), Some(g), Some(b)) => {
+                Some(([r, g, b], [color_scale(r), color_scale(g), color_scale(b)]))
+            }
+            _ => None,
+        };
+
+        VertexLayout {
+            position,
+            normal,
+            uv,
+            color,
+        }
+    }
+}
+
+#[derive(Default)]
+struct VertexBuffers {
+    positions: Vec<Point3f>,
+    normals: Vec<Vector3f>,
+    uvs: Vec<(f64, f64)>,
+    colors: Vec<Vector3f>,
+}
+
+impl VertexBuffers {
+    fn reserve(&mut self, count: usize, layout: &VertexLayout) {
+        self.positions.reserve(count);
+        if layout.normal.is_some() {
+            self.normals.reserve(count);
+        }
+        if layout.uv.is_some() {
+            self.uvs.reserve(count);
+        }
+        if layout.color.is_some() {
+            self.colors.reserve(count);
+        }
+    }
+
+    /// Adds the vertex of property `values`
+    fn push(&mut self, layout: &VertexLayout, values: &[f64]) {
+        let [x, y, z] = layout.position.map(|idx| values[idx.unwrap_or_default()]);
+        self.positions.push(Point3::new(x, y, z));
+
+        if let Some([x, y, z]) = layout.normal {
+            let normal = vec3(values[x], values[y], values[z]);
+            // Some exporters write zero normals for isolated vertices
+            self.normals.push(if normal.magnitude2() > 0.0 {
+                normal.normalize()
+            } else {
+                normal
+            });
+        }
+        if let Some([u, v]) = layout.uv {
+            self.uvs.push((values[u], values[v]));
+        }
+        if let Some(([r, g, b], [r_scale, g_scale, b_scale])) = layout.color {
+            self.colors.push(vec3(
+                values[r] / r_scale,
+                values[g] / g_scale,
+                values[b] / b_scale,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ply(format: &str, body: &[u8]) -> Vec<u8> {
+        let header = format!(
+            "ply\nformat {} 1.0\ncomment test\nelement vertex 4\nproperty float x\nproperty float y\n\
+             property float z\nproperty uchar red\nproperty uchar green\nproperty uchar blue\n\
+             element face 1\nproperty list uchar int vertex_indices\nend_header\n",
+            format
+        );
+        let mut bytes = header.into_bytes();
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    fn check_quad(data: &MeshData) {
+        assert_eq!(data.positions[2], Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(data.colors[1], vec3(1.0, 0.0, 0.0));
+        assert_eq!(data.indices, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn test_parse_ascii() {
+        let body = b"0 0 0 0 0 0\n1 0 0 255 0 0\n1 1 0 0 255 0\n0 1 0 0 0 255\n4 0 1 2 3\n";
+        let data = parse_ply(&ply("ascii", body), Path::new("test.ply"), None).unwrap();
+        check_quad(&data);
+    }
+
+    #[test]
+    fn test_parse_binary() {
+        let vertices = [
+            (0.0f32, 0.0f32, [0u8, 0, 0]),
+            (1.0, 0.0, [255, 0, 0]),
+            (1.0, 1.0, [0, 255, 0]),
+            (0.0, 1.0, [0, 0, 255]),
+        ];
+
+        for &(format, big_endian) in &[("binary_little_endian", false), ("binary_big_endian", true)]
+        {
+            let mut body = Vec::new();
+            for (x, y, color) in &vertices {
+                for value in &[*x, *y, 0.0] {
+                    let bytes = if big_endian {
+                        value.to_be_bytes()
+                    } else {
+                        value.to_le_bytes()
+                    };
+                    body.extend_from_slice(&bytes);
+                }
+                body.extend_from_slice(color);
+            }
+            body.push(4);
+            for idx in 0..4i32 {
+                let bytes = if big_endian {
+                    idx.to_be_bytes()
+                } else {
+                    idx.to_le_bytes()
+                };
+                body.extend_from_slice(&bytes);
+            }
+
+            let data = parse_ply(&ply(format, &body), Path::new("test.ply"), None).unwrap();
+            check_quad(&data);
+        }
+    }
+
+    #[test]
+    fn test_invalid_counts() {
+        let invalid = |bytes: &[u8]| match parse_ply(bytes, Path::new("test.ply"), None) {
+            Err(ImportError::Invalid { message, .. }) => message,
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("expected an error"),
+        };
+
+        // Negative and out of range indices name the face instead of wrapping around
+        let body = b"0 0 0 0 0 0\n1 0 0 255 0 0\n1 1 0 0 255 0\n0 1 0 0 0 255\n4 0 1 -1 3\n";
+        assert_eq!(
+            invalid(&ply("ascii", body)),
+            "face 0: vertex index -1 is out of the 4 vertices"
+        );
+        let body = b"0 0 0 0 0 0\n1 0 0 255 0 0\n1 1 0 0 255 0\n0 1 0 0 0 255\n3 0 1 4\n";
+        assert_eq!(
+            invalid(&ply("ascii", body)),
+            "face 0: vertex index 4 is out of the 4 vertices"
+        );
+
+        // A huge count in the header fails on the missing data rather than on allocating it
+        let header = String::from_utf8(ply("binary_little_endian", &[])).unwrap();
+        let header = header.replace("element vertex 4", "element vertex 99999999999");
+        let mut bytes = header.into_bytes();
+        bytes.extend_from_slice(&[0; 64]);
+        assert!(invalid(&bytes).ends_with("unexpected end of file"));
+    }
+}
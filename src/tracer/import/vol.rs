@@ -0,0 +1,199 @@
+use crate::tracer::bounding_volumes::AABB;
+use crate::tracer::import::ImportError;
+use crate::tracer::scene_file::VolumeFormat;
+use crate::tracer::volume::VoxelGrid;
+use cgmath::*;
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+/// Reads the density grid of the Mitsuba `.vol` file at `path`, placed over the bounding box
+/// of its header.
+///
+/// Grids of float32 or uint8 values are supported. Only the first channel of multi-channel
+/// grids is read, uint8 values are scaled to `[0, 1]`.
+pub fn load_vol(path: &Path) -> Result<VoxelGrid, ImportError> {
+    let bytes = fs::read(path).map_err(|error| ImportError::io(path, error))?;
+    let grid = parse_vol(&bytes, path)?;
+    Ok(grid.with_source(path.to_path_buf(), VolumeFormat::Vol))
+}
+
+/// Parses the `.vol` file `bytes`, read from `path`, see `load_vol`.
+pub fn parse_vol(bytes: &[u8], path: &Path) -> Result<VoxelGrid, ImportError> {
+    const HEADER_SIZE: usize = 48;
+    if bytes.len() < HEADER_SIZE || &bytes[0..3] != b"VOL" {
+        return Err(ImportError::invalid(path, "not a .vol file"));
+    }
+    if bytes[3] != 3 {
+        return Err(ImportError::invalid(
+            path,
+            format!("unsupported .vol version {}", bytes[3]),
+        ));
+    }
+
+    let word = |idx: usize| -> [u8; 4] { bytes[4 + 4 * idx..8 + 4 * idx].try_into().unwrap() };
+    let int = |idx: usize| i32::from_le_bytes(word(idx));
+    let float = |idx: usize| f32::from_le_bytes(word(idx)) as f64;
+
+    let encoding = int(0);
+    let resolution = [int(1), int(2), int(3)];
+    let channels = int(4);
+    if resolution.iter().any(|&n| n <= 0) || channels <= 0 {
+        return Err(ImportError::invalid(path, "empty grid"));
+    }
+    let resolution = resolution.map(|n| n as usize);
+    let channels = channels as usize;
+    let bounds = AABB::new(
+        vec3(float(5), float(6), float(7)),
+        vec3(float(8), float(9), float(10)),
+    );
+
+    let data = &bytes[HEADER_SIZE..];
+    let values = match encoding {
+        1 => {
+            expect_size(data, resolution, channels * 4, path)?;
+            data.chunks_exact(4 * channels)
+                .map(|voxel| f32::from_le_bytes(voxel[0..4].try_into().unwrap()))
+                .collect()
+        }
+        3 => {
+            expect_size(data, resolution, channels, path)?;
+            data.chunks_exact(channels)
+                .map(|voxel| voxel[0] as f32 / 255.0)
+                .collect()
+        }
+        _ => {
+            return Err(ImportError::invalid(
+                path,
+                format!("unsupported .vol encoding {}", encoding),
+            ))
+        }
+    };
+
+    Ok(VoxelGrid::new(resolution, values, bounds))
+}
+
+/// Reads a headerless grid of `resolution` values from `path`, with x varying fastest then y
+/// then z, placed over the unit cube.
+///
+/// `format` tells whether the values are uint8, scaled to `[0, 1]`, or little endian float32.
+pub fn load_raw_volume(
+    path: &Path,
+    resolution: [usize; 3],
+    format: VolumeFormat,
+) -> Result<VoxelGrid, ImportError> {
+    if resolution.contains(&0) {
+        return Err(ImportError::invalid(path, "empty grid"));
+    }
+    let bytes = fs::read(path).map_err(|error| ImportError::io(path, error))?;
+
+    let values = match format {
+        VolumeFormat::RawU8 => {
+            expect_size(&bytes, resolution, 1, path)?;
+            bytes.iter().map(|&value| value as f32 / 255.0).collect()
+        }
+        VolumeFormat::RawF32 => {
+            expect_size(&bytes, resolution, 4, path)?;
+            bytes
+                .chunks_exact(4)
+                .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+                .collect()
+        }
+        VolumeFormat::Vol => return load_vol(path),
+    };
+
+    let bounds = AABB::new(vec3(0.0, 0.0, 0.0), vec3(1.0, 1.0, 1.0));
+    Ok(VoxelGrid::new(resolution, values, bounds).with_source(path.to_path_buf(), format))
+}
+
+/// Checks that `data` holds exactly one voxel of `voxel_size` bytes per cell of `resolution`.
+fn expect_size(
+    data: &[u8],
+    resolution: [usize; 3],
+    voxel_size: usize,
+    path: &Path,
+) -> Result<(), ImportError> {
+    let size = resolution
+        .iter()
+        .try_fold(voxel_size, |size, &n| size.checked_mul(n))
+        .ok_or_else(|| ImportError::invalid(path, "grid too large"))?;
+    if data.len() != size {
+        return Err(ImportError::invalid(
+            path,
+            format!(
+                "expected {} bytes of voxel data, found {}",
+                size,
+                data.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::volume::DensityField;
+    use crate::tracer::Point3f;
+
+    #[test]
+    fn test_parse_vol() {
+        let mut bytes = b"VOL\x03".to_vec();
+        for value in [1i32, 2, 1, 1, 1] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [0.0f32, 0.0, 0.0, 2.0, 1.0, 1.0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [0.25f32, 0.75] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let grid = parse_vol(&bytes, Path::new("test.vol")).unwrap();
+        assert_eq!(grid.resolution(), [2, 1, 1]);
+        assert_eq!(grid.max_density(), 0.75);
+        assert_eq!(grid.density(Point3f::new(0.5, 0.5, 0.5)), 0.25);
+        assert_eq!(grid.density(Point3f::new(1.0, 0.5, 0.5)), 0.5);
+        assert_eq!(grid.density(Point3f::new(3.0, 0.5, 0.5)), 0.0);
+
+        let mut oversized = bytes.clone();
+        oversized.extend_from_slice(&1.0f32.to_le_bytes());
+        assert!(parse_vol(&oversized, Path::new("test.vol")).is_err());
+
+        bytes.truncate(bytes.len() - 4);
+        assert!(parse_vol(&bytes, Path::new("test.vol")).is_err());
+
+        let mut huge = bytes[..48].to_vec();
+        huge[8..20].copy_from_slice(&[0xff, 0xff, 0xff, 0x7f].repeat(3));
+        assert!(parse_vol(&huge, Path::new("test.vol")).is_err());
+    }
+
+    #[test]
+    fn test_load_raw_volume() {
+        let dir = std::env::temp_dir().join(format!("helios-vol-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let load = |name: &str, bytes: &[u8], format: VolumeFormat| {
+            let path = dir.join(name);
+            fs::write(&path, bytes).unwrap();
+            load_raw_volume(&path, [2, 2, 2], format)
+        };
+
+        let grid = load("exact.raw", &[255; 8], VolumeFormat::RawU8).unwrap();
+        assert_eq!(grid.max_density(), 1.0);
+        assert!(load("oversized.raw", &[255; 9], VolumeFormat::RawU8).is_err());
+        assert!(load("short.raw", &[255; 7], VolumeFormat::RawU8).is_err());
+
+        let floats: Vec<u8> = [0.5f32; 8].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let grid = load("exact.f32", &floats, VolumeFormat::RawF32).unwrap();
+        assert_eq!(grid.max_density(), 0.5);
+        assert!(load(
+            "oversized.f32",
+            &[floats.clone(), vec![0; 4]].concat(),
+            VolumeFormat::RawF32
+        )
+        .is_err());
+        assert!(load("short.f32", &floats[..28], VolumeFormat::RawF32).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
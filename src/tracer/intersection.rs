@@ -4,6 +4,28 @@ use std::fmt;
 
 pub trait Intersectable: Sync {
     fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection>;
+
+    /// Whether `ray` hits the object between `dist_min` and `dist_max`, for visibility tests
+    /// which need neither the closest hit nor its normal and uvs.
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.intersects(ray, dist_min, dist_max).is_some()
+    }
+
+    /// All the spans of the whole line of `ray` lying inside the object, sorted along the ray
+    /// and not overlapping, with normals pointing out of the object at both ends.
+    ///
+    /// Only closed objects enclosing a volume can be combined by constructive solid geometry,
+    /// the others return `None` whatever the ray.
+    fn intervals(&self, _ray: &Ray) -> Option<Vec<Interval>> {
+        None
+    }
+}
+
+/// A span of a ray inside a closed object, from where it enters to where it leaves.
+#[derive(Clone)]
+pub struct Interval {
+    pub entry: Intersection,
+    pub exit: Intersection,
 }
 
 #[derive(Clone)]
@@ -12,6 +34,17 @@ pub struct Intersection {
     pub point: Point3f,
     pub normal: Vector3f,
     pub uv: (f64, f64),
+    /// Color interpolated from the vertex colors of meshes having them
+    pub color: Option<Vector3f>,
+    /// How the point moves along the uv coordinates, on surfaces parametrized by them
+    pub tangents: Option<Tangents>,
+}
+
+/// Derivatives of a surface point along its u and v coordinates, orienting bump and normal maps.
+#[derive(Copy, Clone, Debug)]
+pub struct Tangents {
+    pub dpdu: Vector3f,
+    pub dpdv: Vector3f,
 }
 
 impl cmp::PartialEq for Intersection {
@@ -1,4 +1,5 @@
 use crate::tracer::material::{Material, ScatteredRay, Texture};
+use crate::tracer::scene_file::{DiffuseLightDescription, MaterialDescription};
 use crate::tracer::{Intersection, Point3f, Ray, Vector3f};
 use std::sync::Arc;
 
@@ -20,4 +21,10 @@ impl Material for DiffuseLight {
     fn emitted(&self, _ray_in: &Ray, u: f64, v: f64, p: Point3f) -> Vector3f {
         self.texture.texture_value(u, v, p)
     }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::DiffuseLight(DiffuseLightDescription {
+            emit: self.texture.describe()?.into(),
+        }))
+    }
 }
@@ -1,3 +1,5 @@
 mod diffuse_light;
+mod sky;
 
 pub use diffuse_light::*;
+pub use sky::*;
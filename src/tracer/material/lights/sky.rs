@@ -0,0 +1,43 @@
+use crate::tracer::material::{Material, ScatteredRay};
+use crate::tracer::scene_file::{MaterialDescription, SkyDescription};
+use crate::tracer::{Color, Intersection, Point3f, Ray, Vector3f};
+use cgmath::*;
+
+/// Background material emitting a vertical gradient from `horizon` (looking down) to `zenith`
+/// (looking up).
+#[derive(Copy, Clone, Debug)]
+pub struct Sky {
+    pub horizon: Color,
+    pub zenith: Color,
+}
+
+impl Sky {
+    pub fn new(horizon: Color, zenith: Color) -> Sky {
+        Sky { horizon, zenith }
+    }
+}
+
+impl Default for Sky {
+    fn default() -> Sky {
+        Sky::new(Color::white(), Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Material for Sky {
+    fn scatter(&self, _ray_in: &Ray, _hit: &Intersection) -> Option<ScatteredRay> {
+        None
+    }
+
+    fn emitted(&self, ray_in: &Ray, _u: f64, _v: f64, _p: Point3f) -> Vector3f {
+        let unit_dir = ray_in.direction.normalize();
+        let t = 0.5 * (unit_dir.y + 1.0);
+        self.horizon.to_vec3f() * (1.0 - t) + self.zenith.to_vec3f() * t
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Sky(SkyDescription {
+            horizon: self.horizon.into(),
+            zenith: self.zenith.into(),
+        }))
+    }
+}
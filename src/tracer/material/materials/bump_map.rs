@@ -0,0 +1,138 @@
+use super::utils::surface_tangents;
+use crate::tracer::material::{Material, ScatteredRay, Texture};
+use crate::tracer::scene_file::{BumpMapDescription, MaterialDescription, MaterialRef};
+use crate::tracer::{Intersection, Point3f, Ray, Vector3f};
+use cgmath::*;
+use std::sync::Arc;
+
+/// A material whose surface looks bumpy, shading it as if it was displaced along its normal by
+/// `scale` times the `height` texture, averaged over its channels.
+///
+/// Only normals are changed, silhouettes and shadows stay those of the smooth surface. Heights
+/// are compared along the uv tangents of the surface, or along an arbitrary frame on surfaces
+/// without them, where only textures depending on the point look right.
+pub struct BumpMap {
+    material: Arc<dyn Material>,
+    height: Arc<dyn Texture>,
+    scale: f64,
+}
+
+impl BumpMap {
+    /// Distance in uv coordinates over which height slopes are measured
+    const DELTA: f64 = 1e-3;
+
+    pub fn new(material: Arc<dyn Material>, height: Arc<dyn Texture>, scale: f64) -> BumpMap {
+        BumpMap {
+            material,
+            height,
+            scale,
+        }
+    }
+
+    fn displacement(&self, u: f64, v: f64, p: Point3f) -> f64 {
+        let height = self.height.texture_value(u, v, p);
+        self.scale * (height.x + height.y + height.z) / 3.0
+    }
+
+    /// Normal of the displaced surface at `hit`.
+    fn bumped_normal(&self, hit: &Intersection) -> Vector3f {
+        let tangents = surface_tangents(hit);
+        let (u, v) = hit.uv;
+        let delta = BumpMap::DELTA;
+
+        let displacement = self.displacement(u, v, hit.point);
+        let u_slope = (self.displacement(u + delta, v, hit.point + tangents.dpdu * delta)
+            - displacement)
+            / delta;
+        let v_slope = (self.displacement(u, v + delta, hit.point + tangents.dpdv * delta)
+            - displacement)
+            / delta;
+
+        let dpdu = tangents.dpdu + hit.normal * u_slope;
+        let dpdv = tangents.dpdv + hit.normal * v_slope;
+        let normal = dpdu.cross(dpdv).normalize();
+        // Tangents don't say which side the surface faces
+        if normal.dot(hit.normal) < 0.0 {
+            -normal
+        } else {
+            normal
+        }
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(&self, ray_in: &Ray, hit: &Intersection) -> Option<ScatteredRay> {
+        let bumped = Intersection {
+            normal: self.bumped_normal(hit),
+            ..hit.clone()
+        };
+        self.material.scatter(ray_in, &bumped)
+    }
+
+    fn emitted(&self, ray_in: &Ray, u: f64, v: f64, p: Point3f) -> Vector3f {
+        self.material.emitted(ray_in, u, v, p)
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::BumpMap(BumpMapDescription {
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+            height: self.height.describe()?.into(),
+            scale: self.scale,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::{Lambertian, SolidTexture};
+    use crate::tracer::{Color, Tangents};
+
+    /// Heights rising along u
+    struct Ramp;
+
+    impl Texture for Ramp {
+        fn texture_value(&self, u: f64, _v: f64, _p: Point3f) -> Vector3f {
+            vec3(u, u, u)
+        }
+    }
+
+    #[test]
+    fn test_bumped_normal() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let hit = Intersection {
+            dist: 1.0,
+            point: Point3::new(0.0, 0.0, 0.0),
+            normal: vec3(0.0, 0.0, 1.0),
+            uv: (0.5, 0.5),
+            color: None,
+            tangents: Some(Tangents {
+                dpdu: vec3(1.0, 0.0, 0.0),
+                dpdv: vec3(0.0, 1.0, 0.0),
+            }),
+        };
+
+        // Flat heights keep the normal
+        let flat = BumpMap::new(
+            material.clone(),
+            Arc::new(SolidTexture::new(Color::white())),
+            1.0,
+        );
+        assert!((flat.bumped_normal(&hit) - hit.normal).magnitude() < 1e-12);
+
+        // A 45° slope going up along x tilts the normal towards -x
+        let ramp = BumpMap::new(material.clone(), Arc::new(Ramp), 1.0);
+        let expected = vec3(-1.0, 0.0, 1.0).normalize();
+        assert!((ramp.bumped_normal(&hit) - expected).magnitude() < 1e-9);
+
+        // Whichever way the tangents turn
+        let mirrored = Intersection {
+            tangents: Some(Tangents {
+                dpdu: vec3(1.0, 0.0, 0.0),
+                dpdv: vec3(0.0, -1.0, 0.0),
+            }),
+            ..hit.clone()
+        };
+        assert!((ramp.bumped_normal(&mirrored) - expected).magnitude() < 1e-9);
+    }
+}
@@ -1,6 +1,7 @@
 use super::utils::*;
 use crate::tracer::material::{Material, ScatteredRay};
-use crate::tracer::{Intersection, Ray};
+use crate::tracer::scene_file::{DielectricDescription, MaterialDescription};
+use crate::tracer::{random, with_rng, Intersection, Ray};
 use cgmath::*;
 use rand::prelude::*;
 
@@ -22,16 +23,14 @@ impl Dielectric {
 
     /// Create a Dielectric material with reflective index of 1.3-1.7
     pub fn new_glass() -> Dielectric {
-        let mut rng = rand::thread_rng();
-        let reflective_idx: f64 = rng.gen_range(1.3, 1.7);
+        let reflective_idx: f64 = with_rng(|rng| rng.gen_range(1.3, 1.7));
         Dielectric::new(reflective_idx)
     }
 
     /// Create a Dielectric material with reflective index of 2.35-245
     #[allow(dead_code)]
     pub fn new_diamond() -> Dielectric {
-        let mut rng = rand::thread_rng();
-        let reflective_idx: f64 = rng.gen_range(2.35, 2.45);
+        let reflective_idx: f64 = with_rng(|rng| rng.gen_range(2.35, 2.45));
         Dielectric::new(reflective_idx)
     }
 }
@@ -63,8 +62,7 @@ impl Material for Dielectric {
         if let Some(refracted) = refract(ray_in.direction, outward_normal, ni_over_nt) {
             let reflect_prob = schlick(cosine, self.reflective_idx);
 
-            let mut rng = rand::thread_rng();
-            let r: f64 = rng.gen();
+            let r: f64 = random();
             if r < reflect_prob {
                 scatter_ray_direction = reflected;
             } else {
@@ -76,7 +74,13 @@ impl Material for Dielectric {
 
         Some(ScatteredRay {
             attenuation,
-            ray: Ray::new(hit.point, scatter_ray_direction),
+            ray: Ray::with_time(hit.point, scatter_ray_direction, ray_in.time),
         })
     }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Dielectric(DielectricDescription {
+            refractive_index: self.reflective_idx,
+        }))
+    }
 }
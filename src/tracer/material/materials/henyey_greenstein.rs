@@ -0,0 +1,83 @@
+use crate::tracer::material::{Material, ScatteredRay, SolidTexture, Texture};
+use crate::tracer::scene_file::{HenyeyGreensteinDescription, MaterialDescription};
+use crate::tracer::{orthonormal_basis, random, Color, Intersection, Ray, Vector3f};
+use cgmath::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Henyey-Greenstein phase function of participating media, scattering light mostly forward
+/// when the asymmetry `g` is positive and mostly backward when it's negative. A `g` of 0
+/// scatters equally in all directions.
+#[derive(Clone)]
+pub struct HenyeyGreenstein {
+    albedo: Arc<dyn Texture>,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Arc<dyn Texture>, g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein { albedo, g }
+    }
+
+    pub fn from_constant(c: Color, g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein::new(Arc::new(SolidTexture::new(c)), g)
+    }
+
+    /// Samples a direction scattered from the unit `forward` direction.
+    fn sample_direction(&self, forward: Vector3f) -> Vector3f {
+        let g = self.g;
+        let xi: f64 = random();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let ratio = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            (1.0 + g * g - ratio * ratio) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random::<f64>();
+
+        let (tangent, bitangent) = orthonormal_basis(forward);
+        forward * cos_theta + (tangent * phi.cos() + bitangent * phi.sin()) * sin_theta
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, ray_in: &Ray, hit: &Intersection) -> Option<ScatteredRay> {
+        let direction = self.sample_direction(ray_in.direction.normalize());
+        Some(ScatteredRay {
+            attenuation: self.albedo.hit_value(hit),
+            ray: Ray::with_time(hit.point, direction, ray_in.time),
+        })
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::HenyeyGreenstein(
+            HenyeyGreensteinDescription {
+                albedo: self.albedo.describe()?.into(),
+                g: self.g,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::seed_rng;
+
+    #[test]
+    fn test_mean_cosine() {
+        seed_rng(11);
+        // The mean cosine of the scattering angle is g
+        let forward = vec3(1.0, 2.0, -0.5).normalize();
+        for &g in &[-0.6, 0.0, 0.3, 0.9] {
+            let phase = HenyeyGreenstein::from_constant(Color::white(), g);
+            let samples = 20_000;
+            let mean = (0..samples)
+                .map(|_| phase.sample_direction(forward).dot(forward))
+                .sum::<f64>()
+                / samples as f64;
+            assert!((mean - g).abs() < 0.02, "g {} gave {}", g, mean);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use crate::tracer::material::{Material, ScatteredRay, SolidTexture, Texture};
+use crate::tracer::scene_file::{IsotropicDescription, MaterialDescription};
+use crate::tracer::{random_on_unit_sphere, Color, Intersection, Ray};
+use std::sync::Arc;
+
+/// Phase function of participating media scattering light equally in all directions.
+#[derive(Clone)]
+pub struct Isotropic {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Arc<dyn Texture>) -> Isotropic {
+        Isotropic { albedo }
+    }
+
+    pub fn from_constant(c: Color) -> Isotropic {
+        Isotropic::new(Arc::new(SolidTexture::new(c)))
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, ray_in: &Ray, hit: &Intersection) -> Option<ScatteredRay> {
+        Some(ScatteredRay {
+            attenuation: self.albedo.hit_value(hit),
+            ray: Ray::with_time(hit.point, random_on_unit_sphere(), ray_in.time),
+        })
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Isotropic(IsotropicDescription {
+            albedo: self.albedo.describe()?.into(),
+        }))
+    }
+}
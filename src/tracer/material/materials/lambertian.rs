@@ -1,4 +1,6 @@
+use super::utils::facing_normal;
 use crate::tracer::material::{Material, ScatteredRay, SolidTexture, Texture};
+use crate::tracer::scene_file::{LambertianDescription, MaterialDescription};
 use crate::tracer::{random_in_unit_sphere, Color, Intersection, Ray};
 use std::sync::Arc;
 
@@ -22,10 +24,10 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray_in: &Ray, hit: &Intersection) -> Option<ScatteredRay> {
-        let reflection = Ray::new(hit.point, hit.normal + random_in_unit_sphere());
-        let (u, v) = hit.uv;
-        let attenuation = self.albedo.texture_value(u, v, reflection.origin);
+    fn scatter(&self, ray_in: &Ray, hit: &Intersection) -> Option<ScatteredRay> {
+        let normal = facing_normal(ray_in.direction, hit.normal);
+        let reflection = Ray::with_time(hit.point, normal + random_in_unit_sphere(), ray_in.time);
+        let attenuation = self.albedo.hit_value(hit);
 
         let scatter = ScatteredRay {
             attenuation,
@@ -34,4 +36,10 @@ impl Material for Lambertian {
 
         Some(scatter)
     }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Lambertian(LambertianDescription {
+            albedo: self.albedo.describe()?.into(),
+        }))
+    }
 }
@@ -1,6 +1,7 @@
-use super::utils::reflect;
+use super::utils::{facing_normal, reflect};
 use crate::tracer::material::{Material, ScatteredRay};
 use crate::tracer::math::random_in_unit_sphere;
+use crate::tracer::scene_file::{MaterialDescription, MetalDescription};
 use crate::tracer::{Intersection, Ray, Vector3f};
 use cgmath::*;
 
@@ -22,12 +23,17 @@ impl Metal {
 
 impl Material for Metal {
     fn scatter(&self, ray_in: &Ray, hit: &Intersection) -> Option<ScatteredRay> {
+        let normal = facing_normal(ray_in.direction, hit.normal);
         let unit_in_direction = ray_in.direction.normalize();
-        let reflected = reflect(unit_in_direction, hit.normal);
+        let reflected = reflect(unit_in_direction, normal);
 
-        let scattered = Ray::new(hit.point, reflected + random_in_unit_sphere() * self.fuzz);
+        let scattered = Ray::with_time(
+            hit.point,
+            reflected + random_in_unit_sphere() * self.fuzz,
+            ray_in.time,
+        );
 
-        let is_scattered = scattered.direction.dot(hit.normal) > 0.0;
+        let is_scattered = scattered.direction.dot(normal) > 0.0;
         if is_scattered {
             return Some(ScatteredRay {
                 attenuation: self.albedo,
@@ -37,4 +43,11 @@ impl Material for Metal {
 
         None
     }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Metal(MetalDescription {
+            albedo: self.albedo.into(),
+            fuzz: self.fuzz,
+        }))
+    }
 }
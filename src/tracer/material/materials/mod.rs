@@ -1,8 +1,16 @@
+mod bump_map;
 mod dielectric;
+mod henyey_greenstein;
+mod isotropic;
 mod lambertian;
 mod metal;
+mod normal_map;
 mod utils;
 
+pub use bump_map::*;
 pub use dielectric::*;
+pub use henyey_greenstein::*;
+pub use isotropic::*;
 pub use lambertian::*;
 pub use metal::*;
+pub use normal_map::*;
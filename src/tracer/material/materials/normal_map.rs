@@ -0,0 +1,62 @@
+use super::utils::surface_tangents;
+use crate::tracer::material::{Material, ScatteredRay, Texture};
+use crate::tracer::scene_file::{MaterialDescription, MaterialRef, NormalMapDescription};
+use crate::tracer::{Intersection, Point3f, Ray, Vector3f};
+use cgmath::*;
+use std::sync::Arc;
+
+/// A material shaded with the tangent space normals of a `normals` texture, usually an image.
+///
+/// The red, green and blue channels map from `[0, 1]` to the `[-1, 1]` coordinates of normals
+/// along the u tangent, the v tangent and the surface normal, so flat regions are light blue.
+pub struct NormalMap {
+    material: Arc<dyn Material>,
+    normals: Arc<dyn Texture>,
+}
+
+impl NormalMap {
+    pub fn new(material: Arc<dyn Material>, normals: Arc<dyn Texture>) -> NormalMap {
+        NormalMap { material, normals }
+    }
+
+    /// Normal of the texture at `hit`, in world space.
+    fn mapped_normal(&self, hit: &Intersection) -> Vector3f {
+        let tangents = surface_tangents(hit);
+        let normal = hit.normal;
+
+        // Orthonormal frame following the u tangent, with the v tangent on the same side
+        let tangent = (tangents.dpdu - normal * normal.dot(tangents.dpdu)).normalize();
+        let mut bitangent = normal.cross(tangent);
+        if bitangent.dot(tangents.dpdv) < 0.0 {
+            bitangent = -bitangent;
+        }
+
+        let value = self.normals.hit_value(hit) * 2.0 - vec3(1.0, 1.0, 1.0);
+        let mapped = tangent * value.x + bitangent * value.y + normal * value.z;
+        if mapped.magnitude2() == 0.0 {
+            return normal;
+        }
+        mapped.normalize()
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(&self, ray_in: &Ray, hit: &Intersection) -> Option<ScatteredRay> {
+        let mapped = Intersection {
+            normal: self.mapped_normal(hit),
+            ..hit.clone()
+        };
+        self.material.scatter(ray_in, &mapped)
+    }
+
+    fn emitted(&self, ray_in: &Ray, u: f64, v: f64, p: Point3f) -> Vector3f {
+        self.material.emitted(ray_in, u, v, p)
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::NormalMap(NormalMapDescription {
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+            normals: self.normals.describe()?.into(),
+        }))
+    }
+}
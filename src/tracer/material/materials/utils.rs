@@ -1,4 +1,4 @@
-use crate::tracer::Vector3f;
+use crate::tracer::{orthonormal_basis, Intersection, Tangents, Vector3f};
 use cgmath::*;
 
 pub fn schlick(cosine: f64, ref_idx: f64) -> f64 {
@@ -7,6 +7,28 @@ pub fn schlick(cosine: f64, ref_idx: f64) -> f64 {
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
 
+/// Flips `normal` if needed so that it faces against the incoming ray, for surfaces that can be
+/// hit from either side.
+pub fn facing_normal(ray_direction: Vector3f, normal: Vector3f) -> Vector3f {
+    if ray_direction.dot(normal) > 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
+
+/// Tangents of `hit`, or an arbitrary frame around its normal on surfaces without a usable
+/// parametrization, such as the poles of spheres.
+pub fn surface_tangents(hit: &Intersection) -> Tangents {
+    match hit.tangents {
+        Some(tangents) if tangents.dpdu.cross(tangents.dpdv).magnitude2() > 0.0 => tangents,
+        _ => {
+            let (dpdu, dpdv) = orthonormal_basis(hit.normal);
+            Tangents { dpdu, dpdv }
+        }
+    }
+}
+
 pub fn reflect(v: Vector3f, normal: Vector3f) -> Vector3f {
     let d2 = v.dot(normal) * 2.0;
     v - normal * d2
@@ -23,3 +45,45 @@ pub fn refract(v: Vector3f, n: Vector3f, ni_over_nt: f64) -> Option<Vector3f> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::{Lambertian, Material, Metal};
+    use crate::tracer::{random_in_unit_sphere, seed_rng, Color, Point3f, Ray};
+
+    fn hit(normal: Vector3f) -> Intersection {
+        Intersection {
+            dist: 1.0,
+            point: Point3f::new(0.0, 0.0, 0.0),
+            normal,
+            uv: (0.0, 0.0),
+            color: None,
+            tangents: None,
+        }
+    }
+
+    #[test]
+    fn test_back_faces() {
+        let lambertian = Lambertian::from_constant(Color::white());
+        let metal = Metal::new(vec3(0.8, 0.8, 0.8), 0.3);
+        let up = Vector3f::unit_y();
+
+        // Front faces scatter along the outward normal, drawing the same random numbers
+        let down = Ray::new(Point3f::new(0.0, 1.0, 0.0), -up);
+        seed_rng(5);
+        let scattered = lambertian.scatter(&down, &hit(up)).unwrap();
+        seed_rng(5);
+        assert_eq!(scattered.ray.direction, up + random_in_unit_sphere());
+
+        // Back faces scatter back towards the side of the incoming ray
+        let rising = Ray::new(Point3f::new(0.0, -1.0, 0.0), vec3(0.3, 1.0, 0.0));
+        for _ in 0..100 {
+            let scattered = lambertian.scatter(&rising, &hit(up)).unwrap();
+            assert!(scattered.ray.direction.dot(up) < 0.0);
+            if let Some(scattered) = metal.scatter(&rising, &hit(up)) {
+                assert!(scattered.ray.direction.dot(up) < 0.0);
+            }
+        }
+    }
+}
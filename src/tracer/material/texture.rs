@@ -1,5 +1,16 @@
-use crate::tracer::{Point3f, Vector3f};
+use crate::tracer::scene_file::TextureDescription;
+use crate::tracer::{Intersection, Point3f, Vector3f};
 
 pub trait Texture: Sync + Send {
     fn texture_value(&self, u: f64, v: f64, p: Point3f) -> Vector3f;
+
+    /// Value of the texture at a ray hit, for textures depending on more than its uv and point.
+    fn hit_value(&self, hit: &Intersection) -> Vector3f {
+        self.texture_value(hit.uv.0, hit.uv.1, hit.point)
+    }
+
+    /// Scene file description of this texture, `None` when it can't be exported.
+    fn describe(&self) -> Option<TextureDescription> {
+        None
+    }
 }
@@ -1,5 +1,6 @@
 use crate::tracer::material::{SolidTexture, Texture};
-use crate::tracer::{Color, Point3f, Vector3f};
+use crate::tracer::scene_file::{CheckersTextureDescription, TextureDescription};
+use crate::tracer::{Color, Intersection, Point3f, Vector3f};
 use std::sync::Arc;
 
 pub struct CheckersTexture {
@@ -9,7 +10,6 @@ pub struct CheckersTexture {
 }
 
 impl CheckersTexture {
-    #[allow(dead_code)]
     pub fn new(odd: Arc<dyn Texture>, even: Arc<dyn Texture>, scale: f64) -> CheckersTexture {
         CheckersTexture { odd, even, scale }
     }
@@ -23,10 +23,7 @@ impl CheckersTexture {
 
 impl Texture for CheckersTexture {
     fn texture_value(&self, u: f64, v: f64, p: Point3f) -> Vector3f {
-        let pt = p * 10.0;
-        let sines = pt.x.sin() * pt.y.sin() * pt.z.sin();
-
-        if sines < 0.0 {
+        if is_odd(p) {
             return self.odd.texture_value(u, v, p);
         }
 
@@ -41,4 +38,26 @@ impl Texture for CheckersTexture {
         //
         //        self.even.texture_value(u, v, p)
     }
+
+    fn hit_value(&self, hit: &Intersection) -> Vector3f {
+        if is_odd(hit.point) {
+            return self.odd.hit_value(hit);
+        }
+
+        self.even.hit_value(hit)
+    }
+
+    fn describe(&self) -> Option<TextureDescription> {
+        Some(TextureDescription::Checkers(CheckersTextureDescription {
+            odd: self.odd.describe()?.into(),
+            even: self.even.describe()?.into(),
+            scale: self.scale,
+        }))
+    }
+}
+
+fn is_odd(p: Point3f) -> bool {
+    let pt = p * 10.0;
+    let sines = pt.x.sin() * pt.y.sin() * pt.z.sin();
+    sines < 0.0
 }
@@ -0,0 +1,55 @@
+use crate::tracer::material::Texture;
+use crate::tracer::scene_file::{ImageTextureDescription, TextureDescription};
+use crate::tracer::{Point3f, Vector3f};
+use cgmath::*;
+use image::{ImageResult, RgbImage};
+use std::path::{Path, PathBuf};
+
+/// An image mapped over the surface uv coordinates, repeating outside of `[0, 1]`.
+pub struct ImageTexture {
+    image: RgbImage,
+    path: Option<PathBuf>,
+}
+
+impl ImageTexture {
+    pub fn new(image: RgbImage) -> ImageTexture {
+        ImageTexture { image, path: None }
+    }
+
+    /// Loads the image file at `path`, which is kept to describe the texture.
+    pub fn open(path: &Path) -> ImageResult<ImageTexture> {
+        let image = image::open(path)?.to_rgb8();
+        Ok(ImageTexture {
+            image,
+            path: Some(path.to_path_buf()),
+        })
+    }
+}
+
+impl Texture for ImageTexture {
+    fn texture_value(&self, u: f64, v: f64, _p: Point3f) -> Vector3f {
+        let (width, height) = self.image.dimensions();
+        if width == 0 || height == 0 {
+            return vec3(0.0, 1.0, 1.0);
+        }
+
+        // Image rows go top to bottom while v goes up
+        let u = u - u.floor();
+        let v = 1.0 - (v - v.floor());
+        let x = ((u * width as f64) as u32).min(width - 1);
+        let y = ((v * height as f64) as u32).min(height - 1);
+
+        let pixel = self.image.get_pixel(x, y);
+        vec3(
+            pixel[0] as f64 / 255.0,
+            pixel[1] as f64 / 255.0,
+            pixel[2] as f64 / 255.0,
+        )
+    }
+
+    fn describe(&self) -> Option<TextureDescription> {
+        Some(TextureDescription::Image(ImageTextureDescription {
+            path: self.path.clone()?,
+        }))
+    }
+}
@@ -1,9 +1,13 @@
 mod checkers;
+mod image;
 mod noise;
 mod solid;
+mod vertex_color;
 
 pub mod noises;
 
 pub use self::noise::*;
 pub use checkers::*;
+pub use image::*;
 pub use solid::*;
+pub use vertex_color::*;
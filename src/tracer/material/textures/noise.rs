@@ -1,5 +1,6 @@
 use super::noises::PerlinNoise;
 use crate::tracer::material::Texture;
+use crate::tracer::scene_file::{NoiseTextureDescription, TextureDescription};
 use crate::tracer::{Color, Point3f, Vector3f};
 use cgmath::*;
 
@@ -29,9 +30,10 @@ impl NoiseTexture {
             color_end,
         }
     }
+}
 
-    #[allow(dead_code)]
-    pub fn default() -> NoiseTexture {
+impl Default for NoiseTexture {
+    fn default() -> NoiseTexture {
         let perlin = PerlinNoise::default();
 
         NoiseTexture {
@@ -62,4 +64,16 @@ impl Texture for NoiseTexture {
 
         vec3(r, g, b)
     }
+
+    fn describe(&self) -> Option<TextureDescription> {
+        Some(TextureDescription::Noise(NoiseTextureDescription {
+            scale: self.scale,
+            octaves: self.noise.octaves(),
+            frequency: self.noise.frequency(),
+            persistence: self.noise.persistence(),
+            lacunarity: self.noise.lacunarity(),
+            color_start: self.color_start.into(),
+            color_end: self.color_end.into(),
+        }))
+    }
 }
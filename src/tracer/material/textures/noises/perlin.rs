@@ -1,7 +1,7 @@
 use crate::tracer::Vector3f;
 use noise::{NoiseFn, Perlin};
 
-#[derive(Default, Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone)]
 pub struct PerlinNoise {
     /// Octaves are the number of layers of coherent noise used in the generation of perlin noise.
     /// The default value for this is 8.
@@ -33,9 +33,20 @@ impl PerlinNoise {
         }
     }
 
-    #[allow(dead_code)]
-    pub fn default() -> PerlinNoise {
-        PerlinNoise::new(8, 1.0, 0.5, 2.0)
+    pub fn octaves(&self) -> u32 {
+        self.octaves
+    }
+
+    pub fn frequency(&self) -> f64 {
+        self.freq
+    }
+
+    pub fn persistence(&self) -> f64 {
+        self.pers
+    }
+
+    pub fn lacunarity(&self) -> f64 {
+        self.lacu
     }
 
     pub fn noise(&self, p: Vector3f) -> f64 {
@@ -99,3 +110,9 @@ impl PerlinNoise {
         total / max_value
     }
 }
+
+impl Default for PerlinNoise {
+    fn default() -> PerlinNoise {
+        PerlinNoise::new(8, 1.0, 0.5, 2.0)
+    }
+}
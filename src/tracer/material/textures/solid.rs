@@ -1,4 +1,5 @@
 use crate::tracer::material::Texture;
+use crate::tracer::scene_file::{SolidTextureDescription, TextureDescription};
 use crate::tracer::{Color, Point3f, Vector3f};
 
 pub struct SolidTexture {
@@ -15,4 +16,10 @@ impl Texture for SolidTexture {
     fn texture_value(&self, _u: f64, _v: f64, _p: Point3f) -> Vector3f {
         self.color.to_vec3f()
     }
+
+    fn describe(&self) -> Option<TextureDescription> {
+        Some(TextureDescription::Solid(SolidTextureDescription {
+            color: self.color.into(),
+        }))
+    }
 }
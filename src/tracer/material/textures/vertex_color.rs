@@ -0,0 +1,34 @@
+use crate::tracer::material::Texture;
+use crate::tracer::scene_file::{TextureDescription, VertexColorTextureDescription};
+use crate::tracer::{Color, Intersection, Point3f, Vector3f};
+
+/// The vertex colors of the mesh hit, interpolated over its triangles.
+///
+/// Objects without vertex colors get the `fallback` color.
+pub struct VertexColorTexture {
+    fallback: Color,
+}
+
+impl VertexColorTexture {
+    pub fn new(fallback: Color) -> VertexColorTexture {
+        VertexColorTexture { fallback }
+    }
+}
+
+impl Texture for VertexColorTexture {
+    fn texture_value(&self, _u: f64, _v: f64, _p: Point3f) -> Vector3f {
+        self.fallback.to_vec3f()
+    }
+
+    fn hit_value(&self, hit: &Intersection) -> Vector3f {
+        hit.color.unwrap_or_else(|| self.fallback.to_vec3f())
+    }
+
+    fn describe(&self) -> Option<TextureDescription> {
+        Some(TextureDescription::VertexColor(
+            VertexColorTextureDescription {
+                fallback: self.fallback.into(),
+            },
+        ))
+    }
+}
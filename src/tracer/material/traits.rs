@@ -1,3 +1,4 @@
+use crate::tracer::scene_file::MaterialDescription;
 use crate::tracer::{Intersection, Point3f, Ray, Vector3f};
 use cgmath::*;
 
@@ -16,4 +17,9 @@ pub trait Material: Sync + Send {
     fn emitted(&self, _ray_in: &Ray, _u: f64, _v: f64, _p: Point3f) -> Vector3f {
         vec3(0.0, 0.0, 0.0)
     }
+
+    /// Scene file description of this material, `None` when it can't be exported.
+    fn describe(&self) -> Option<MaterialDescription> {
+        None
+    }
 }
@@ -1,38 +1,64 @@
 use cgmath::*;
+use rand::distributions::{Distribution, Standard};
 use rand::prelude::*;
+use rand::rngs::SmallRng;
+use std::cell::RefCell;
 
 pub type Point3f = Point3<f64>;
 pub type Vector3f = Vector3<f64>;
 
+thread_local! {
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_entropy());
+}
+
+/// Re-seeds the current thread's random number generator.
+/// Every random value used by the tracer is drawn from it, so seeding it makes scene
+/// construction and rendering reproducible.
+pub fn seed_rng(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
+}
+
+pub fn with_rng<T>(f: impl FnOnce(&mut SmallRng) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+pub fn random<T>() -> T
+where
+    Standard: Distribution<T>,
+{
+    with_rng(|rng| rng.gen())
+}
+
 pub fn random_in_unit_sphere() -> Vector3f {
     // Pick a random point in the unit cube where x,y,z all range from -1 to +1.
     // Reject and try again if point is outside of sphere
-    let mut rng = rand::thread_rng();
     let one = vec3(1_f64, 1_f64, 1_f64);
 
-    loop {
+    with_rng(|rng| loop {
         let random_vec = vec3(rng.gen(), rng.gen(), rng.gen());
         let p = (random_vec * 2_f64) - one;
 
         if p.magnitude2() < 1_f64 {
             return p;
         }
-    }
+    })
 }
 
-#[allow(dead_code)]
 pub fn random_on_unit_sphere() -> Vector3f {
-    // Pick a random point in the unit cube where x,y,z all range from -1 to +1.
-    // Reject and try again if point is outside of sphere
-    let mut rng = rand::thread_rng();
-    let one = vec3(1_f64, 1_f64, 1_f64);
-
-    loop {
-        let random_vec = vec3(rng.gen(), rng.gen(), rng.gen());
-        let p = (random_vec * 2_f64) - one;
+    random_in_unit_sphere().normalize()
+}
 
-        if p.magnitude2() < 1_f64 {
-            return p.normalize();
-        }
-    }
+/// Two unit vectors forming a right-handed orthonormal basis with the unit vector `normal`
+/// (Duff et al., "Building an Orthonormal Basis, Revisited", JCGT 2017).
+pub fn orthonormal_basis(normal: Vector3f) -> (Vector3f, Vector3f) {
+    let sign = 1_f64.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+    let tangent = vec3(
+        1.0 + sign * normal.x * normal.x * a,
+        sign * b,
+        -sign * normal.x,
+    );
+    let bitangent = vec3(b, sign + normal.y * normal.y * a, -normal.y);
+    (tangent, bitangent)
 }
@@ -1,7 +1,6 @@
 mod camera;
 mod color;
 mod intersection;
-mod light;
 mod math;
 mod ray;
 mod render_context;
@@ -11,12 +10,15 @@ mod scene_object_list;
 
 pub mod bounding_volumes;
 pub mod geometry;
+pub mod import;
 pub mod material;
+pub mod scene_file;
+pub mod sdf;
+pub mod volume;
 
 pub use camera::*;
 pub use color::*;
 pub use intersection::*;
-pub use light::*;
 pub use math::*;
 pub use ray::*;
 pub use render_context::*;
@@ -7,15 +7,22 @@ use std::fmt;
 pub struct Ray {
     pub origin: Point3f,
     pub direction: Vector3f,
+    /// Time at which the ray is cast, for objects moving while the camera shutter is open
+    pub time: f64,
 
     inverse_direction: RefCell<Option<Vector3f>>,
 }
 
 impl Ray {
     pub fn new(origin: Point3f, direction: Vector3f) -> Ray {
+        Ray::with_time(origin, direction, 0.0)
+    }
+
+    pub fn with_time(origin: Point3f, direction: Vector3f, time: f64) -> Ray {
         Ray {
             origin,
             direction,
+            time,
             inverse_direction: RefCell::new(None),
         }
     }
@@ -39,13 +46,14 @@ impl fmt::Display for Ray {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "(Ray origin:({},{},{}) direction:({},{},{}))",
+            "(Ray origin:({},{},{}) direction:({},{},{}) time:{})",
             self.origin.x,
             self.origin.y,
             self.origin.z,
             self.direction.x,
             self.direction.y,
             self.direction.z,
+            self.time,
         )
     }
 }
@@ -1,12 +1,14 @@
-use crate::tracer::{Color, Point3f, Ray, Scene, SceneIntersectable};
+use crate::tracer::bounding_volumes::TraversalCounters;
+use crate::tracer::{random, seed_rng, Color, Point3f, Ray, Scene, SceneIntersectable};
 use image::ImageBuffer;
 use indicatif::ProgressBar;
 use itertools::Itertools;
 use log::info;
-use rand::prelude::*;
 use rayon::prelude::*;
+use serde::Deserialize;
 use std::ops::Range;
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
@@ -17,6 +19,9 @@ pub struct RenderContext {
 
     pub pixels: Vec<Color>,
 
+    /// Seed making the render reproducible, random when `None`
+    pub seed: Option<u64>,
+
     // Some stats
     pub rays_cast: u64,
     pub start_time: Instant,
@@ -30,6 +35,7 @@ pub struct RenderTask {
     pub to_y: u64,
     pub width: u64,
     pub height: u64,
+    pub seed: Option<u64>,
 }
 
 pub struct RenderResult {
@@ -37,6 +43,41 @@ pub struct RenderResult {
     pub rays_cast: u64,
 }
 
+/// What a heatmap render counts for each primary ray
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeatmapCounter {
+    /// Acceleration structure nodes visited
+    Nodes,
+    /// Objects tested for an intersection
+    Primitives,
+}
+
+impl FromStr for HeatmapCounter {
+    type Err = serde_json::error::Error;
+    fn from_str(s: &str) -> Result<HeatmapCounter, serde_json::error::Error> {
+        serde_json::from_str(&format!("\"{}\"", s))
+    }
+}
+
+impl HeatmapCounter {
+    fn count(self, counters: TraversalCounters) -> u64 {
+        match self {
+            HeatmapCounter::Nodes => counters.nodes,
+            HeatmapCounter::Primitives => counters.primitives,
+        }
+    }
+}
+
+/// Traversal work of the primary rays of a heatmap render
+#[derive(Debug, Default, Copy, Clone)]
+pub struct HeatmapStats {
+    pub rays: u64,
+    pub total: TraversalCounters,
+    /// Most work done by a single ray
+    pub max: TraversalCounters,
+}
+
 impl RenderContext {
     pub fn new(width: u64, height: u64) -> RenderContext {
         let total_pixels = width * height;
@@ -44,12 +85,13 @@ impl RenderContext {
             width,
             height,
             pixels: vec![Color::black(); total_pixels as usize],
+            seed: None,
             rays_cast: 0,
             start_time: Instant::now(),
         }
     }
 
-    pub fn print_stats(&self) {
+    pub fn print_stats(&self, scene: &Scene) {
         let elapsed = self.start_time.elapsed();
 
         println!();
@@ -60,6 +102,19 @@ impl RenderContext {
             "| Rays per sec: {:.2}\n",
             self.rays_cast as f64 / elapsed.as_secs_f64()
         );
+
+        let bvh_stats = scene.bvh_stats();
+        if bvh_stats.builds > 0 {
+            println!(
+                "| BVH Build Time (s): {:.4} | Trees: {}\n",
+                bvh_stats.build_time.as_secs_f64(),
+                bvh_stats.builds
+            );
+            println!(
+                "| BVH Nodes: {} | Leaves: {} | Objects: {} | Max depth: {}\n",
+                bvh_stats.nodes, bvh_stats.leaves, bvh_stats.objects, bvh_stats.max_depth
+            );
+        }
         println!("==========================================");
     }
 
@@ -81,12 +136,12 @@ impl RenderContext {
     }
 
     fn get_tasks(&self, n: usize) -> Vec<RenderTask> {
-        let mut v = Vec::with_capacity(n as usize);
+        let mut v = Vec::with_capacity(n);
         let width = self.width as usize;
 
         let chunk_size = (width as f64 / n as f64).ceil() as usize;
 
-        for mut wc in &(0..width).into_iter().chunks(chunk_size) {
+        for mut wc in &(0..width).chunks(chunk_size) {
             let chunk_size = chunk_size as u64;
             let from_x: u64 = wc.next().unwrap() as u64;
             let to_x = (from_x + chunk_size).min(self.width);
@@ -98,6 +153,7 @@ impl RenderContext {
                 to_y: self.height,
                 width: self.width,
                 height: self.height,
+                seed: self.seed,
             };
             v.push(task);
         }
@@ -141,11 +197,52 @@ impl RenderContext {
 
             if let Some(pb) = pb {
                 let message = rc.get_stats_message();
-                pb.set_message(&*message);
+                pb.set_message(&message);
             }
         });
     }
 
+    /// Replaces the image with a false-color heatmap of the work done by the ray through the center
+    /// of each pixel, from blue for none to red for the most expensive ray.
+    pub fn render_heatmap(&mut self, scene: &Scene, counter: HeatmapCounter) -> HeatmapStats {
+        let width = self.width as usize;
+        let mut counts = vec![TraversalCounters::default(); self.pixels.len()];
+
+        counts
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let u = (x as f64 + 0.5) / self.width as f64;
+                    let v = (self.height as f64 - y as f64 - 0.5) / self.height as f64;
+                    let ray = scene.camera.get_ray(u, v);
+
+                    let (_, counters) =
+                        TraversalCounters::measure(|| scene.intersect(&ray, 0.001, f64::MAX));
+                    *pixel = counters;
+                }
+            });
+
+        let mut stats = HeatmapStats {
+            rays: counts.len() as u64,
+            ..Default::default()
+        };
+        for &count in &counts {
+            stats.total += count;
+            stats.max = stats.max.max(count);
+        }
+
+        let max = counter.count(stats.max).max(1) as f64;
+        for (pixel, &count) in self.pixels.iter_mut().zip(&counts) {
+            // Squared, as `save` applies a gamma of 2
+            let color = heat_color(counter.count(count) as f64 / max);
+            *pixel = color * color;
+        }
+
+        self.rays_cast += stats.rays;
+        stats
+    }
+
     pub fn save(&self, output: &Path) {
         let img = ImageBuffer::from_fn(self.width as u32, self.height as u32, |x, y| {
             let x = x as u64;
@@ -165,6 +262,28 @@ impl RenderContext {
     }
 }
 
+/// Blue, cyan, green, yellow then red as `t` goes from 0 to 1
+fn heat_color(t: f64) -> Color {
+    const STOPS: [(f64, f64, f64); 5] = [
+        (0.0, 0.0, 1.0),
+        (0.0, 1.0, 1.0),
+        (0.0, 1.0, 0.0),
+        (1.0, 1.0, 0.0),
+        (1.0, 0.0, 0.0),
+    ];
+
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let i = (t as usize).min(STOPS.len() - 2);
+    let f = t - i as f64;
+    let (from, to) = (STOPS[i], STOPS[i + 1]);
+
+    Color::new(
+        from.0 + (to.0 - from.0) * f,
+        from.1 + (to.1 - from.1) * f,
+        from.2 + (to.2 - from.2) * f,
+    )
+}
+
 impl RenderTask {
     pub fn xrange_width(&self) -> u64 {
         self.to_x - self.from_x
@@ -207,9 +326,15 @@ impl RenderTask {
     }
 
     fn render_pixel(&self, x: u64, y: u64, scene: &Scene) -> (u64, Color) {
+        if let Some(seed) = self.seed {
+            // Seed every pixel on its own so the image doesn't depend on how pixels are split
+            // between threads
+            let pixel_idx = y * self.width + x;
+            seed_rng(seed ^ pixel_idx.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        }
+
         let mut color = Color::black();
         let mut rays_count = 0;
-        let mut rng = rand::thread_rng();
 
         let x = x as f64;
         let y = y as f64;
@@ -219,14 +344,14 @@ impl RenderTask {
         let height = self.height as f64;
 
         for _ in 0..samples {
-            let su: f64 = rng.gen();
-            let sv: f64 = rng.gen();
+            let su: f64 = random();
+            let sv: f64 = random();
 
             let u = (x + su) / width;
             let v = (height - y + sv) / height;
 
             let ray = scene.camera.get_ray(u, v);
-            let color_sample = RenderTask::cast_ray(&ray, scene, &mut rng, 0);
+            let color_sample = RenderTask::cast_ray(&ray, scene, 0);
 
             color += color_sample;
             rays_count += 1
@@ -237,8 +362,8 @@ impl RenderTask {
         (rays_count, color)
     }
 
-    fn cast_ray(ray: &Ray, scene: &Scene, rng: &mut ThreadRng, depth: u32) -> Color {
-        let maybe_intersection = scene.intersect(ray, 0.001, std::f64::MAX);
+    fn cast_ray(ray: &Ray, scene: &Scene, depth: u32) -> Color {
+        let maybe_intersection = scene.intersect(ray, 0.001, f64::MAX);
 
         if let Some(intersection) = maybe_intersection {
             let material = intersection
@@ -252,7 +377,7 @@ impl RenderTask {
             if depth < scene.options.max_depth {
                 if let Some(ref scatter) = material.scatter(ray, &intersection) {
                     let attenution = scatter.attenuation;
-                    let color = RenderTask::cast_ray(&scatter.ray, scene, rng, depth + 1);
+                    let color = RenderTask::cast_ray(&scatter.ray, scene, depth + 1);
                     return (color * attenution) + emitted;
                 }
                 return Color::from_vec3f(emitted);
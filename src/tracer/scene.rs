@@ -1,13 +1,26 @@
+use crate::tracer::bounding_volumes::{Accelerator, BVHNode, BVHStats};
 use crate::tracer::material::Material;
-use crate::tracer::{Camera, Ray, SceneIntersectable, SceneIntersection};
+use crate::tracer::{Camera, Ray, SceneIntersectable, SceneIntersection, SceneObject};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::Arc;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct RenderOpts {
     pub max_depth: u32,
     pub samples: u32,
 }
 
+impl Default for RenderOpts {
+    fn default() -> RenderOpts {
+        RenderOpts {
+            max_depth: 50,
+            samples: 100,
+        }
+    }
+}
+
 pub struct Scene {
     pub options: RenderOpts,
     pub camera: Arc<dyn Camera>,
@@ -29,10 +42,116 @@ impl Scene {
             background,
         }
     }
+
+    /// This scene with its objects moved into a new `accelerator`, or left as they are when
+    /// they already are in one.
+    #[must_use]
+    pub fn with_accelerator(self, accelerator: Accelerator) -> Scene {
+        if self.objects.accelerator() == Some(accelerator) {
+            return self;
+        }
+        Scene {
+            objects: accelerator.build(self.objects.leaves()),
+            ..self
+        }
+    }
+
+    /// Stats of the BVHs of the scene: the one holding its objects, if any, and the ones nested
+    /// in objects such as meshes, counted once however many instances share them.
+    pub fn bvh_stats(&self) -> BVHStats {
+        let mut stats = self.objects.bvh_stats().unwrap_or_default();
+        let mut nested = HashSet::new();
+        for object in self.objects.leaves() {
+            for bvh in object.bvhs() {
+                if nested.insert(bvh as *const BVHNode) {
+                    stats.add(&bvh.stats());
+                }
+            }
+        }
+        stats
+    }
 }
 
 impl SceneIntersectable for Scene {
     fn intersect(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<SceneIntersection> {
         self.objects.intersect(ray, dist_min, dist_max)
     }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        self.objects.occluded(ray, dist_min, dist_max)
+    }
+
+    fn leaves(&self) -> Vec<Arc<dyn SceneObject>> {
+        self.objects.leaves()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::bounding_volumes::SceneBVH;
+    use crate::tracer::geometry::{Instance, MeshData, Sphere, TriangleMesh};
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::{Color, SimpleCamera};
+    use cgmath::*;
+
+    #[test]
+    fn test_bvh_stats() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let quad: Arc<dyn SceneObject> = Arc::new(TriangleMesh::new(MeshData {
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals: vec![],
+            uvs: vec![],
+            colors: vec![],
+            indices: vec![[0, 1, 2], [0, 2, 3]],
+            material: material.clone(),
+        }));
+        let objects: Vec<Arc<dyn SceneObject>> = vec![
+            Arc::new(Instance::new(quad.clone(), Matrix4::identity())),
+            Arc::new(Instance::new(
+                quad,
+                Matrix4::from_translation(vec3(2.0, 0.0, 0.0)),
+            )),
+            Arc::new(Sphere {
+                center: Point3::new(0.0, 0.0, 2.0),
+                radius: 0.5,
+                material,
+            }),
+        ];
+        let camera = SimpleCamera::new(
+            Point3::new(0.0, 0.0, -5.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+        );
+        let scene = Scene::new(
+            RenderOpts::default(),
+            Arc::new(camera),
+            Arc::new(SceneBVH::build(objects)),
+            Arc::new(Lambertian::from_constant(Color::black())),
+        );
+
+        // The scene BVH, and the mesh BVH once for both instances
+        let stats = scene.bvh_stats();
+        assert_eq!(stats.builds, 2);
+        assert_eq!(stats.objects, 3 + 2);
+
+        // Already a BVH, nothing to build again
+        let objects = scene.objects.clone();
+        let scene = scene.with_accelerator(Accelerator::Bvh);
+        assert!(Arc::ptr_eq(&objects, &scene.objects));
+
+        let scene = scene.with_accelerator(Accelerator::Grid);
+        assert_eq!(scene.objects.accelerator(), Some(Accelerator::Grid));
+        assert_eq!(scene.bvh_stats().builds, 1);
+        assert_eq!(scene.bvh_stats().objects, 2);
+    }
 }
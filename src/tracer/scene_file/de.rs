@@ -0,0 +1,158 @@
+//! Deserialization helpers for the scene file format.
+//!
+//! Serde's derived internally tagged (`"type": "..."`) and untagged enums buffer their content
+//! before picking a variant, which hides the location of nested errors. The helpers here stream
+//! the enum contents instead, so errors keep their full JSON path (e.g. `objects[3].radius`).
+
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// An enum represented as a JSON object whose `type` field selects the variant.
+pub trait Tagged: Sized {
+    const NAME: &'static str;
+    const VARIANTS: &'static [&'static str];
+
+    /// Deserializes the variant selected by `tag` from the remaining fields of the object.
+    fn deserialize_variant<'de, D: Deserializer<'de>>(
+        tag: &str,
+        fields: D,
+    ) -> Result<Self, D::Error>;
+}
+
+pub fn deserialize_tagged<'de, D: Deserializer<'de>, T: Tagged>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    deserializer.deserialize_map(TaggedVisitor(PhantomData))
+}
+
+struct TaggedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Tagged> Visitor<'de> for TaggedVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a {} object with a `type` field", T::NAME)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<T, A::Error> {
+        let first_key: Option<String> = map.next_key()?;
+        match first_key.as_deref() {
+            Some("type") => {
+                let tag = map.next_value_seed(TagSeed(T::VARIANTS))?;
+                T::deserialize_variant(&tag, MapAccessDeserializer::new(map))
+            }
+            Some(key) => {
+                // `type` is not the first field: buffer the object to find it. Errors are then
+                // reported against the object itself rather than the nested field.
+                let mut fields = Map::new();
+                fields.insert(key.to_string(), map.next_value()?);
+                while let Some((key, value)) = map.next_entry::<String, Value>()? {
+                    fields.insert(key, value);
+                }
+
+                let tag = match fields.remove("type") {
+                    Some(Value::String(tag)) => tag,
+                    Some(_) => return Err(de::Error::custom("`type` must be a string")),
+                    None => return Err(de::Error::missing_field("type")),
+                };
+                if !T::VARIANTS.contains(&tag.as_str()) {
+                    return Err(de::Error::unknown_variant(&tag, T::VARIANTS));
+                }
+
+                T::deserialize_variant(&tag, Value::Object(fields)).map_err(de::Error::custom)
+            }
+            None => Err(de::Error::missing_field("type")),
+        }
+    }
+}
+
+/// Reads the `type` value, rejecting unknown variants while the path still points at it.
+struct TagSeed(&'static [&'static str]);
+
+impl<'de> DeserializeSeed<'de> for TagSeed {
+    type Value = String;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<String, D::Error> {
+        let tag = String::deserialize(deserializer)?;
+        if !self.0.contains(&tag.as_str()) {
+            return Err(de::Error::unknown_variant(&tag, self.0));
+        }
+        Ok(tag)
+    }
+}
+
+/// Implements `Tagged` and `Deserialize` for an enum whose variants each wrap a description
+/// struct, e.g. `ObjectDescription::Sphere(SphereDescription)`.
+macro_rules! tagged_enum {
+    ($name:ident, $expecting:literal, { $($tag:literal => $variant:ident),* $(,)? }) => {
+        impl $crate::tracer::scene_file::de::Tagged for $name {
+            const NAME: &'static str = $expecting;
+            const VARIANTS: &'static [&'static str] = &[$($tag),*];
+
+            fn deserialize_variant<'de, D: serde::Deserializer<'de>>(
+                tag: &str,
+                fields: D,
+            ) -> Result<Self, D::Error> {
+                match tag {
+                    $($tag => serde::Deserialize::deserialize(fields).map($name::$variant),)*
+                    _ => Err(serde::de::Error::unknown_variant(tag, Self::VARIANTS)),
+                }
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                $crate::tracer::scene_file::de::deserialize_tagged(deserializer)
+            }
+        }
+    };
+}
+
+/// Deserializes a reference that may be a name (string), an array or an inline object, as
+/// used by `TextureRef` and `MaterialRef`.
+pub trait Reference: Sized {
+    const EXPECTING: &'static str;
+
+    fn from_name(name: &str) -> Self;
+
+    fn from_seq<'de, A: SeqAccess<'de>>(_seq: A) -> Result<Self, A::Error> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::Seq,
+            &Self::EXPECTING,
+        ))
+    }
+
+    fn from_map<'de, A: MapAccess<'de>>(map: A) -> Result<Self, A::Error>;
+}
+
+pub fn deserialize_reference<'de, D: Deserializer<'de>, T: Reference>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    deserializer.deserialize_any(ReferenceVisitor(PhantomData))
+}
+
+struct ReferenceVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Reference> Visitor<'de> for ReferenceVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(T::EXPECTING)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        Ok(T::from_name(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<T, A::Error> {
+        T::from_seq(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<T, A::Error> {
+        T::from_map(map)
+    }
+}
@@ -0,0 +1,949 @@
+use crate::tracer::scene_file::de::{deserialize_reference, Reference};
+use crate::tracer::sdf::SdfObject;
+use crate::tracer::RenderOpts;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{MapAccess, SeqAccess};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// A 3-component `[x, y, z]` (or `[r, g, b]`) array.
+pub type Vec3Description = [f64; 3];
+
+/// Root of a JSON scene file.
+///
+/// Textures and materials can either be declared inline where they are used, or declared once
+/// in the `textures`/`materials` maps and referenced by name. Objects in `shapes` are only
+/// rendered through the instances referencing them by name, which all share their geometry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+
+    #[serde(default)]
+    pub options: RenderOpts,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub textures: BTreeMap<String, TextureDescription>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<String, MaterialDescription>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub shapes: BTreeMap<String, ObjectDescription>,
+
+    pub objects: Vec<ObjectDescription>,
+
+    #[serde(default = "default_background")]
+    pub background: MaterialRef,
+}
+
+fn default_background() -> MaterialRef {
+    MaterialRef::Inline(Box::new(MaterialDescription::Lambertian(
+        LambertianDescription {
+            albedo: TextureRef::Color([0.0, 0.0, 0.0]),
+        },
+    )))
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CameraDescription {
+    Simple(SimpleCameraDescription),
+}
+
+tagged_enum!(CameraDescription, "camera", {
+    "simple" => Simple,
+});
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SimpleCameraDescription {
+    pub look_from: Vec3Description,
+    pub look_at: Vec3Description,
+    #[serde(default = "default_up")]
+    pub up: Vec3Description,
+    /// Vertical field of view, top to bottom in degrees
+    pub vfov: f64,
+    /// Defaults to the aspect ratio of the rendered image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect: Option<f64>,
+    #[serde(default)]
+    pub aperture: f64,
+    pub focus_dist: f64,
+    /// Rays are cast at random times between `shutter_open` and `shutter_close`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub shutter_open: f64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub shutter_close: f64,
+}
+
+fn is_zero(value: &f64) -> bool {
+    *value == 0.0
+}
+
+fn default_up() -> Vec3Description {
+    [0.0, 1.0, 0.0]
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextureDescription {
+    Solid(SolidTextureDescription),
+    Checkers(CheckersTextureDescription),
+    Noise(NoiseTextureDescription),
+    Image(ImageTextureDescription),
+    VertexColor(VertexColorTextureDescription),
+}
+
+tagged_enum!(TextureDescription, "texture", {
+    "solid" => Solid,
+    "checkers" => Checkers,
+    "noise" => Noise,
+    "image" => Image,
+    "vertex_color" => VertexColor,
+});
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SolidTextureDescription {
+    pub color: Vec3Description,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CheckersTextureDescription {
+    pub odd: TextureRef,
+    pub even: TextureRef,
+    pub scale: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseTextureDescription {
+    pub scale: f64,
+    pub octaves: u32,
+    pub frequency: f64,
+    pub persistence: f64,
+    pub lacunarity: f64,
+    pub color_start: Vec3Description,
+    pub color_end: Vec3Description,
+}
+
+/// An image file mapped over the surface uv coordinates. Relative paths are resolved from the
+/// working directory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageTextureDescription {
+    pub path: PathBuf,
+}
+
+/// The colors of the mesh vertices, interpolated over its triangles. Objects without vertex
+/// colors get the `fallback` color.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VertexColorTextureDescription {
+    #[serde(default = "default_fallback")]
+    pub fallback: Vec3Description,
+}
+
+fn default_fallback() -> Vec3Description {
+    [0.5, 0.5, 0.5]
+}
+
+/// Where a texture is expected, a scene file may use a name from `textures`, a bare
+/// `[r, g, b]` color or an inline texture object.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum TextureRef {
+    Named(String),
+    Color(Vec3Description),
+    Inline(Box<TextureDescription>),
+}
+
+impl From<TextureDescription> for TextureRef {
+    /// Inlines the texture, writing solid textures as a bare color.
+    fn from(texture: TextureDescription) -> TextureRef {
+        match texture {
+            TextureDescription::Solid(solid) => TextureRef::Color(solid.color),
+            texture => TextureRef::Inline(Box::new(texture)),
+        }
+    }
+}
+
+impl Reference for TextureRef {
+    const EXPECTING: &'static str = "a texture name, an [r, g, b] color or a texture object";
+
+    fn from_name(name: &str) -> TextureRef {
+        TextureRef::Named(name.to_string())
+    }
+
+    fn from_seq<'de, A: SeqAccess<'de>>(seq: A) -> Result<TextureRef, A::Error> {
+        Deserialize::deserialize(SeqAccessDeserializer::new(seq)).map(TextureRef::Color)
+    }
+
+    fn from_map<'de, A: MapAccess<'de>>(map: A) -> Result<TextureRef, A::Error> {
+        Deserialize::deserialize(MapAccessDeserializer::new(map))
+            .map(|t| TextureRef::Inline(Box::new(t)))
+    }
+}
+
+impl<'de> Deserialize<'de> for TextureRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TextureRef, D::Error> {
+        deserialize_reference(deserializer)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialDescription {
+    Lambertian(LambertianDescription),
+    Metal(MetalDescription),
+    Dielectric(DielectricDescription),
+    DiffuseLight(DiffuseLightDescription),
+    Sky(SkyDescription),
+    Isotropic(IsotropicDescription),
+    HenyeyGreenstein(HenyeyGreensteinDescription),
+    BumpMap(BumpMapDescription),
+    NormalMap(NormalMapDescription),
+}
+
+tagged_enum!(MaterialDescription, "material", {
+    "lambertian" => Lambertian,
+    "metal" => Metal,
+    "dielectric" => Dielectric,
+    "diffuse_light" => DiffuseLight,
+    "sky" => Sky,
+    "isotropic" => Isotropic,
+    "henyey_greenstein" => HenyeyGreenstein,
+    "bump_map" => BumpMap,
+    "normal_map" => NormalMap,
+});
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LambertianDescription {
+    pub albedo: TextureRef,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetalDescription {
+    pub albedo: Vec3Description,
+    #[serde(default)]
+    pub fuzz: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DielectricDescription {
+    pub refractive_index: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiffuseLightDescription {
+    pub emit: TextureRef,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkyDescription {
+    pub horizon: Vec3Description,
+    pub zenith: Vec3Description,
+}
+
+/// Phase function of participating media scattering light equally in all directions.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IsotropicDescription {
+    pub albedo: TextureRef,
+}
+
+/// Phase function of participating media scattering light mostly forward when the asymmetry
+/// `g`, between -1 and 1, is positive and mostly backward when it's negative.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HenyeyGreensteinDescription {
+    pub albedo: TextureRef,
+    pub g: f64,
+}
+
+/// `material` shaded as if its surface was displaced along its normal by `scale` times the
+/// `height` texture, averaged over its channels.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BumpMapDescription {
+    pub material: MaterialRef,
+    pub height: TextureRef,
+    #[serde(default = "default_bump_scale")]
+    pub scale: f64,
+}
+
+fn default_bump_scale() -> f64 {
+    1.0
+}
+
+/// `material` shaded with the tangent space normals of the `normals` texture, its red, green
+/// and blue channels going along the u tangent, the v tangent and the surface normal.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NormalMapDescription {
+    pub material: MaterialRef,
+    pub normals: TextureRef,
+}
+
+/// Where a material is expected, a scene file may use a name from `materials` or an inline
+/// material object.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum MaterialRef {
+    Named(String),
+    Inline(Box<MaterialDescription>),
+}
+
+impl Reference for MaterialRef {
+    const EXPECTING: &'static str = "a material name or a material object";
+
+    fn from_name(name: &str) -> MaterialRef {
+        MaterialRef::Named(name.to_string())
+    }
+
+    fn from_map<'de, A: MapAccess<'de>>(map: A) -> Result<MaterialRef, A::Error> {
+        Deserialize::deserialize(MapAccessDeserializer::new(map))
+            .map(|m| MaterialRef::Inline(Box::new(m)))
+    }
+}
+
+impl<'de> Deserialize<'de> for MaterialRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<MaterialRef, D::Error> {
+        deserialize_reference(deserializer)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObjectDescription {
+    Sphere(SphereDescription),
+    MovingSphere(MovingSphereDescription),
+    Plane(PlaneDescription),
+    Quad(QuadDescription),
+    Disk(DiskDescription),
+    Box(BoxDescription),
+    Cylinder(CylinderDescription),
+    Cone(ConeDescription),
+    Paraboloid(ParaboloidDescription),
+    Torus(TorusDescription),
+    ConstantMedium(ConstantMediumDescription),
+    HeterogeneousMedium(HeterogeneousMediumDescription),
+    Csg(CsgDescription),
+    Sdf(SdfObjectDescription),
+    Heightfield(HeightfieldDescription),
+    Triangle(TriangleDescription),
+    Mesh(MeshDescription),
+    Instance(InstanceDescription),
+}
+
+tagged_enum!(ObjectDescription, "object", {
+    "sphere" => Sphere,
+    "moving_sphere" => MovingSphere,
+    "plane" => Plane,
+    "quad" => Quad,
+    "disk" => Disk,
+    "box" => Box,
+    "cylinder" => Cylinder,
+    "cone" => Cone,
+    "paraboloid" => Paraboloid,
+    "torus" => Torus,
+    "constant_medium" => ConstantMedium,
+    "heterogeneous_medium" => HeterogeneousMedium,
+    "csg" => Csg,
+    "sdf" => Sdf,
+    "heightfield" => Heightfield,
+    "triangle" => Triangle,
+    "mesh" => Mesh,
+    "instance" => Instance,
+});
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SphereDescription {
+    pub center: Vec3Description,
+    pub radius: f64,
+    pub material: MaterialRef,
+}
+
+/// A sphere moving from `center0` at `time0` to `center1` at `time1`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MovingSphereDescription {
+    pub center0: Vec3Description,
+    pub center1: Vec3Description,
+    #[serde(default)]
+    pub time0: f64,
+    #[serde(default = "default_time1")]
+    pub time1: f64,
+    pub radius: f64,
+    pub material: MaterialRef,
+}
+
+fn default_time1() -> f64 {
+    1.0
+}
+
+/// An infinite plane through `point`, perpendicular to `normal`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlaneDescription {
+    pub point: Vec3Description,
+    pub normal: Vec3Description,
+    pub material: MaterialRef,
+}
+
+/// A parallelogram with a `corner` and two edges `u` and `v` leaving from it, facing `u` x `v`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuadDescription {
+    pub corner: Vec3Description,
+    pub u: Vec3Description,
+    pub v: Vec3Description,
+    pub material: MaterialRef,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiskDescription {
+    pub center: Vec3Description,
+    pub normal: Vec3Description,
+    pub radius: f64,
+    pub material: MaterialRef,
+}
+
+/// An axis-aligned box between two opposite corners.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoxDescription {
+    pub min: Vec3Description,
+    pub max: Vec3Description,
+    pub material: MaterialRef,
+}
+
+/// A cylinder standing on `center` along the y axis. `phi_max` sweeps it by less than a full
+/// turn, in degrees from the +x axis towards +z.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CylinderDescription {
+    pub center: Vec3Description,
+    pub radius: f64,
+    pub height: f64,
+    #[serde(default = "default_phi_max")]
+    pub phi_max: f64,
+    #[serde(default)]
+    pub capped: bool,
+    pub material: MaterialRef,
+}
+
+/// A cone with its base on `center` and its apex `height` above it, clipped between `y_min`
+/// and `y_max` above the base.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConeDescription {
+    pub center: Vec3Description,
+    pub radius: f64,
+    pub height: f64,
+    #[serde(default)]
+    pub y_min: f64,
+    /// Defaults to `height`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y_max: Option<f64>,
+    #[serde(default = "default_phi_max")]
+    pub phi_max: f64,
+    #[serde(default)]
+    pub capped: bool,
+    pub material: MaterialRef,
+}
+
+/// A paraboloid with its vertex on `center`, opening up to `radius` at `height`, clipped between
+/// `y_min` and `y_max` above the vertex.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParaboloidDescription {
+    pub center: Vec3Description,
+    pub radius: f64,
+    pub height: f64,
+    #[serde(default)]
+    pub y_min: f64,
+    /// Defaults to `height`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y_max: Option<f64>,
+    #[serde(default = "default_phi_max")]
+    pub phi_max: f64,
+    #[serde(default)]
+    pub capped: bool,
+    pub material: MaterialRef,
+}
+
+/// A torus around the y axis of `center`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TorusDescription {
+    pub center: Vec3Description,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    #[serde(default = "default_phi_max")]
+    pub phi_max: f64,
+    pub material: MaterialRef,
+}
+
+/// A participating medium of uniform `density` filling a convex `boundary` object, whose own
+/// material is ignored. Rays scatter inside it following the `phase` material, usually
+/// `isotropic`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConstantMediumDescription {
+    pub boundary: Box<ObjectDescription>,
+    pub density: f64,
+    pub phase: MaterialRef,
+}
+
+/// A solid made of `material` combining the `left` and `right` objects, whose own materials
+/// are ignored. Only spheres, boxes, instances of them and other `csg` solids can be combined.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CsgDescription {
+    pub operation: CsgOperation,
+    pub left: Box<ObjectDescription>,
+    pub right: Box<ObjectDescription>,
+    pub material: MaterialRef,
+}
+
+/// How a `csg` solid combines its objects, `difference` removing `right` from `left`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// The surface of the signed distance function `sdf`, which must lie within `bounds`
+/// (`[min, max]`). Rays step towards it at most `max_steps` times, until they're within
+/// `epsilon` of it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SdfObjectDescription {
+    pub sdf: SdfDescription,
+    pub bounds: [Vec3Description; 2],
+    pub material: MaterialRef,
+    #[serde(default = "default_max_steps")]
+    pub max_steps: u32,
+    #[serde(default = "default_epsilon")]
+    pub epsilon: f64,
+}
+
+fn default_max_steps() -> u32 {
+    SdfObject::DEFAULT_MAX_STEPS
+}
+
+fn default_epsilon() -> f64 {
+    SdfObject::DEFAULT_EPSILON
+}
+
+/// Distance functions centered on the origin, and combinators of other functions.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SdfDescription {
+    Sphere(SdfSphereDescription),
+    Box(SdfBoxDescription),
+    Torus(SdfTorusDescription),
+    Capsule(SdfCapsuleDescription),
+    Mandelbulb(MandelbulbDescription),
+    Translate(TranslateSdfDescription),
+    SmoothUnion(SmoothUnionDescription),
+    Twist(TwistDescription),
+    Repeat(RepeatDescription),
+    Round(RoundDescription),
+}
+
+tagged_enum!(SdfDescription, "sdf", {
+    "sphere" => Sphere,
+    "box" => Box,
+    "torus" => Torus,
+    "capsule" => Capsule,
+    "mandelbulb" => Mandelbulb,
+    "translate" => Translate,
+    "smooth_union" => SmoothUnion,
+    "twist" => Twist,
+    "repeat" => Repeat,
+    "round" => Round,
+});
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SdfSphereDescription {
+    pub radius: f64,
+}
+
+/// A box extending by `half_size` on both sides of each axis.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SdfBoxDescription {
+    pub half_size: Vec3Description,
+}
+
+/// A torus around the y axis.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SdfTorusDescription {
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+/// The points within `radius` of the segment from `a` to `b`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SdfCapsuleDescription {
+    pub a: Vec3Description,
+    pub b: Vec3Description,
+    pub radius: f64,
+}
+
+/// The Mandelbulb fractal, fitting within 1.2 of the origin for the usual `power` of 8.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MandelbulbDescription {
+    #[serde(default = "default_mandelbulb_power")]
+    pub power: f64,
+    #[serde(default = "default_mandelbulb_iterations")]
+    pub iterations: u32,
+}
+
+fn default_mandelbulb_power() -> f64 {
+    8.0
+}
+
+fn default_mandelbulb_iterations() -> u32 {
+    12
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TranslateSdfDescription {
+    pub sdf: Box<SdfDescription>,
+    pub offset: Vec3Description,
+}
+
+/// The union of `a` and `b`, blended over a distance of `smoothness` where they meet.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmoothUnionDescription {
+    pub a: Box<SdfDescription>,
+    pub b: Box<SdfDescription>,
+    pub smoothness: f64,
+}
+
+/// `sdf` twisted around the y axis by `rate` radians per unit of height.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TwistDescription {
+    pub sdf: Box<SdfDescription>,
+    pub rate: f64,
+}
+
+/// Copies of `sdf` every `period` along each axis, `count` times on both sides of the
+/// original. Axes with a zero period or count aren't repeated.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepeatDescription {
+    pub sdf: Box<SdfDescription>,
+    pub period: Vec3Description,
+    pub count: [u32; 3],
+}
+
+/// `sdf` grown by `radius`, rounding its edges.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoundDescription {
+    pub sdf: Box<SdfDescription>,
+    pub radius: f64,
+}
+
+/// Terrain stretching `height_map` over `size`, from its `corner` with the lowest x, y and z.
+/// Heights of 1 rise to `size.y` above the corner.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeightfieldDescription {
+    pub height_map: HeightMapDescription,
+    pub corner: Vec3Description,
+    pub size: Vec3Description,
+    pub material: MaterialRef,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HeightMapDescription {
+    Noise(NoiseHeightMapDescription),
+    Image(ImageHeightMapDescription),
+}
+
+tagged_enum!(HeightMapDescription, "height map", {
+    "noise" => Noise,
+    "image" => Image,
+});
+
+/// Heights sampled from Perlin noise over `[0, scale]` along both axes, as in the images of
+/// the `noise` command, at `resolution` (`[x, z]`) samples.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseHeightMapDescription {
+    pub resolution: [usize; 2],
+    #[serde(default = "default_noise_scale")]
+    pub scale: f64,
+    #[serde(default = "default_octaves")]
+    pub octaves: u32,
+    #[serde(default = "default_frequency")]
+    pub frequency: f64,
+    #[serde(default = "default_persistence")]
+    pub persistence: f64,
+    #[serde(default = "default_lacunarity")]
+    pub lacunarity: f64,
+}
+
+/// Heights read from the grayscale of an image file, with its rows going along z. Relative
+/// paths are resolved from the working directory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageHeightMapDescription {
+    pub path: PathBuf,
+}
+
+/// A participating medium filling a convex `boundary` object with a `density` varying in space.
+/// Media with an `emission` texture glow where rays scatter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeterogeneousMediumDescription {
+    pub boundary: Box<ObjectDescription>,
+    pub density: DensityDescription,
+    pub phase: MaterialRef,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emission: Option<TextureRef>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DensityDescription {
+    Noise(NoiseDensityDescription),
+    VoxelGrid(VoxelGridDescription),
+}
+
+tagged_enum!(DensityDescription, "density", {
+    "noise" => Noise,
+    "voxel_grid" => VoxelGrid,
+});
+
+/// Cloudy density from Perlin noise, up to `density`. Noise values under `cutoff`, between 0
+/// and 1, leave empty space between the clouds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseDensityDescription {
+    pub density: f64,
+    #[serde(default = "default_noise_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub cutoff: f64,
+    #[serde(default = "default_octaves")]
+    pub octaves: u32,
+    #[serde(default = "default_frequency")]
+    pub frequency: f64,
+    #[serde(default = "default_persistence")]
+    pub persistence: f64,
+    #[serde(default = "default_lacunarity")]
+    pub lacunarity: f64,
+}
+
+fn default_noise_scale() -> f64 {
+    1.0
+}
+
+fn default_octaves() -> u32 {
+    8
+}
+
+fn default_frequency() -> f64 {
+    1.0
+}
+
+fn default_persistence() -> f64 {
+    0.5
+}
+
+fn default_lacunarity() -> f64 {
+    2.0
+}
+
+/// A density grid loaded from `path`, a Mitsuba `.vol` file or a headerless `raw_u8`/`raw_f32`
+/// file of `resolution` values. The grid is stretched over `bounds` (`[min, max]`), by default
+/// the bounding box of `.vol` files and the unit cube for raw files, and its values are
+/// multiplied by `scale`. Relative paths are resolved from the working directory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VoxelGridDescription {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub format: VolumeFormat,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<[usize; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<[Vec3Description; 2]>,
+    #[serde(default = "default_noise_scale")]
+    pub scale: f64,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeFormat {
+    #[default]
+    Vol,
+    RawU8,
+    RawF32,
+}
+
+fn default_phi_max() -> f64 {
+    360.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TriangleDescription {
+    pub vertices: [Vec3Description; 3],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normals: Option<[Vec3Description; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uvs: Option<[[f64; 2]; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colors: Option<[Vec3Description; 3]>,
+    pub material: MaterialRef,
+}
+
+/// An indexed triangle mesh. `normals`, `uvs` and `colors` are optional, and hold one entry per
+/// position when given.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MeshDescription {
+    pub positions: Vec<Vec3Description>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub normals: Vec<Vec3Description>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uvs: Vec<[f64; 2]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub colors: Vec<Vec3Description>,
+    pub indices: Vec<[u32; 3]>,
+    pub material: MaterialRef,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub displacement: Option<DisplacementDescription>,
+}
+
+/// Moves the vertices of a mesh along their normals by `scale` times the `height` texture,
+/// after splitting each triangle in 4 `subdivisions` times.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DisplacementDescription {
+    pub height: TextureRef,
+    #[serde(default = "default_bump_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub subdivisions: u32,
+}
+
+/// Where an instanced object is expected, a scene file may use a name from `shapes` or an
+/// inline object.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum ObjectRef {
+    Named(String),
+    Inline(Box<ObjectDescription>),
+}
+
+impl Reference for ObjectRef {
+    const EXPECTING: &'static str = "a shape name or an object";
+
+    fn from_name(name: &str) -> ObjectRef {
+        ObjectRef::Named(name.to_string())
+    }
+
+    fn from_map<'de, A: MapAccess<'de>>(map: A) -> Result<ObjectRef, A::Error> {
+        Deserialize::deserialize(MapAccessDeserializer::new(map))
+            .map(|o| ObjectRef::Inline(Box::new(o)))
+    }
+}
+
+impl<'de> Deserialize<'de> for ObjectRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ObjectRef, D::Error> {
+        deserialize_reference(deserializer)
+    }
+}
+
+/// An object placed through a list of transforms, applied in order.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstanceDescription {
+    pub object: ObjectRef,
+    pub transform: Vec<TransformDescription>,
+    /// Moves the object from `transform` to `motion.transform` while the shutter is open
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion: Option<MotionDescription>,
+}
+
+/// An instance placed by its `transform` at `time0` and by this `transform` at `time1`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MotionDescription {
+    pub transform: Vec<TransformDescription>,
+    #[serde(default)]
+    pub time0: f64,
+    #[serde(default = "default_time1")]
+    pub time1: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransformDescription {
+    Translate(TranslateDescription),
+    Rotate(RotateDescription),
+    Scale(ScaleDescription),
+    Matrix(MatrixDescription),
+}
+
+tagged_enum!(TransformDescription, "transform", {
+    "translate" => Translate,
+    "rotate" => Rotate,
+    "scale" => Scale,
+    "matrix" => Matrix,
+});
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TranslateDescription {
+    pub offset: Vec3Description,
+}
+
+/// A counterclockwise rotation around `axis`, looking down the axis.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RotateDescription {
+    pub axis: Vec3Description,
+    pub degrees: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScaleDescription {
+    pub factor: Vec3Description,
+}
+
+/// An affine transform matrix, given row by row.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MatrixDescription {
+    pub rows: [[f64; 4]; 4],
+}
@@ -0,0 +1,61 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Errors raised while reading or building a scene file.
+///
+/// `path` is the location of the offending value inside the JSON document, e.g.
+/// `objects[3].material.albedo`.
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(io::Error),
+    Parse { path: String, message: String },
+    Invalid { path: String, message: String },
+}
+
+impl SceneFileError {
+    pub fn invalid(path: &str, message: impl Into<String>) -> SceneFileError {
+        SceneFileError::Invalid {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneFileError::Io(error) => write!(f, "failed to read scene file: {}", error),
+            SceneFileError::Parse { path, message } => {
+                write!(f, "invalid scene file at `{}`: {}", path, message)
+            }
+            SceneFileError::Invalid { path, message } => {
+                write!(f, "invalid scene at `{}`: {}", path, message)
+            }
+        }
+    }
+}
+
+impl Error for SceneFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneFileError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneFileError {
+    fn from(error: io::Error) -> SceneFileError {
+        SceneFileError::Io(error)
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for SceneFileError {
+    fn from(error: serde_path_to_error::Error<serde_json::Error>) -> SceneFileError {
+        SceneFileError::Parse {
+            path: error.path().to_string(),
+            message: error.inner().to_string(),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::tracer::scene_file::*;
+use crate::tracer::{Scene, SceneIntersectable};
+use std::fs;
+use std::path::Path;
+
+impl SceneDescription {
+    /// Describes a constructed scene, rendered at `width`x`height`, so it can be saved as a
+    /// scene file.
+    ///
+    /// Textures, materials and instanced objects are written inline for every object using them.
+    /// Fails if the camera, the background or one of the objects can't be described.
+    pub fn from_scene(
+        scene: &Scene,
+        width: u64,
+        height: u64,
+    ) -> Result<SceneDescription, SceneFileError> {
+        let mut camera = scene
+            .camera
+            .describe()
+            .ok_or_else(|| SceneFileError::invalid("camera", "camera can't be exported"))?;
+
+        // Leave the aspect ratio out when it follows the image size, so the exported scene
+        // can be rendered at other sizes
+        match &mut camera {
+            CameraDescription::Simple(camera) => {
+                if camera.aspect == Some(width as f64 / height as f64) {
+                    camera.aspect = None;
+                }
+            }
+        }
+
+        let objects = scene
+            .leaves()
+            .iter()
+            .enumerate()
+            .map(|(idx, object)| {
+                object.describe().ok_or_else(|| {
+                    SceneFileError::invalid(
+                        &format!("objects[{}]", idx),
+                        "object or its material can't be exported",
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let background = scene.background.describe().ok_or_else(|| {
+            SceneFileError::invalid("background", "background material can't be exported")
+        })?;
+
+        Ok(SceneDescription {
+            camera,
+            options: scene.options,
+            textures: Default::default(),
+            materials: Default::default(),
+            shapes: Default::default(),
+            objects,
+            background: MaterialRef::Inline(Box::new(background)),
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scene descriptions always serialize")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SceneFileError> {
+        fs::write(path, self.to_json())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenes;
+    use crate::tracer::bounding_volumes::Accelerator;
+    use crate::tracer::{seed_rng, Color, RenderContext};
+
+    #[test]
+    fn test_round_trip() {
+        let scene = scenes::two_spheres_perlin::get_scene(40, 30, 10);
+        let json = SceneDescription::from_scene(&scene, 40, 30)
+            .unwrap()
+            .to_json();
+
+        let loaded = SceneDescription::from_json(&json)
+            .unwrap()
+            .build(40, 30)
+            .unwrap();
+        let reexported = SceneDescription::from_scene(&loaded, 40, 30)
+            .unwrap()
+            .to_json();
+
+        assert_eq!(json, reexported);
+    }
+
+    /// Pixels of `scene` rendered at 16x12 with a fixed seed.
+    fn render(scene: &Scene) -> Vec<Color> {
+        let mut context = RenderContext::new(16, 12);
+        context.seed = Some(7);
+        context.render(scene, 2, None);
+        context.pixels
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        // The three reference scenes, the weekend one with random spheres, and the next week one
+        // with smoke and fog for media and random ground boxes
+        seed_rng(1);
+        let scenes = [
+            scenes::weekend_spheres::get_scene(16, 12, 4),
+            scenes::two_spheres_perlin::get_scene(16, 12, 4),
+            scenes::two_spheres_light::get_scene(16, 12, 4),
+            scenes::next_week_final::get_scene(16, 12, 4),
+        ];
+        for scene in &scenes {
+            let json = SceneDescription::from_scene(scene, 16, 12)
+                .unwrap()
+                .to_json();
+            let loaded = SceneDescription::from_json(&json)
+                .unwrap()
+                .build(16, 12)
+                .unwrap();
+
+            let pixels = render(scene);
+            assert!(pixels.iter().any(|pixel| *pixel != Color::black()));
+            assert_eq!(pixels, render(&loaded));
+
+            // Nor does the image depend on the accelerator the objects are loaded in
+            let listed = SceneDescription::from_json(&json)
+                .unwrap()
+                .build(16, 12)
+                .unwrap()
+                .with_accelerator(Accelerator::List);
+            assert_eq!(pixels, render(&listed));
+        }
+    }
+}
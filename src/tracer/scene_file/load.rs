@@ -0,0 +1,1116 @@
+use crate::tracer::bounding_volumes::{SceneBVH, AABB};
+use crate::tracer::geometry::{
+    Cone, Csg, Cuboid, Cylinder, Disk, HeightMap, Heightfield, Instance, MeshData, MovingSphere,
+    Paraboloid, Plane, Quad, Sphere, Torus, Triangle, TriangleMesh,
+};
+use crate::tracer::import::{load_raw_volume, load_vol};
+use crate::tracer::material::noises::PerlinNoise;
+use crate::tracer::material::{
+    BumpMap, CheckersTexture, Dielectric, DiffuseLight, HenyeyGreenstein, ImageTexture, Isotropic,
+    Lambertian, Material, Metal, NoiseTexture, NormalMap, Sky, SolidTexture, Texture,
+    VertexColorTexture,
+};
+use crate::tracer::scene_file::*;
+use crate::tracer::sdf::{
+    DistanceFunction, Mandelbulb, Repeat, Round, SdfBox, SdfCapsule, SdfObject, SdfSphere,
+    SdfTorus, SmoothUnion, Translate, Twist,
+};
+use crate::tracer::volume::{
+    ConstantMedium, DensityField, HeterogeneousMedium, NoiseDensity, VoxelGrid,
+};
+use crate::tracer::{
+    Camera, Color, Point3f, Ray, Scene, SceneObject, SceneObjectList, SimpleCamera, Vector3f,
+};
+use cgmath::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Each subdivision multiplies the triangles of a mesh by 4
+const MAX_SUBDIVISIONS: u32 = 8;
+
+/// Reads the scene file at `path` and builds a `Scene` rendering at `width`x`height`.
+pub fn load_scene(path: &Path, width: u64, height: u64) -> Result<Scene, SceneFileError> {
+    SceneDescription::from_file(path)?.build(width, height)
+}
+
+impl SceneDescription {
+    pub fn from_json(json: &str) -> Result<SceneDescription, SceneFileError> {
+        let deserializer = &mut serde_json::Deserializer::from_str(json);
+        let description = serde_path_to_error::deserialize(deserializer)?;
+        Ok(description)
+    }
+
+    pub fn from_file(path: &Path) -> Result<SceneDescription, SceneFileError> {
+        let json = fs::read_to_string(path)?;
+        SceneDescription::from_json(&json)
+    }
+
+    pub fn build(&self, width: u64, height: u64) -> Result<Scene, SceneFileError> {
+        SceneBuilder::new(self).build(width, height)
+    }
+}
+
+/// Resolves a `SceneDescription` into tracer objects, sharing every named texture and material
+/// between the objects referencing it.
+struct SceneBuilder<'a> {
+    description: &'a SceneDescription,
+    textures: HashMap<&'a str, Arc<dyn Texture>>,
+    materials: HashMap<&'a str, Arc<dyn Material>>,
+    shapes: HashMap<&'a str, Arc<dyn SceneObject>>,
+
+    // Named textures currently being built, used to detect reference cycles
+    resolving: Vec<&'a str>,
+    // Same for named materials and shapes
+    resolving_materials: Vec<&'a str>,
+    resolving_shapes: Vec<&'a str>,
+}
+
+impl<'a> SceneBuilder<'a> {
+    fn new(description: &'a SceneDescription) -> SceneBuilder<'a> {
+        SceneBuilder {
+            description,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            shapes: HashMap::new(),
+            resolving: Vec::new(),
+            resolving_materials: Vec::new(),
+            resolving_shapes: Vec::new(),
+        }
+    }
+
+    fn build(mut self, width: u64, height: u64) -> Result<Scene, SceneFileError> {
+        let description = self.description;
+
+        if description.options.samples == 0 {
+            return Err(SceneFileError::invalid(
+                "options.samples",
+                "must be greater than 0",
+            ));
+        }
+
+        let camera = description.camera.build(width, height)?;
+
+        if description.objects.is_empty() {
+            return Err(SceneFileError::invalid(
+                "objects",
+                "scene must contain at least one object",
+            ));
+        }
+
+        let mut objects = SceneObjectList::new();
+        for (idx, object) in description.objects.iter().enumerate() {
+            let path = format!("objects[{}]", idx);
+            objects.push(self.object(object, &path)?);
+        }
+
+        let background = self.material(&description.background, "background")?;
+
+        Ok(Scene::new(
+            description.options,
+            camera,
+            Arc::new(SceneBVH::build(objects.objects)),
+            background,
+        ))
+    }
+
+    fn object(
+        &mut self,
+        description: &'a ObjectDescription,
+        path: &str,
+    ) -> Result<Arc<dyn SceneObject>, SceneFileError> {
+        match description {
+            ObjectDescription::Sphere(SphereDescription {
+                center,
+                radius,
+                material,
+            }) => {
+                let radius = positive(*radius, &format!("{}.radius", path))?;
+                let material = self.material(material, &format!("{}.material", path))?;
+                Ok(Arc::new(Sphere {
+                    center: to_point(center),
+                    radius,
+                    material,
+                }))
+            }
+            ObjectDescription::MovingSphere(MovingSphereDescription {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            }) => {
+                let radius = positive(*radius, &format!("{}.radius", path))?;
+                check_times(*time0, *time1, path)?;
+                let material = self.material(material, &format!("{}.material", path))?;
+                Ok(Arc::new(MovingSphere {
+                    center0: to_point(center0),
+                    center1: to_point(center1),
+                    time0: *time0,
+                    time1: *time1,
+                    radius,
+                    material,
+                }))
+            }
+            ObjectDescription::Plane(PlaneDescription {
+                point,
+                normal,
+                material,
+            }) => {
+                let normal = non_zero(to_vector(normal), &format!("{}.normal", path))?;
+                let material = self.material(material, &format!("{}.material", path))?;
+                Ok(Arc::new(Plane::new(to_point(point), normal, material)))
+            }
+            ObjectDescription::Quad(QuadDescription {
+                corner,
+                u,
+                v,
+                material,
+            }) => {
+                let (u, v) = (to_vector(u), to_vector(v));
+                if u.cross(v).magnitude2() == 0.0 {
+                    return Err(SceneFileError::invalid(
+                        &format!("{}.v", path),
+                        "must not be parallel to u",
+                    ));
+                }
+                let material = self.material(material, &format!("{}.material", path))?;
+                Ok(Arc::new(Quad::new(to_point(corner), u, v, material)))
+            }
+            ObjectDescription::Disk(DiskDescription {
+                center,
+                normal,
+                radius,
+                material,
+            }) => {
+                let normal = non_zero(to_vector(normal), &format!("{}.normal", path))?;
+                let radius = positive(*radius, &format!("{}.radius", path))?;
+                let material = self.material(material, &format!("{}.material", path))?;
+                Ok(Arc::new(Disk::new(
+                    to_point(center),
+                    normal,
+                    radius,
+                    material,
+                )))
+            }
+            ObjectDescription::Box(BoxDescription { min, max, material }) => {
+                if (0..3).any(|axis| min[axis] == max[axis]) {
+                    return Err(SceneFileError::invalid(
+                        &format!("{}.max", path),
+                        "must differ from min along every axis",
+                    ));
+                }
+                let material = self.material(material, &format!("{}.material", path))?;
+                Ok(Arc::new(Cuboid::new(
+                    to_point(min),
+                    to_point(max),
+                    material,
+                )))
+            }
+            ObjectDescription::Cylinder(CylinderDescription {
+                center,
+                radius,
+                height,
+                phi_max,
+                capped,
+                material,
+            }) => {
+                let radius = positive(*radius, &format!("{}.radius", path))?;
+                let height = positive(*height, &format!("{}.height", path))?;
+                let phi_max = sweep(*phi_max, path)?;
+                let material = self.material(material, &format!("{}.material", path))?;
+                let cylinder =
+                    Cylinder::new(to_point(center), radius, height, material).with_phi_max(phi_max);
+                Ok(Arc::new(if *capped { cylinder.capped() } else { cylinder }))
+            }
+            ObjectDescription::Cone(ConeDescription {
+                center,
+                radius,
+                height,
+                y_min,
+                y_max,
+                phi_max,
+                capped,
+                material,
+            }) => {
+                let radius = positive(*radius, &format!("{}.radius", path))?;
+                let height = positive(*height, &format!("{}.height", path))?;
+                let (y_min, y_max) = height_range(*y_min, *y_max, height, path)?;
+                let phi_max = sweep(*phi_max, path)?;
+                let material = self.material(material, &format!("{}.material", path))?;
+                let cone = Cone::new(to_point(center), radius, height, material)
+                    .clipped(y_min, y_max)
+                    .with_phi_max(phi_max);
+                Ok(Arc::new(if *capped { cone.capped() } else { cone }))
+            }
+            ObjectDescription::Paraboloid(ParaboloidDescription {
+                center,
+                radius,
+                height,
+                y_min,
+                y_max,
+                phi_max,
+                capped,
+                material,
+            }) => {
+                let radius = positive(*radius, &format!("{}.radius", path))?;
+                let height = positive(*height, &format!("{}.height", path))?;
+                let (y_min, y_max) = height_range(*y_min, *y_max, height, path)?;
+                let phi_max = sweep(*phi_max, path)?;
+                let material = self.material(material, &format!("{}.material", path))?;
+                let paraboloid = Paraboloid::new(to_point(center), radius, height, material)
+                    .clipped(y_min, y_max)
+                    .with_phi_max(phi_max);
+                Ok(Arc::new(if *capped {
+                    paraboloid.capped()
+                } else {
+                    paraboloid
+                }))
+            }
+            ObjectDescription::Torus(TorusDescription {
+                center,
+                major_radius,
+                minor_radius,
+                phi_max,
+                material,
+            }) => {
+                let major_radius = positive(*major_radius, &format!("{}.major_radius", path))?;
+                let minor_radius = positive(*minor_radius, &format!("{}.minor_radius", path))?;
+                let phi_max = sweep(*phi_max, path)?;
+                let material = self.material(material, &format!("{}.material", path))?;
+                let torus = Torus::new(to_point(center), major_radius, minor_radius, material);
+                Ok(Arc::new(torus.with_phi_max(phi_max)))
+            }
+            ObjectDescription::ConstantMedium(ConstantMediumDescription {
+                boundary,
+                density,
+                phase,
+            }) => {
+                let boundary = self.object(boundary, &format!("{}.boundary", path))?;
+                let density = positive(*density, &format!("{}.density", path))?;
+                let phase = self.material(phase, &format!("{}.phase", path))?;
+                Ok(Arc::new(ConstantMedium::new(boundary, density, phase)))
+            }
+            ObjectDescription::HeterogeneousMedium(HeterogeneousMediumDescription {
+                boundary,
+                density,
+                phase,
+                emission,
+            }) => {
+                let boundary = self.object(boundary, &format!("{}.boundary", path))?;
+                let density = build_density(density, &format!("{}.density", path))?;
+                let phase = self.material(phase, &format!("{}.phase", path))?;
+                let medium = HeterogeneousMedium::new(boundary, density, phase);
+                match emission {
+                    Some(emission) => {
+                        let emission = self.texture(emission, &format!("{}.emission", path))?;
+                        Ok(Arc::new(medium.with_emission(emission)))
+                    }
+                    None => Ok(Arc::new(medium)),
+                }
+            }
+            ObjectDescription::Csg(CsgDescription {
+                operation,
+                left,
+                right,
+                material,
+            }) => {
+                let left = closed(self.object(left, &format!("{}.left", path))?, path, "left")?;
+                let right = closed(
+                    self.object(right, &format!("{}.right", path))?,
+                    path,
+                    "right",
+                )?;
+                let material = self.material(material, &format!("{}.material", path))?;
+                Ok(Arc::new(Csg::new(*operation, left, right, material)))
+            }
+            ObjectDescription::Sdf(SdfObjectDescription {
+                sdf,
+                bounds: [min, max],
+                material,
+                max_steps,
+                epsilon,
+            }) => {
+                let distance = build_sdf(sdf, &format!("{}.sdf", path))?;
+                let bounds = bounds_range(min, max, &format!("{}.bounds", path))?;
+                let material = self.material(material, &format!("{}.material", path))?;
+                let epsilon = positive(*epsilon, &format!("{}.epsilon", path))?;
+                let object = SdfObject::new(distance, bounds, material);
+                Ok(Arc::new(
+                    object.with_max_steps(*max_steps).with_epsilon(epsilon),
+                ))
+            }
+            ObjectDescription::Heightfield(HeightfieldDescription {
+                height_map,
+                corner,
+                size,
+                material,
+            }) => {
+                let map = build_height_map(height_map, &format!("{}.height_map", path))?;
+                for extent in size {
+                    positive(*extent, &format!("{}.size", path))?;
+                }
+                let material = self.material(material, &format!("{}.material", path))?;
+                let heightfield =
+                    Heightfield::new(map, to_point(corner), to_vector(size), material);
+                Ok(Arc::new(heightfield))
+            }
+            ObjectDescription::Triangle(TriangleDescription {
+                vertices,
+                normals,
+                uvs,
+                colors,
+                material,
+            }) => {
+                let data = MeshData {
+                    positions: vertices.iter().map(to_point).collect(),
+                    normals: normals.iter().flatten().map(to_vector).collect(),
+                    uvs: uvs.iter().flatten().map(|uv| (uv[0], uv[1])).collect(),
+                    colors: colors.iter().flatten().map(to_vector).collect(),
+                    indices: vec![[0, 1, 2]],
+                    material: self.material(material, &format!("{}.material", path))?,
+                };
+                Ok(Arc::new(Triangle::from_mesh(Arc::new(data), 0)))
+            }
+            ObjectDescription::Mesh(MeshDescription {
+                positions,
+                normals,
+                uvs,
+                colors,
+                indices,
+                material,
+                displacement,
+            }) => {
+                let mut data = MeshData {
+                    positions: positions.iter().map(to_point).collect(),
+                    normals: normals.iter().map(to_vector).collect(),
+                    uvs: uvs.iter().map(|uv| (uv[0], uv[1])).collect(),
+                    colors: colors.iter().map(to_vector).collect(),
+                    indices: indices.clone(),
+                    material: self.material(material, &format!("{}.material", path))?,
+                };
+                data.validate()
+                    .map_err(|error| SceneFileError::invalid(path, error))?;
+
+                if let Some(DisplacementDescription {
+                    height,
+                    scale,
+                    subdivisions,
+                }) = displacement
+                {
+                    let path = format!("{}.displacement", path);
+                    if *subdivisions > MAX_SUBDIVISIONS {
+                        return Err(SceneFileError::invalid(
+                            &format!("{}.subdivisions", path),
+                            format!("must be at most {}", MAX_SUBDIVISIONS),
+                        ));
+                    }
+                    let height = self.texture(height, &format!("{}.height", path))?;
+                    data = data.subdivided(*subdivisions).displaced(&*height, *scale);
+                }
+                Ok(Arc::new(TriangleMesh::new(data)))
+            }
+            ObjectDescription::Instance(InstanceDescription {
+                object,
+                transform,
+                motion,
+            }) => {
+                let object = self.shape(object, &format!("{}.object", path))?;
+                let transform = build_transform(transform, &format!("{}.transform", path))?;
+                let instance = match motion {
+                    Some(MotionDescription {
+                        transform: end,
+                        time0,
+                        time1,
+                    }) => {
+                        let end = build_transform(end, &format!("{}.motion.transform", path))?;
+                        check_times(*time0, *time1, &format!("{}.motion", path))?;
+                        Instance::moving(object, transform, end, *time0, *time1)
+                    }
+                    None => Instance::new(object, transform),
+                };
+                Ok(Arc::new(instance))
+            }
+        }
+    }
+
+    /// The object of an instance, built once for all the instances of a named shape.
+    fn shape(
+        &mut self,
+        object: &'a ObjectRef,
+        path: &str,
+    ) -> Result<Arc<dyn SceneObject>, SceneFileError> {
+        match object {
+            ObjectRef::Named(name) => {
+                if let Some(shape) = self.shapes.get(name.as_str()) {
+                    return Ok(shape.clone());
+                }
+
+                if self.resolving_shapes.contains(&name.as_str()) {
+                    return Err(SceneFileError::invalid(
+                        path,
+                        format!("shape `{}` references itself", name),
+                    ));
+                }
+
+                let description = self.description.shapes.get(name).ok_or_else(|| {
+                    SceneFileError::invalid(path, format!("unknown shape `{}`", name))
+                })?;
+
+                self.resolving_shapes.push(name);
+                let shape = self.object(description, &format!("shapes.{}", name));
+                self.resolving_shapes.pop();
+
+                let shape = shape?;
+                self.shapes.insert(name, shape.clone());
+                Ok(shape)
+            }
+            ObjectRef::Inline(description) => self.object(description, path),
+        }
+    }
+
+    fn material(
+        &mut self,
+        material: &'a MaterialRef,
+        path: &str,
+    ) -> Result<Arc<dyn Material>, SceneFileError> {
+        match material {
+            MaterialRef::Named(name) => {
+                if let Some(material) = self.materials.get(name.as_str()) {
+                    return Ok(material.clone());
+                }
+
+                if self.resolving_materials.contains(&name.as_str()) {
+                    return Err(SceneFileError::invalid(
+                        path,
+                        format!("material `{}` references itself", name),
+                    ));
+                }
+
+                let description = self.description.materials.get(name).ok_or_else(|| {
+                    SceneFileError::invalid(path, format!("unknown material `{}`", name))
+                })?;
+
+                self.resolving_materials.push(name);
+                let material = self.build_material(description, &format!("materials.{}", name));
+                self.resolving_materials.pop();
+
+                let material = material?;
+                self.materials.insert(name, material.clone());
+                Ok(material)
+            }
+            MaterialRef::Inline(description) => self.build_material(description, path),
+        }
+    }
+
+    fn build_material(
+        &mut self,
+        description: &'a MaterialDescription,
+        path: &str,
+    ) -> Result<Arc<dyn Material>, SceneFileError> {
+        let material: Arc<dyn Material> = match description {
+            MaterialDescription::Lambertian(LambertianDescription { albedo }) => {
+                let albedo = self.texture(albedo, &format!("{}.albedo", path))?;
+                Arc::new(Lambertian::new(albedo))
+            }
+            MaterialDescription::Metal(MetalDescription { albedo, fuzz }) => {
+                if !(0.0..=1.0).contains(fuzz) {
+                    return Err(SceneFileError::invalid(
+                        &format!("{}.fuzz", path),
+                        "must be between 0 and 1",
+                    ));
+                }
+                Arc::new(Metal::new(to_vector(albedo), *fuzz))
+            }
+            MaterialDescription::Dielectric(DielectricDescription { refractive_index }) => {
+                let path = format!("{}.refractive_index", path);
+                Arc::new(Dielectric::new(positive(*refractive_index, &path)?))
+            }
+            MaterialDescription::DiffuseLight(DiffuseLightDescription { emit }) => {
+                let emit = self.texture(emit, &format!("{}.emit", path))?;
+                Arc::new(DiffuseLight::new(emit))
+            }
+            MaterialDescription::Sky(SkyDescription { horizon, zenith }) => {
+                Arc::new(Sky::new(to_color(horizon), to_color(zenith)))
+            }
+            MaterialDescription::Isotropic(IsotropicDescription { albedo }) => {
+                let albedo = self.texture(albedo, &format!("{}.albedo", path))?;
+                Arc::new(Isotropic::new(albedo))
+            }
+            MaterialDescription::BumpMap(BumpMapDescription {
+                material,
+                height,
+                scale,
+            }) => {
+                let material = self.material(material, &format!("{}.material", path))?;
+                let height = self.texture(height, &format!("{}.height", path))?;
+                Arc::new(BumpMap::new(material, height, *scale))
+            }
+            MaterialDescription::NormalMap(NormalMapDescription { material, normals }) => {
+                let material = self.material(material, &format!("{}.material", path))?;
+                let normals = self.texture(normals, &format!("{}.normals", path))?;
+                Arc::new(NormalMap::new(material, normals))
+            }
+            MaterialDescription::HenyeyGreenstein(HenyeyGreensteinDescription { albedo, g }) => {
+                if !(-1.0 < *g && *g < 1.0) {
+                    return Err(SceneFileError::invalid(
+                        &format!("{}.g", path),
+                        "must be between -1 and 1 excluded",
+                    ));
+                }
+                let albedo = self.texture(albedo, &format!("{}.albedo", path))?;
+                Arc::new(HenyeyGreenstein::new(albedo, *g))
+            }
+        };
+
+        Ok(material)
+    }
+
+    fn texture(
+        &mut self,
+        texture: &'a TextureRef,
+        path: &str,
+    ) -> Result<Arc<dyn Texture>, SceneFileError> {
+        match texture {
+            TextureRef::Named(name) => {
+                if let Some(texture) = self.textures.get(name.as_str()) {
+                    return Ok(texture.clone());
+                }
+
+                if self.resolving.contains(&name.as_str()) {
+                    return Err(SceneFileError::invalid(
+                        path,
+                        format!("texture `{}` references itself", name),
+                    ));
+                }
+
+                let description = self.description.textures.get(name).ok_or_else(|| {
+                    SceneFileError::invalid(path, format!("unknown texture `{}`", name))
+                })?;
+
+                self.resolving.push(name);
+                let texture = self.build_texture(description, &format!("textures.{}", name));
+                self.resolving.pop();
+
+                let texture = texture?;
+                self.textures.insert(name, texture.clone());
+                Ok(texture)
+            }
+            TextureRef::Color(color) => Ok(Arc::new(SolidTexture::new(to_color(color)))),
+            TextureRef::Inline(description) => self.build_texture(description, path),
+        }
+    }
+
+    fn build_texture(
+        &mut self,
+        description: &'a TextureDescription,
+        path: &str,
+    ) -> Result<Arc<dyn Texture>, SceneFileError> {
+        let texture: Arc<dyn Texture> = match description {
+            TextureDescription::Solid(SolidTextureDescription { color }) => {
+                Arc::new(SolidTexture::new(to_color(color)))
+            }
+            TextureDescription::Checkers(CheckersTextureDescription { odd, even, scale }) => {
+                let odd = self.texture(odd, &format!("{}.odd", path))?;
+                let even = self.texture(even, &format!("{}.even", path))?;
+                Arc::new(CheckersTexture::new(odd, even, *scale))
+            }
+            TextureDescription::Noise(NoiseTextureDescription {
+                scale,
+                octaves,
+                frequency,
+                persistence,
+                lacunarity,
+                color_start,
+                color_end,
+            }) => Arc::new(NoiseTexture::new(
+                *scale,
+                *octaves,
+                *frequency,
+                *persistence,
+                *lacunarity,
+                to_color(color_start),
+                to_color(color_end),
+            )),
+            TextureDescription::Image(ImageTextureDescription { path: image_path }) => {
+                let texture = ImageTexture::open(image_path).map_err(|error| {
+                    SceneFileError::invalid(
+                        &format!("{}.path", path),
+                        format!("failed to load {:?}: {}", image_path, error),
+                    )
+                })?;
+                Arc::new(texture)
+            }
+            TextureDescription::VertexColor(VertexColorTextureDescription { fallback }) => {
+                Arc::new(VertexColorTexture::new(to_color(fallback)))
+            }
+        };
+
+        Ok(texture)
+    }
+}
+
+impl CameraDescription {
+    /// Builds the camera for rendering at `width`x`height`.
+    pub fn build(&self, width: u64, height: u64) -> Result<Arc<dyn Camera>, SceneFileError> {
+        match self {
+            CameraDescription::Simple(SimpleCameraDescription {
+                look_from,
+                look_at,
+                up,
+                vfov,
+                aspect,
+                aperture,
+                focus_dist,
+                shutter_open,
+                shutter_close,
+            }) => {
+                let look_from = to_point(look_from);
+                let look_at = to_vector(look_at);
+                let up = to_vector(up);
+
+                let view = look_at - look_from.to_vec();
+                if view.magnitude2() == 0.0 {
+                    return Err(SceneFileError::invalid(
+                        "camera.look_at",
+                        "must differ from camera.look_from",
+                    ));
+                }
+                if up.cross(view).magnitude2() == 0.0 {
+                    return Err(SceneFileError::invalid(
+                        "camera.up",
+                        "must not be parallel to the viewing direction",
+                    ));
+                }
+                if !(*vfov > 0.0 && *vfov < 180.0) {
+                    return Err(SceneFileError::invalid(
+                        "camera.vfov",
+                        "must be between 0 and 180 degrees",
+                    ));
+                }
+                let focus_dist = positive(*focus_dist, "camera.focus_dist")?;
+                if shutter_close < shutter_open {
+                    return Err(SceneFileError::invalid(
+                        "camera.shutter_close",
+                        "must not be before camera.shutter_open",
+                    ));
+                }
+
+                let aspect = aspect.unwrap_or(width as f64 / height as f64);
+                let camera =
+                    SimpleCamera::new(look_from, look_at, up, *vfov, aspect, *aperture, focus_dist)
+                        .with_shutter(*shutter_open, *shutter_close);
+                Ok(Arc::new(camera))
+            }
+        }
+    }
+}
+
+fn build_density(
+    description: &DensityDescription,
+    path: &str,
+) -> Result<Arc<dyn DensityField>, SceneFileError> {
+    match description {
+        DensityDescription::Noise(NoiseDensityDescription {
+            density,
+            scale,
+            cutoff,
+            octaves,
+            frequency,
+            persistence,
+            lacunarity,
+        }) => {
+            let density = positive(*density, &format!("{}.density", path))?;
+            if !(0.0..1.0).contains(cutoff) {
+                return Err(SceneFileError::invalid(
+                    &format!("{}.cutoff", path),
+                    "must be between 0 included and 1 excluded",
+                ));
+            }
+            let noise = PerlinNoise::new(*octaves, *frequency, *persistence, *lacunarity);
+            Ok(Arc::new(NoiseDensity::new(noise, *scale, density, *cutoff)))
+        }
+        DensityDescription::VoxelGrid(VoxelGridDescription {
+            path: grid_path,
+            format,
+            resolution,
+            bounds,
+            scale,
+        }) => {
+            let grid = match (format, resolution) {
+                (VolumeFormat::Vol, None) => load_vol(grid_path),
+                (VolumeFormat::Vol, Some(_)) => {
+                    return Err(SceneFileError::invalid(
+                        &format!("{}.resolution", path),
+                        "is read from the header of .vol files",
+                    ))
+                }
+                (_, Some(resolution)) => load_raw_volume(grid_path, *resolution, *format),
+                (_, None) => {
+                    return Err(SceneFileError::invalid(
+                        &format!("{}.resolution", path),
+                        "is required for raw files",
+                    ))
+                }
+            };
+            let mut grid: VoxelGrid = grid.map_err(|error| {
+                SceneFileError::invalid(&format!("{}.path", path), error.to_string())
+            })?;
+
+            if let Some([min, max]) = bounds {
+                grid = grid.with_bounds(bounds_range(min, max, &format!("{}.bounds", path))?);
+            }
+            let scale = positive(*scale, &format!("{}.scale", path))?;
+            Ok(Arc::new(grid.with_scale(scale)))
+        }
+    }
+}
+
+fn build_height_map(
+    description: &HeightMapDescription,
+    path: &str,
+) -> Result<HeightMap, SceneFileError> {
+    match description {
+        HeightMapDescription::Noise(NoiseHeightMapDescription {
+            resolution,
+            scale,
+            octaves,
+            frequency,
+            persistence,
+            lacunarity,
+        }) => {
+            if resolution.iter().any(|&samples| samples < 2) {
+                return Err(SceneFileError::invalid(
+                    &format!("{}.resolution", path),
+                    "must be at least 2 along both axes",
+                ));
+            }
+            let noise = PerlinNoise::new(*octaves, *frequency, *persistence, *lacunarity);
+            Ok(HeightMap::from_noise(noise, *resolution, *scale))
+        }
+        HeightMapDescription::Image(ImageHeightMapDescription { path: image_path }) => {
+            HeightMap::open(image_path).map_err(|error| {
+                SceneFileError::invalid(
+                    &format!("{}.path", path),
+                    format!("failed to load {:?}: {}", image_path, error),
+                )
+            })
+        }
+    }
+}
+
+fn build_sdf(
+    description: &SdfDescription,
+    path: &str,
+) -> Result<Arc<dyn DistanceFunction>, SceneFileError> {
+    let inner = |sdf: &SdfDescription| build_sdf(sdf, &format!("{}.sdf", path));
+    let distance: Arc<dyn DistanceFunction> = match description {
+        SdfDescription::Sphere(SdfSphereDescription { radius }) => Arc::new(SdfSphere::new(
+            positive(*radius, &format!("{}.radius", path))?,
+        )),
+        SdfDescription::Box(SdfBoxDescription { half_size }) => {
+            for size in half_size {
+                positive(*size, &format!("{}.half_size", path))?;
+            }
+            Arc::new(SdfBox::new(to_vector(half_size)))
+        }
+        SdfDescription::Torus(SdfTorusDescription {
+            major_radius,
+            minor_radius,
+        }) => Arc::new(SdfTorus::new(
+            positive(*major_radius, &format!("{}.major_radius", path))?,
+            positive(*minor_radius, &format!("{}.minor_radius", path))?,
+        )),
+        SdfDescription::Capsule(SdfCapsuleDescription { a, b, radius }) => {
+            let radius = positive(*radius, &format!("{}.radius", path))?;
+            Arc::new(SdfCapsule::new(to_point(a), to_point(b), radius))
+        }
+        SdfDescription::Mandelbulb(MandelbulbDescription { power, iterations }) => {
+            let power = positive(*power, &format!("{}.power", path))?;
+            Arc::new(Mandelbulb::new(power, *iterations))
+        }
+        SdfDescription::Translate(TranslateSdfDescription { sdf, offset }) => {
+            Arc::new(Translate::new(inner(sdf)?, to_vector(offset)))
+        }
+        SdfDescription::SmoothUnion(SmoothUnionDescription { a, b, smoothness }) => {
+            let a = build_sdf(a, &format!("{}.a", path))?;
+            let b = build_sdf(b, &format!("{}.b", path))?;
+            if *smoothness < 0.0 {
+                return Err(SceneFileError::invalid(
+                    &format!("{}.smoothness", path),
+                    "must not be negative",
+                ));
+            }
+            Arc::new(SmoothUnion::new(a, b, *smoothness))
+        }
+        SdfDescription::Twist(TwistDescription { sdf, rate }) => {
+            Arc::new(Twist::new(inner(sdf)?, *rate))
+        }
+        SdfDescription::Repeat(RepeatDescription { sdf, period, count }) => {
+            if period.iter().any(|&period| period < 0.0) {
+                return Err(SceneFileError::invalid(
+                    &format!("{}.period", path),
+                    "must not be negative",
+                ));
+            }
+            Arc::new(Repeat::new(inner(sdf)?, to_vector(period), *count))
+        }
+        SdfDescription::Round(RoundDescription { sdf, radius }) => {
+            let radius = positive(*radius, &format!("{}.radius", path))?;
+            Arc::new(Round::new(inner(sdf)?, radius))
+        }
+    };
+
+    Ok(distance)
+}
+
+/// Bounds between the `min` and `max` corners of the object at `path`.
+fn bounds_range(
+    min: &Vec3Description,
+    max: &Vec3Description,
+    path: &str,
+) -> Result<AABB, SceneFileError> {
+    if (0..3).any(|axis| min[axis] >= max[axis]) {
+        return Err(SceneFileError::invalid(path, "min must be less than max"));
+    }
+    Ok(AABB::new(to_vector(min), to_vector(max)))
+}
+
+/// Checks that `object`, at `field` of the object at `path`, encloses a volume that
+/// constructive solid geometry can combine.
+fn closed(
+    object: Arc<dyn SceneObject>,
+    path: &str,
+    field: &str,
+) -> Result<Arc<dyn SceneObject>, SceneFileError> {
+    // Whether intervals are supported doesn't depend on the ray
+    let ray = Ray::new(Point3::origin(), Vector3f::unit_x());
+    if object.intervals(&ray).is_none() {
+        return Err(SceneFileError::invalid(
+            &format!("{}.{}", path, field),
+            "must be a sphere, a box, an instance of them or a csg solid",
+        ));
+    }
+    Ok(object)
+}
+
+/// Checks that the motion from `time0` to `time1` of the object at `path` goes forward.
+fn check_times(time0: f64, time1: f64, path: &str) -> Result<(), SceneFileError> {
+    if time1 < time0 {
+        return Err(SceneFileError::invalid(
+            &format!("{}.time1", path),
+            "must not be before time0",
+        ));
+    }
+    Ok(())
+}
+
+/// Composes `steps`, the first step being applied first.
+fn build_transform(
+    steps: &[TransformDescription],
+    path: &str,
+) -> Result<Matrix4<f64>, SceneFileError> {
+    let mut transform = Matrix4::identity();
+    for (idx, step) in steps.iter().enumerate() {
+        let step_path = format!("{}[{}]", path, idx);
+        let matrix = match step {
+            TransformDescription::Translate(TranslateDescription { offset }) => {
+                Matrix4::from_translation(to_vector(offset))
+            }
+            TransformDescription::Rotate(RotateDescription { axis, degrees }) => {
+                let axis = to_vector(axis);
+                if axis.magnitude2() == 0.0 {
+                    return Err(SceneFileError::invalid(
+                        &format!("{}.axis", step_path),
+                        "must not be zero",
+                    ));
+                }
+                Matrix4::from_axis_angle(axis.normalize(), Deg(*degrees))
+            }
+            TransformDescription::Scale(ScaleDescription { factor }) => {
+                Matrix4::from_nonuniform_scale(factor[0], factor[1], factor[2])
+            }
+            TransformDescription::Matrix(MatrixDescription { rows }) => {
+                Matrix4::from(*rows).transpose()
+            }
+        };
+        transform = matrix * transform;
+    }
+
+    if transform.determinant() == 0.0 || !transform.determinant().is_finite() {
+        return Err(SceneFileError::invalid(
+            path,
+            "transform must be invertible",
+        ));
+    }
+    Ok(transform)
+}
+
+fn positive(value: f64, path: &str) -> Result<f64, SceneFileError> {
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(SceneFileError::invalid(path, "must be greater than 0"))
+    }
+}
+
+/// Converts the `phi_max` sweep of the quadric at `path` from degrees to radians.
+fn sweep(degrees: f64, path: &str) -> Result<f64, SceneFileError> {
+    if degrees > 0.0 && degrees <= 360.0 {
+        Ok(degrees.to_radians())
+    } else {
+        Err(SceneFileError::invalid(
+            &format!("{}.phi_max", path),
+            "must be between 0 and 360 degrees",
+        ))
+    }
+}
+
+/// The clipping range of the quadric at `path`, `y_max` defaulting to its `height`.
+fn height_range(
+    y_min: f64,
+    y_max: Option<f64>,
+    height: f64,
+    path: &str,
+) -> Result<(f64, f64), SceneFileError> {
+    let y_max = y_max.unwrap_or(height);
+    if y_min < 0.0 || y_min >= height {
+        return Err(SceneFileError::invalid(
+            &format!("{}.y_min", path),
+            "must be between 0 and height",
+        ));
+    }
+    if y_max <= y_min || y_max > height {
+        return Err(SceneFileError::invalid(
+            &format!("{}.y_max", path),
+            "must be between y_min and height",
+        ));
+    }
+    Ok((y_min, y_max))
+}
+
+fn non_zero(vector: Vector3f, path: &str) -> Result<Vector3f, SceneFileError> {
+    if vector.magnitude2() > 0.0 {
+        Ok(vector)
+    } else {
+        Err(SceneFileError::invalid(path, "must not be a zero vector"))
+    }
+}
+
+fn to_point(v: &Vec3Description) -> Point3f {
+    Point3::new(v[0], v[1], v[2])
+}
+
+fn to_vector(v: &Vec3Description) -> Vector3f {
+    vec3(v[0], v[1], v[2])
+}
+
+fn to_color(v: &Vec3Description) -> Color {
+    Color::new(v[0], v[1], v[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::{RenderOpts, SceneIntersectable};
+
+    const SCENE: &str = r#"{
+        "camera": {"type": "simple", "look_from": [13, 2, 3], "look_at": [0, 0, 0], "vfov": 20, "focus_dist": 10},
+        "textures": {"checks": {"type": "checkers", "odd": [0, 0, 0], "even": "white", "scale": 1}, "white": {"type": "solid", "color": [1, 1, 1]}},
+        "materials": {"ground": {"type": "lambertian", "albedo": "checks"}},
+        "objects": [
+            {"type": "sphere", "center": [0, -1000, 0], "radius": 1000, "material": "ground"},
+            {"type": "sphere", "center": [0, 1, 0], "radius": 1, "material": {"type": "dielectric", "refractive_index": 1.5}}
+        ]
+    }"#;
+
+    fn error_path(json: &str) -> String {
+        match SceneDescription::from_json(json).and_then(|d| d.build(40, 30).map(|_| d)) {
+            Err(SceneFileError::Parse { path, .. }) | Err(SceneFileError::Invalid { path, .. }) => {
+                path
+            }
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let description = SceneDescription::from_json(SCENE).unwrap();
+        let scene = description.build(40, 30).unwrap();
+        assert_eq!(scene.options.samples, RenderOpts::default().samples);
+    }
+
+    #[test]
+    fn test_error_paths() {
+        let json = SCENE.replace(r#""radius": 1,"#, r#""radius": "one","#);
+        assert_eq!(error_path(&json), "objects[1].radius");
+
+        let json = SCENE.replace(r#""refractive_index": 1.5"#, r#""refractive_index": -1"#);
+        assert_eq!(error_path(&json), "objects[1].material.refractive_index");
+
+        let json = SCENE.replace(r#""type": "dielectric""#, r#""type": "glass""#);
+        assert_eq!(error_path(&json), "objects[1].material.type");
+
+        let json = SCENE.replace(r#""even": "white""#, r#""even": "checks""#);
+        assert_eq!(error_path(&json), "textures.checks.even");
+
+        let json = SCENE.replace(
+            r#""albedo": "checks"}}"#,
+            r#""albedo": "checks"}, "bumpy": {"type": "bump_map", "material": "bumpy", "height": "checks"}}"#,
+        )
+        .replace(r#""material": "ground""#, r#""material": "bumpy""#);
+        assert_eq!(error_path(&json), "materials.bumpy.material");
+
+        let json = SCENE.replace(r#""material": "ground""#, r#""material": "grass""#);
+        assert_eq!(error_path(&json), "objects[0].material");
+    }
+
+    #[test]
+    fn test_shared_shapes() {
+        let instances: Vec<String> = (0..10_000)
+            .map(|i| {
+                format!(
+                    r#"{{"type": "instance", "object": "tree", "transform": [{{"type": "translate", "offset": [{}, 0, {}]}}]}}"#,
+                    i % 100,
+                    i / 100
+                )
+            })
+            .collect();
+        let json = format!(
+            r#"{{
+                "camera": {{"type": "simple", "look_from": [50, 20, -20], "look_at": [50, 0, 50], "vfov": 40, "focus_dist": 10}},
+                "shapes": {{"tree": {{
+                    "type": "mesh",
+                    "positions": [[0, 0, 0], [1, 0, 0], [0, 0, 1], [0, 2, 0]],
+                    "indices": [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]],
+                    "material": {{"type": "lambertian", "albedo": [0.2, 0.6, 0.2]}}
+                }}}},
+                "objects": [{}]
+            }}"#,
+            instances.join(",")
+        );
+        let description = SceneDescription::from_json(&json).unwrap();
+
+        let mut builder = SceneBuilder::new(&description);
+        let tree = ObjectRef::Named("tree".to_string());
+        let first = builder.shape(&tree, "objects[0].object").unwrap();
+        let second = builder.shape(&tree, "objects[1].object").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let scene = description.build(40, 30).unwrap();
+        assert_eq!(scene.leaves().len(), 10_000);
+
+        let json = json.replace(r#""object": "tree""#, r#""object": "bush""#);
+        assert_eq!(error_path(&json), "objects[0].object");
+
+        let json = SCENE.replace(
+            r#""objects": ["#,
+            r#""shapes": {"loop": {"type": "instance", "object": "loop", "transform": []}},
+            "objects": [{"type": "instance", "object": "loop", "transform": []},"#,
+        );
+        assert_eq!(error_path(&json), "shapes.loop.object");
+    }
+}
@@ -0,0 +1,10 @@
+#[macro_use]
+mod de;
+mod description;
+mod error;
+mod export;
+mod load;
+
+pub use description::*;
+pub use error::*;
+pub use load::*;
@@ -1,5 +1,6 @@
-use crate::tracer::bounding_volumes::Boundable;
+use crate::tracer::bounding_volumes::{Accelerator, BVHNode, BVHStats, Boundable};
 use crate::tracer::material::Material;
+use crate::tracer::scene_file::ObjectDescription;
 use crate::tracer::{Intersectable, Intersection, Point3f, Ray};
 use std::sync::Arc;
 
@@ -9,6 +10,22 @@ pub trait SceneObject: Intersectable + Boundable + Sync + Send {
     fn primitives(&self) -> u64 {
         1
     }
+
+    /// BVHs nested in this object, such as the one over the triangles of a mesh.
+    fn bvhs(&self) -> Vec<&BVHNode> {
+        Vec::new()
+    }
+
+    /// Bottom-level BVH holding the whole object in its own space, which the instances of the
+    /// object traverse directly. `None` for objects without one, intersected as a whole.
+    fn blas(&self) -> Option<&Arc<BVHNode>> {
+        None
+    }
+
+    /// Scene file description of this object, `None` when it can't be exported.
+    fn describe(&self) -> Option<ObjectDescription> {
+        None
+    }
 }
 
 pub struct SceneIntersection {
@@ -18,4 +35,21 @@ pub struct SceneIntersection {
 
 pub trait SceneIntersectable: Sync + Send {
     fn intersect(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<SceneIntersection>;
+
+    /// Whether any object is hit by `ray` between `dist_min` and `dist_max`, returning as soon
+    /// as one is found. Cheaper than `intersect` for shadow and ambient occlusion rays.
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool;
+
+    /// All the scene objects held by this structure.
+    fn leaves(&self) -> Vec<Arc<dyn SceneObject>>;
+
+    /// The accelerator this structure is, `None` for structures `Accelerator` doesn't build.
+    fn accelerator(&self) -> Option<Accelerator> {
+        None
+    }
+
+    /// Stats of the BVH this structure holds its objects in, `None` when it isn't one.
+    fn bvh_stats(&self) -> Option<BVHStats> {
+        None
+    }
 }
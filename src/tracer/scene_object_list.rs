@@ -1,3 +1,4 @@
+use crate::tracer::bounding_volumes::{Accelerator, TraversalCounters};
 use crate::tracer::{Intersection, Ray, SceneIntersectable, SceneIntersection, SceneObject};
 use std::sync::Arc;
 
@@ -25,11 +26,15 @@ impl Default for SceneObjectList {
 
 impl SceneIntersectable for SceneObjectList {
     fn intersect(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<SceneIntersection> {
+        let counting = TraversalCounters::enabled();
         let mut closest: Option<Intersection> = None;
         let mut closest_obj: Option<Arc<dyn SceneObject>> = None;
         let mut closest_dist = dist_max;
 
         for obj in self.objects.iter() {
+            if counting {
+                TraversalCounters::count_primitive();
+            }
             let maybe_intersect = obj.intersects(ray, dist_min, closest_dist);
             if let Some(ref inter) = maybe_intersect {
                 closest_dist = inter.dist;
@@ -48,4 +53,22 @@ impl SceneIntersectable for SceneObjectList {
 
         None
     }
+
+    fn occluded(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> bool {
+        let counting = TraversalCounters::enabled();
+        self.objects.iter().any(|object| {
+            if counting {
+                TraversalCounters::count_primitive();
+            }
+            object.occluded(ray, dist_min, dist_max)
+        })
+    }
+
+    fn leaves(&self) -> Vec<Arc<dyn SceneObject>> {
+        self.objects.clone()
+    }
+
+    fn accelerator(&self) -> Option<Accelerator> {
+        Some(Accelerator::List)
+    }
 }
@@ -0,0 +1,172 @@
+use crate::tracer::scene_file::{
+    RepeatDescription, RoundDescription, SdfDescription, SmoothUnionDescription,
+    TranslateSdfDescription, TwistDescription,
+};
+use crate::tracer::sdf::DistanceFunction;
+use crate::tracer::{Point3f, Vector3f};
+use cgmath::*;
+use std::sync::Arc;
+
+/// A distance function moved by `offset`.
+pub struct Translate {
+    inner: Arc<dyn DistanceFunction>,
+    offset: Vector3f,
+}
+
+impl Translate {
+    pub fn new(inner: Arc<dyn DistanceFunction>, offset: Vector3f) -> Translate {
+        Translate { inner, offset }
+    }
+}
+
+impl DistanceFunction for Translate {
+    fn distance(&self, p: Point3f) -> f64 {
+        self.inner.distance(p - self.offset)
+    }
+
+    fn describe(&self) -> Option<SdfDescription> {
+        Some(SdfDescription::Translate(TranslateSdfDescription {
+            sdf: Box::new(self.inner.describe()?),
+            offset: self.offset.into(),
+        }))
+    }
+}
+
+/// The union of two distance functions, blended over a distance of `smoothness` where they
+/// meet.
+pub struct SmoothUnion {
+    a: Arc<dyn DistanceFunction>,
+    b: Arc<dyn DistanceFunction>,
+    smoothness: f64,
+}
+
+impl SmoothUnion {
+    pub fn new(
+        a: Arc<dyn DistanceFunction>,
+        b: Arc<dyn DistanceFunction>,
+        smoothness: f64,
+    ) -> SmoothUnion {
+        SmoothUnion { a, b, smoothness }
+    }
+}
+
+impl DistanceFunction for SmoothUnion {
+    fn distance(&self, p: Point3f) -> f64 {
+        let (a, b) = (self.a.distance(p), self.b.distance(p));
+        if self.smoothness <= 0.0 {
+            return a.min(b);
+        }
+        // Polynomial smooth minimum
+        let h = (0.5 + 0.5 * (b - a) / self.smoothness).clamp(0.0, 1.0);
+        b + (a - b) * h - self.smoothness * h * (1.0 - h)
+    }
+
+    fn describe(&self) -> Option<SdfDescription> {
+        Some(SdfDescription::SmoothUnion(SmoothUnionDescription {
+            a: Box::new(self.a.describe()?),
+            b: Box::new(self.b.describe()?),
+            smoothness: self.smoothness,
+        }))
+    }
+}
+
+/// A distance function twisted around the y axis by `rate` radians per unit of height.
+///
+/// Twisting stretches distances away from the axis, so distances are shrunk to keep sphere
+/// tracing from overshooting the surface.
+pub struct Twist {
+    inner: Arc<dyn DistanceFunction>,
+    rate: f64,
+}
+
+impl Twist {
+    pub fn new(inner: Arc<dyn DistanceFunction>, rate: f64) -> Twist {
+        Twist { inner, rate }
+    }
+}
+
+impl DistanceFunction for Twist {
+    fn distance(&self, p: Point3f) -> f64 {
+        let angle = self.rate * p.y;
+        let (sin, cos) = angle.sin_cos();
+        let q = Point3::new(cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z);
+        let stretch = self.rate * vec2(p.x, p.z).magnitude();
+        self.inner.distance(q) / (1.0 + stretch * stretch).sqrt()
+    }
+
+    fn describe(&self) -> Option<SdfDescription> {
+        Some(SdfDescription::Twist(TwistDescription {
+            sdf: Box::new(self.inner.describe()?),
+            rate: self.rate,
+        }))
+    }
+}
+
+/// Copies of a distance function centered on the origin laid out every `period` along each
+/// axis, `count` times on both sides of the original. Axes with a zero period or count aren't
+/// repeated.
+///
+/// Copies must fit in their period for distances to stay correct.
+pub struct Repeat {
+    inner: Arc<dyn DistanceFunction>,
+    period: Vector3f,
+    count: [u32; 3],
+}
+
+impl Repeat {
+    pub fn new(inner: Arc<dyn DistanceFunction>, period: Vector3f, count: [u32; 3]) -> Repeat {
+        Repeat {
+            inner,
+            period,
+            count,
+        }
+    }
+}
+
+impl DistanceFunction for Repeat {
+    fn distance(&self, p: Point3f) -> f64 {
+        let mut q = p;
+        for axis in 0..3 {
+            let period = self.period[axis];
+            if period > 0.0 {
+                let count = self.count[axis] as f64;
+                let copy = (p[axis] / period).round().clamp(-count, count);
+                q[axis] -= period * copy;
+            }
+        }
+        self.inner.distance(q)
+    }
+
+    fn describe(&self) -> Option<SdfDescription> {
+        Some(SdfDescription::Repeat(RepeatDescription {
+            sdf: Box::new(self.inner.describe()?),
+            period: self.period.into(),
+            count: self.count,
+        }))
+    }
+}
+
+/// A distance function grown by `radius`, rounding its edges.
+pub struct Round {
+    inner: Arc<dyn DistanceFunction>,
+    radius: f64,
+}
+
+impl Round {
+    pub fn new(inner: Arc<dyn DistanceFunction>, radius: f64) -> Round {
+        Round { inner, radius }
+    }
+}
+
+impl DistanceFunction for Round {
+    fn distance(&self, p: Point3f) -> f64 {
+        self.inner.distance(p) - self.radius
+    }
+
+    fn describe(&self) -> Option<SdfDescription> {
+        Some(SdfDescription::Round(RoundDescription {
+            sdf: Box::new(self.inner.describe()?),
+            radius: self.radius,
+        }))
+    }
+}
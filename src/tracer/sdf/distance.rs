@@ -0,0 +1,15 @@
+use crate::tracer::scene_file::SdfDescription;
+use crate::tracer::Point3f;
+
+/// Signed distance from points to a surface, negative inside of it.
+///
+/// Sphere tracing steps along rays by the returned distance, so it must never be more than
+/// the distance to the closest point of the surface. Smaller values only slow tracing down.
+pub trait DistanceFunction: Sync + Send {
+    fn distance(&self, p: Point3f) -> f64;
+
+    /// Scene file description of this function, `None` when it can't be exported.
+    fn describe(&self) -> Option<SdfDescription> {
+        None
+    }
+}
@@ -0,0 +1,57 @@
+use crate::tracer::scene_file::{MandelbulbDescription, SdfDescription};
+use crate::tracer::sdf::DistanceFunction;
+use crate::tracer::Point3f;
+use cgmath::*;
+
+/// The Mandelbulb fractal of the given `power`, fitting in a sphere of radius 1.2 around the
+/// origin for the usual power of 8. More `iterations` bring out finer details.
+pub struct Mandelbulb {
+    power: f64,
+    iterations: u32,
+}
+
+impl Mandelbulb {
+    const BAILOUT: f64 = 2.0;
+
+    pub fn new(power: f64, iterations: u32) -> Mandelbulb {
+        Mandelbulb { power, iterations }
+    }
+}
+
+impl DistanceFunction for Mandelbulb {
+    fn distance(&self, p: Point3f) -> f64 {
+        // Distance estimated from the escape speed of the orbit of p
+        let c = p.to_vec();
+        let mut z = c;
+        let mut derivative = 1.0;
+        let mut r = z.magnitude();
+        for _ in 0..self.iterations {
+            if r > Mandelbulb::BAILOUT || r == 0.0 {
+                break;
+            }
+
+            let theta = (z.z / r).acos() * self.power;
+            let phi = z.y.atan2(z.x) * self.power;
+            derivative = r.powf(self.power - 1.0) * self.power * derivative + 1.0;
+            let radius = r.powf(self.power);
+            z = vec3(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            ) * radius
+                + c;
+            r = z.magnitude();
+        }
+        if r == 0.0 {
+            return 0.0;
+        }
+        0.5 * r.ln() * r / derivative
+    }
+
+    fn describe(&self) -> Option<SdfDescription> {
+        Some(SdfDescription::Mandelbulb(MandelbulbDescription {
+            power: self.power,
+            iterations: self.iterations,
+        }))
+    }
+}
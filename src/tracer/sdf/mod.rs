@@ -0,0 +1,11 @@
+mod combinators;
+mod distance;
+mod mandelbulb;
+mod primitives;
+mod sdf_object;
+
+pub use combinators::*;
+pub use distance::*;
+pub use mandelbulb::*;
+pub use primitives::*;
+pub use sdf_object::*;
@@ -0,0 +1,120 @@
+use crate::tracer::scene_file::{
+    SdfBoxDescription, SdfCapsuleDescription, SdfDescription, SdfSphereDescription,
+    SdfTorusDescription,
+};
+use crate::tracer::sdf::DistanceFunction;
+use crate::tracer::{Point3f, Vector3f};
+use cgmath::*;
+
+/// A sphere of `radius` centered on the origin.
+pub struct SdfSphere {
+    radius: f64,
+}
+
+impl SdfSphere {
+    pub fn new(radius: f64) -> SdfSphere {
+        SdfSphere { radius }
+    }
+}
+
+impl DistanceFunction for SdfSphere {
+    fn distance(&self, p: Point3f) -> f64 {
+        p.to_vec().magnitude() - self.radius
+    }
+
+    fn describe(&self) -> Option<SdfDescription> {
+        Some(SdfDescription::Sphere(SdfSphereDescription {
+            radius: self.radius,
+        }))
+    }
+}
+
+/// A box centered on the origin, extending by `half_size` on both sides of each axis.
+pub struct SdfBox {
+    half_size: Vector3f,
+}
+
+impl SdfBox {
+    pub fn new(half_size: Vector3f) -> SdfBox {
+        SdfBox { half_size }
+    }
+}
+
+impl DistanceFunction for SdfBox {
+    fn distance(&self, p: Point3f) -> f64 {
+        let q = vec3(p.x.abs(), p.y.abs(), p.z.abs()) - self.half_size;
+        let outside = vec3(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).magnitude();
+        let inside = q.x.max(q.y).max(q.z).min(0.0);
+        outside + inside
+    }
+
+    fn describe(&self) -> Option<SdfDescription> {
+        Some(SdfDescription::Box(SdfBoxDescription {
+            half_size: self.half_size.into(),
+        }))
+    }
+}
+
+/// A torus around the y axis, centered on the origin.
+pub struct SdfTorus {
+    major_radius: f64,
+    minor_radius: f64,
+}
+
+impl SdfTorus {
+    pub fn new(major_radius: f64, minor_radius: f64) -> SdfTorus {
+        SdfTorus {
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl DistanceFunction for SdfTorus {
+    fn distance(&self, p: Point3f) -> f64 {
+        let ring = vec2(p.x, p.z).magnitude() - self.major_radius;
+        vec2(ring, p.y).magnitude() - self.minor_radius
+    }
+
+    fn describe(&self) -> Option<SdfDescription> {
+        Some(SdfDescription::Torus(SdfTorusDescription {
+            major_radius: self.major_radius,
+            minor_radius: self.minor_radius,
+        }))
+    }
+}
+
+/// The points within `radius` of the segment from `a` to `b`.
+pub struct SdfCapsule {
+    a: Point3f,
+    b: Point3f,
+    radius: f64,
+}
+
+impl SdfCapsule {
+    pub fn new(a: Point3f, b: Point3f, radius: f64) -> SdfCapsule {
+        SdfCapsule { a, b, radius }
+    }
+}
+
+impl DistanceFunction for SdfCapsule {
+    fn distance(&self, p: Point3f) -> f64 {
+        let pa = p - self.a;
+        let ba = self.b - self.a;
+        let length2 = ba.magnitude2();
+        let h = if length2 > 0.0 {
+            (pa.dot(ba) / length2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (pa - ba * h).magnitude() - self.radius
+    }
+
+    fn describe(&self) -> Option<SdfDescription> {
+        Some(SdfDescription::Capsule(SdfCapsuleDescription {
+            a: self.a.into(),
+            b: self.b.into(),
+            radius: self.radius,
+        }))
+    }
+}
@@ -0,0 +1,162 @@
+use crate::tracer::bounding_volumes::{Boundable, AABB};
+use crate::tracer::material::Material;
+use crate::tracer::scene_file::{MaterialRef, ObjectDescription, SdfObjectDescription};
+use crate::tracer::sdf::DistanceFunction;
+use crate::tracer::{Intersectable, Intersection, Point3f, Ray, SceneObject, Vector3f};
+use cgmath::*;
+use std::sync::Arc;
+
+/// The surface of a signed distance function, found by sphere tracing: rays step forward by
+/// the distance to the surface until they're within `epsilon` of it.
+///
+/// The surface must lie within the declared `bounds`, where tracing starts and stops. Rays
+/// needing more than `max_steps` steps, grazing the surface, miss it.
+pub struct SdfObject {
+    distance: Arc<dyn DistanceFunction>,
+    bounds: AABB,
+    material: Arc<dyn Material>,
+    max_steps: u32,
+    epsilon: f64,
+}
+
+impl SdfObject {
+    pub const DEFAULT_MAX_STEPS: u32 = 256;
+    pub const DEFAULT_EPSILON: f64 = 1e-4;
+
+    pub fn new(
+        distance: Arc<dyn DistanceFunction>,
+        bounds: AABB,
+        material: Arc<dyn Material>,
+    ) -> SdfObject {
+        SdfObject {
+            distance,
+            bounds,
+            material,
+            max_steps: SdfObject::DEFAULT_MAX_STEPS,
+            epsilon: SdfObject::DEFAULT_EPSILON,
+        }
+    }
+
+    pub fn with_max_steps(self, max_steps: u32) -> SdfObject {
+        SdfObject { max_steps, ..self }
+    }
+
+    pub fn with_epsilon(self, epsilon: f64) -> SdfObject {
+        SdfObject { epsilon, ..self }
+    }
+
+    /// Gradient of the distance at `p` by central differences.
+    fn normal(&self, p: Point3f) -> Vector3f {
+        let h = self.epsilon;
+        let difference = |offset: Vector3f| {
+            self.distance.distance(p + offset) - self.distance.distance(p - offset)
+        };
+        let gradient = vec3(
+            difference(Vector3f::unit_x() * h),
+            difference(Vector3f::unit_y() * h),
+            difference(Vector3f::unit_z() * h),
+        );
+        if gradient.magnitude2() == 0.0 {
+            return Vector3f::unit_y();
+        }
+        gradient.normalize()
+    }
+}
+
+impl Intersectable for SdfObject {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        let (start, end) = self.bounds.clip_ray(ray, dist_min, dist_max)?;
+        let length = ray.direction.magnitude();
+
+        // Rays leaving the surface from the inside step by the opposite distance
+        let side = self.distance.distance(ray.point_at(start)).signum();
+        let mut dist = start;
+        for _ in 0..self.max_steps {
+            let point = ray.point_at(dist);
+            let distance = side * self.distance.distance(point);
+            if distance < self.epsilon {
+                return Some(Intersection {
+                    dist,
+                    point,
+                    normal: self.normal(point),
+                    // Distance functions have no natural parametrization
+                    uv: (0.0, 0.0),
+                    color: None,
+                    tangents: None,
+                });
+            }
+
+            dist += distance / length;
+            if dist > end {
+                return None;
+            }
+        }
+
+        None
+    }
+}
+
+impl Boundable for SdfObject {
+    fn get_bounds(&self) -> AABB {
+        self.bounds
+    }
+}
+
+impl SceneObject for SdfObject {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        Some(ObjectDescription::Sdf(SdfObjectDescription {
+            sdf: self.distance.describe()?,
+            bounds: [self.bounds.min.into(), self.bounds.max.into()],
+            material: MaterialRef::Inline(Box::new(self.material.describe()?)),
+            max_steps: self.max_steps,
+            epsilon: self.epsilon,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::material::Lambertian;
+    use crate::tracer::sdf::{SdfBox, SdfSphere, SmoothUnion, Translate};
+    use crate::tracer::Color;
+
+    #[test]
+    fn test_sphere_tracing() {
+        let material = Arc::new(Lambertian::from_constant(Color::white()));
+        let bounds = AABB::new(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0));
+        let sphere = SdfObject::new(Arc::new(SdfSphere::new(1.0)), bounds, material.clone());
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3f::unit_z() * 2.0);
+        let hit = sphere.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 2.0).abs() < 1e-4);
+        assert!((hit.normal - vec3(0.0, 0.0, -1.0)).magnitude() < 1e-6);
+
+        // From the inside, the ray leaves through the far side
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3f::unit_x());
+        let hit = sphere.intersects(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.dist - 1.0).abs() < 1e-4);
+        assert!(sphere.intersects(&ray, 0.001, 0.5).is_none());
+
+        let ray = Ray::new(Point3::new(0.0, 1.5, -5.0), Vector3f::unit_z());
+        assert!(sphere.intersects(&ray, 0.001, f64::MAX).is_none());
+
+        // Blending a box next to the sphere fills the gap between them
+        let blend = SmoothUnion::new(
+            Arc::new(SdfSphere::new(1.0)),
+            Arc::new(Translate::new(
+                Arc::new(SdfBox::new(vec3(0.5, 0.5, 0.5))),
+                vec3(2.0, 0.0, 0.0),
+            )),
+            1.5,
+        );
+        let bounds = AABB::new(vec3(-1.0, -1.0, -1.0), vec3(2.5, 1.0, 1.0));
+        let blend = SdfObject::new(Arc::new(blend), bounds, material);
+        let ray = Ray::new(Point3::new(1.25, 0.0, -5.0), Vector3f::unit_z());
+        assert!(blend.intersects(&ray, 0.001, f64::MAX).is_some());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::tracer::bounding_volumes::AABB;
+use crate::tracer::material::noises::PerlinNoise;
+use crate::tracer::scene_file::{DensityDescription, NoiseDensityDescription};
+use crate::tracer::Point3f;
+use cgmath::*;
+
+/// Density of a heterogeneous medium varying in space.
+pub trait DensityField: Sync + Send {
+    fn density(&self, p: Point3f) -> f64;
+
+    /// Upper bound of the density over the whole field.
+    fn max_density(&self) -> f64;
+
+    /// Upper bound of the density inside `bounds`, the majorant of the tracking through that
+    /// part of the field. The closer to the actual maximum, the fewer lookups tracking takes.
+    /// Defaults to the bound over the whole field.
+    fn max_density_in(&self, _bounds: &AABB) -> f64 {
+        self.max_density()
+    }
+
+    /// Scene file description of this field, `None` when it can't be exported.
+    fn describe(&self) -> Option<DensityDescription> {
+        None
+    }
+}
+
+/// Cloudy density from Perlin noise. Noise values under `cutoff`, between 0 and 1, leave
+/// empty space between the clouds.
+///
+/// The noise isn't bounded any tighter over parts of the field, so tracking steps through
+/// clouds as through their densest parts.
+pub struct NoiseDensity {
+    noise: PerlinNoise,
+    scale: f64,
+    density: f64,
+    cutoff: f64,
+}
+
+impl NoiseDensity {
+    /// # Panics
+    ///
+    /// If `cutoff` isn't between 0 included and 1 excluded.
+    pub fn new(noise: PerlinNoise, scale: f64, density: f64, cutoff: f64) -> NoiseDensity {
+        assert!(
+            (0.0..1.0).contains(&cutoff),
+            "noise density cutoffs must be between 0 included and 1 excluded"
+        );
+        NoiseDensity {
+            noise,
+            scale,
+            density,
+            cutoff,
+        }
+    }
+}
+
+impl DensityField for NoiseDensity {
+    fn density(&self, p: Point3f) -> f64 {
+        let value = 0.5 * (1.0 + self.noise.noise(p.to_vec() * self.scale));
+        self.density * ((value - self.cutoff) / (1.0 - self.cutoff)).clamp(0.0, 1.0)
+    }
+
+    fn max_density(&self) -> f64 {
+        self.density
+    }
+
+    fn describe(&self) -> Option<DensityDescription> {
+        Some(DensityDescription::Noise(NoiseDensityDescription {
+            density: self.density,
+            scale: self.scale,
+            cutoff: self.cutoff,
+            octaves: self.noise.octaves(),
+            frequency: self.noise.frequency(),
+            persistence: self.noise.persistence(),
+            lacunarity: self.noise.lacunarity(),
+        }))
+    }
+}
@@ -0,0 +1,266 @@
+use crate::tracer::bounding_volumes::{BVHNode, Boundable, AABB};
+use crate::tracer::material::{Material, ScatteredRay, Texture};
+use crate::tracer::scene_file::{HeterogeneousMediumDescription, MaterialRef, ObjectDescription};
+use crate::tracer::volume::constant_medium::{boundary_span, scatter_rng};
+use crate::tracer::volume::majorant_grid::MajorantGrid;
+use crate::tracer::volume::DensityField;
+use crate::tracer::{Intersectable, Intersection, Point3f, Ray, SceneObject, Vector3f};
+use cgmath::*;
+use rand::Rng;
+use std::sync::Arc;
+
+/// A participating medium filling a convex `boundary` object with a density varying in space,
+/// such as clouds.
+///
+/// Scattering distances are sampled by delta tracking against the maximum density of each
+/// cell of a coarse grid over the boundary, so empty space is crossed in a single step. Media
+/// with an `emission` texture glow at every point where rays scatter.
+pub struct HeterogeneousMedium {
+    boundary: Arc<dyn SceneObject>,
+    density: Arc<dyn DensityField>,
+    majorants: MajorantGrid,
+    phase: Arc<dyn Material>,
+    emission: Option<Arc<dyn Texture>>,
+    // The phase function along with the emission
+    material: Arc<dyn Material>,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(
+        boundary: Arc<dyn SceneObject>,
+        density: Arc<dyn DensityField>,
+        phase: Arc<dyn Material>,
+    ) -> HeterogeneousMedium {
+        HeterogeneousMedium {
+            majorants: MajorantGrid::new(&*density, boundary.get_bounds()),
+            boundary,
+            density,
+            material: phase.clone(),
+            phase,
+            emission: None,
+        }
+    }
+
+    pub fn with_emission(self, emission: Arc<dyn Texture>) -> HeterogeneousMedium {
+        HeterogeneousMedium {
+            material: Arc::new(EmissivePhase {
+                phase: self.phase.clone(),
+                emission: emission.clone(),
+            }),
+            emission: Some(emission),
+            ..self
+        }
+    }
+}
+
+impl Intersectable for HeterogeneousMedium {
+    fn intersects(&self, ray: &Ray, dist_min: f64, dist_max: f64) -> Option<Intersection> {
+        let (entry_dist, exit_dist) = boundary_span(&*self.boundary, ray, dist_min, dist_max)?;
+        let mut rng = scatter_rng(ray, entry_dist);
+        let length = ray.direction.magnitude();
+
+        // Delta tracking: sample collisions against the majorant of each cell, and keep each
+        // of them as a real scattering event with the probability density / majorant. The
+        // optical depth left to the next collision carries over from cell to cell.
+        let mut depth = -(1.0 - rng.gen::<f64>()).ln();
+        self.majorants
+            .march(ray, entry_dist, exit_dist, |start, end, majorant| {
+                if majorant <= 0.0 {
+                    return None;
+                }
+                let rate = majorant * length;
+                let mut dist = start;
+                loop {
+                    if dist + depth / rate >= end {
+                        depth -= (end - dist) * rate;
+                        return None;
+                    }
+                    dist += depth / rate;
+                    depth = -(1.0 - rng.gen::<f64>()).ln();
+
+                    let point = ray.point_at(dist);
+                    if rng.gen::<f64>() * majorant < self.density.density(point) {
+                        return Some(Intersection {
+                            dist,
+                            point,
+                            // Arbitrary, scattering in media doesn't depend on a surface
+                            normal: vec3(1.0, 0.0, 0.0),
+                            uv: (0.0, 0.0),
+                            color: None,
+                            tangents: None,
+                        });
+                    }
+                }
+            })
+    }
+}
+
+impl Boundable for HeterogeneousMedium {
+    fn get_bounds(&self) -> AABB {
+        self.boundary.get_bounds()
+    }
+}
+
+impl SceneObject for HeterogeneousMedium {
+    fn get_material(&self, _point: Point3f) -> Box<Arc<dyn Material>> {
+        Box::new(self.material.clone())
+    }
+
+    fn primitives(&self) -> u64 {
+        self.boundary.primitives()
+    }
+
+    fn bvhs(&self) -> Vec<&BVHNode> {
+        self.boundary.bvhs()
+    }
+
+    fn describe(&self) -> Option<ObjectDescription> {
+        let emission = match &self.emission {
+            Some(emission) => Some(emission.describe()?.into()),
+            None => None,
+        };
+        Some(ObjectDescription::HeterogeneousMedium(
+            HeterogeneousMediumDescription {
+                boundary: Box::new(self.boundary.describe()?),
+                density: self.density.describe()?,
+                phase: MaterialRef::Inline(Box::new(self.phase.describe()?)),
+                emission,
+            },
+        ))
+    }
+}
+
+/// A phase function emitting light where rays scatter.
+struct EmissivePhase {
+    phase: Arc<dyn Material>,
+    emission: Arc<dyn Texture>,
+}
+
+impl Material for EmissivePhase {
+    fn scatter(&self, ray_in: &Ray, hit: &Intersection) -> Option<ScatteredRay> {
+        self.phase.scatter(ray_in, hit)
+    }
+
+    fn emitted(&self, _ray_in: &Ray, u: f64, v: f64, p: Point3f) -> Vector3f {
+        self.emission.texture_value(u, v, p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::geometry::Cuboid;
+    use crate::tracer::material::Isotropic;
+    use crate::tracer::volume::VoxelGrid;
+    use crate::tracer::{random, seed_rng, Color};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A density field counting its lookups.
+    struct CountedDensity {
+        density: VoxelGrid,
+        lookups: AtomicUsize,
+    }
+
+    impl DensityField for CountedDensity {
+        fn density(&self, p: Point3f) -> f64 {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            self.density.density(p)
+        }
+
+        fn max_density(&self) -> f64 {
+            self.density.max_density()
+        }
+
+        fn max_density_in(&self, bounds: &AABB) -> f64 {
+            self.density.max_density_in(bounds)
+        }
+    }
+
+    #[test]
+    fn test_tracking() {
+        seed_rng(3);
+        // Density 1 in the x < 0 half of the box, 0 in the other
+        let bounds = AABB::new(vec3(-1.0, -1.0, -1.0), vec3(1.0, 1.0, 1.0));
+        let density = Arc::new(CountedDensity {
+            density: VoxelGrid::new([2, 1, 1], vec![1.0, 0.0], bounds),
+            lookups: AtomicUsize::new(0),
+        });
+        let phase = Arc::new(Isotropic::from_constant(Color::white()));
+        let boundary = Arc::new(Cuboid::new(
+            Point3::new(-1.0, -1.0, -1.0),
+            Point3::new(1.0, 1.0, 1.0),
+            phase.clone(),
+        ));
+        let medium = HeterogeneousMedium::new(boundary, density.clone(), phase);
+
+        // Constant density along y, in the dense half
+        let expected = (-2.0f64).exp();
+        let samples = 20_000;
+        let mut through = 0;
+        for _ in 0..samples {
+            let origin = Point3::new(-0.9, -5.0 - random::<f64>(), 0.0);
+            let ray = Ray::new(origin, Vector3f::unit_y());
+            match medium.intersects(&ray, 0.001, f64::MAX) {
+                Some(hit) => assert!(hit.point.y >= -1.0 && hit.point.y <= 1.0),
+                None => through += 1,
+            }
+        }
+        assert!((through as f64 / samples as f64 - expected).abs() < 0.02);
+
+        // Across both halves, only the dense one scatters
+        let mut through = 0;
+        for _ in 0..samples {
+            let origin = Point3::new(-5.0 - random::<f64>(), 0.0, 0.0);
+            let ray = Ray::new(origin, Vector3f::unit_x());
+            match medium.intersects(&ray, 0.001, f64::MAX) {
+                Some(hit) => assert!(hit.point.x >= -1.0 && hit.point.x <= 0.5),
+                None => through += 1,
+            }
+        }
+        assert!((through as f64 / samples as f64 - (-1.0f64).exp()).abs() < 0.03);
+
+        // The empty half is crossed without looking the density up
+        density.lookups.store(0, Ordering::Relaxed);
+        for _ in 0..100 {
+            let origin = Point3::new(0.9, -5.0 - random::<f64>(), 0.0);
+            let ray = Ray::new(origin, Vector3f::unit_y());
+            assert!(medium.intersects(&ray, 0.001, f64::MAX).is_none());
+        }
+        assert_eq!(density.lookups.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_majorants() {
+        seed_rng(5);
+        let bounds = AABB::new(vec3(-1.0, -2.0, 0.0), vec3(1.0, 2.0, 3.0));
+        let values = (0..5 * 3 * 4).map(|_| random::<f32>()).collect();
+        let grid = VoxelGrid::new([5, 3, 4], values, bounds);
+        let majorants = MajorantGrid::new(&grid, bounds);
+
+        // Every density along random rays is under the majorant of its cell, and the cell
+        // segments follow each other without gaps
+        for _ in 0..1000 {
+            let origin = Point3::new(-3.0, -3.0, -3.0) + vec3(random(), random(), random()) * 6.0;
+            let ray = Ray::new(
+                origin,
+                vec3(random(), random(), random()) * 2.0 - vec3(1.0, 1.0, 1.0),
+            );
+            let (start, end) = match bounds.clip_ray(&ray, 0.0, f64::MAX) {
+                Some(span) => span,
+                None => continue,
+            };
+
+            let mut reached = start;
+            majorants.march(&ray, start, end, |cell_start, cell_end, majorant| {
+                assert!((cell_start - reached).abs() < 1e-9);
+                reached = cell_end;
+                for step in 0..=10 {
+                    let dist = cell_start + (cell_end - cell_start) * step as f64 / 10.0;
+                    assert!(grid.density(ray.point_at(dist)) <= majorant + 1e-6);
+                }
+                None::<()>
+            });
+            assert!((reached - end).abs() < 1e-9);
+        }
+    }
+}
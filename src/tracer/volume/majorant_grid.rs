@@ -0,0 +1,132 @@
+use crate::tracer::bounding_volumes::AABB;
+use crate::tracer::volume::DensityField;
+use crate::tracer::Ray;
+use cgmath::*;
+
+/// Cells along each axis of a majorant grid.
+const RESOLUTION: usize = 16;
+
+/// Upper bounds of a density field over the cells of a coarse grid stretched over a medium,
+/// so that tracking takes short steps only through the dense parts and crosses empty space in
+/// one step.
+pub(super) struct MajorantGrid {
+    bounds: AABB,
+    resolution: [usize; 3],
+    // x varies fastest, then y, then z
+    majorants: Vec<f64>,
+}
+
+impl MajorantGrid {
+    pub fn new(density: &dyn DensityField, bounds: AABB) -> MajorantGrid {
+        // Unbounded media get a single cell of the bound over the whole field
+        if !bounds.is_finite() {
+            return MajorantGrid {
+                bounds,
+                resolution: [1; 3],
+                majorants: vec![density.max_density()],
+            };
+        }
+
+        // Flat bounds are a single cell thick
+        let extent = bounds.max - bounds.min;
+        let resolution =
+            [extent.x, extent.y, extent.z].map(|size| if size > 0.0 { RESOLUTION } else { 1 });
+        let size = vec3(
+            extent.x / resolution[0] as f64,
+            extent.y / resolution[1] as f64,
+            extent.z / resolution[2] as f64,
+        );
+
+        let mut majorants = Vec::with_capacity(resolution.iter().product());
+        for z in 0..resolution[2] {
+            for y in 0..resolution[1] {
+                for x in 0..resolution[0] {
+                    let corner = vec3(x as f64, y as f64, z as f64);
+                    let min = bounds.min + size.mul_element_wise(corner);
+                    let cell = AABB::new(min, min + size);
+                    majorants.push(density.max_density_in(&cell));
+                }
+            }
+        }
+
+        MajorantGrid {
+            bounds,
+            resolution,
+            majorants,
+        }
+    }
+
+    /// Calls `visit` with the start and end distances of the segments of `ray` crossing each
+    /// cell between `dist_min` and `dist_max`, and the majorant of the cell, in order along the
+    /// ray until it returns a value.
+    pub fn march<T>(
+        &self,
+        ray: &Ray,
+        dist_min: f64,
+        dist_max: f64,
+        mut visit: impl FnMut(f64, f64, f64) -> Option<T>,
+    ) -> Option<T> {
+        if self.majorants.len() == 1 {
+            return visit(dist_min, dist_max, self.majorants[0]);
+        }
+        let (start, end) = self.bounds.clip_ray(ray, dist_min, dist_max)?;
+
+        // Walk the cells as in Amanatides and Woo, "A Fast Voxel Traversal Algorithm for Ray
+        // Tracing", tracking the distance of the next cell boundary along each axis
+        let origin = ray.point_at(start);
+        let mut cell = [0; 3];
+        let mut step = [0; 3];
+        let mut next = [f64::INFINITY; 3];
+        let mut delta = [f64::INFINITY; 3];
+        for axis in 0..3 {
+            let last = self.resolution[axis] - 1;
+            let size =
+                (self.bounds.max[axis] - self.bounds.min[axis]) / self.resolution[axis] as f64;
+            if size <= 0.0 {
+                continue;
+            }
+            let relative = (origin[axis] - self.bounds.min[axis]) / size;
+            cell[axis] = (relative.max(0.0) as usize).min(last);
+
+            let direction = ray.direction[axis];
+            if direction != 0.0 {
+                let boundary = if direction > 0.0 {
+                    step[axis] = 1;
+                    cell[axis] + 1
+                } else {
+                    step[axis] = -1;
+                    cell[axis]
+                };
+                let boundary = self.bounds.min[axis] + boundary as f64 * size;
+                next[axis] = (boundary - ray.origin[axis]) / direction;
+                delta[axis] = size / direction.abs();
+            }
+        }
+
+        let [res_x, res_y, _] = self.resolution;
+        let mut dist = start;
+        loop {
+            let axis = (0..3)
+                .min_by(|&a, &b| next[a].partial_cmp(&next[b]).unwrap())
+                .unwrap();
+            let cell_end = next[axis].min(end);
+            if cell_end > dist {
+                let majorant = self.majorants[(cell[2] * res_y + cell[1]) * res_x + cell[0]];
+                if let Some(value) = visit(dist, cell_end, majorant) {
+                    return Some(value);
+                }
+                dist = cell_end;
+            }
+            if next[axis] >= end {
+                return None;
+            }
+
+            let moved = cell[axis] as isize + step[axis];
+            if moved < 0 || moved as usize >= self.resolution[axis] {
+                return None;
+            }
+            cell[axis] = moved as usize;
+            next[axis] += delta[axis];
+        }
+    }
+}
@@ -0,0 +1,10 @@
+mod constant_medium;
+mod density;
+mod heterogeneous_medium;
+mod majorant_grid;
+mod voxel_grid;
+
+pub use constant_medium::*;
+pub use density::*;
+pub use heterogeneous_medium::*;
+pub use voxel_grid::*;
@@ -0,0 +1,173 @@
+use crate::tracer::bounding_volumes::AABB;
+use crate::tracer::scene_file::{DensityDescription, VolumeFormat, VoxelGridDescription};
+use crate::tracer::volume::DensityField;
+use crate::tracer::Point3f;
+use cgmath::*;
+use std::path::PathBuf;
+
+/// Densities sampled on a regular grid stretched over `bounds`, interpolated trilinearly
+/// between voxel centers. The density is zero outside of the grid.
+pub struct VoxelGrid {
+    resolution: [usize; 3],
+    // x varies fastest, then y, then z
+    values: Vec<f32>,
+    bounds: AABB,
+    scale: f64,
+    max_value: f64,
+
+    // File the grid was loaded from, kept to describe it
+    source: Option<(PathBuf, VolumeFormat)>,
+}
+
+impl VoxelGrid {
+    /// # Panics
+    ///
+    /// If `values` doesn't hold one value per voxel of the `resolution`.
+    pub fn new(resolution: [usize; 3], values: Vec<f32>, bounds: AABB) -> VoxelGrid {
+        assert_eq!(
+            values.len(),
+            resolution[0] * resolution[1] * resolution[2],
+            "voxel grids need one value per voxel"
+        );
+        let max_value = values
+            .iter()
+            .fold(0.0f64, |max, &value| max.max(value as f64));
+        VoxelGrid {
+            resolution,
+            values,
+            bounds,
+            scale: 1.0,
+            max_value,
+            source: None,
+        }
+    }
+
+    /// Multiplies the grid values by `scale`.
+    pub fn with_scale(self, scale: f64) -> VoxelGrid {
+        VoxelGrid { scale, ..self }
+    }
+
+    /// Stretches the grid over `bounds` instead.
+    pub fn with_bounds(self, bounds: AABB) -> VoxelGrid {
+        VoxelGrid { bounds, ..self }
+    }
+
+    pub(crate) fn with_source(self, path: PathBuf, format: VolumeFormat) -> VoxelGrid {
+        VoxelGrid {
+            source: Some((path, format)),
+            ..self
+        }
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    pub fn bounds(&self) -> AABB {
+        self.bounds
+    }
+
+    fn value(&self, x: usize, y: usize, z: usize) -> f64 {
+        let [nx, ny, _] = self.resolution;
+        self.values[(z * ny + y) * nx + x] as f64
+    }
+}
+
+impl DensityField for VoxelGrid {
+    fn density(&self, p: Point3f) -> f64 {
+        let p = p.to_vec();
+        if !self.bounds.contains_point(p) {
+            return 0.0;
+        }
+
+        // Interpolate between the 8 voxel centers around p, clamping to the grid border
+        let mut lower = [0; 3];
+        let mut upper = [0; 3];
+        let mut weight = [0.0; 3];
+        for axis in 0..3 {
+            let size = self.bounds.max[axis] - self.bounds.min[axis];
+            let last = self.resolution[axis] - 1;
+            let relative = if size > 0.0 {
+                (p[axis] - self.bounds.min[axis]) / size
+            } else {
+                0.0
+            };
+            let position = (relative * self.resolution[axis] as f64 - 0.5).max(0.0);
+            lower[axis] = (position as usize).min(last);
+            upper[axis] = (lower[axis] + 1).min(last);
+            weight[axis] = (position - lower[axis] as f64).min(1.0);
+        }
+
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let pick = |axis: usize| corner & (1 << axis) != 0;
+            let mut corner_weight = 1.0;
+            let mut index = [0; 3];
+            for axis in 0..3 {
+                if pick(axis) {
+                    index[axis] = upper[axis];
+                    corner_weight *= weight[axis];
+                } else {
+                    index[axis] = lower[axis];
+                    corner_weight *= 1.0 - weight[axis];
+                }
+            }
+            if corner_weight > 0.0 {
+                density += corner_weight * self.value(index[0], index[1], index[2]);
+            }
+        }
+        density * self.scale
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max_value * self.scale
+    }
+
+    fn max_density_in(&self, bounds: &AABB) -> f64 {
+        // Points of `bounds` interpolate between the voxels whose centers surround them
+        let mut lower = [0; 3];
+        let mut upper = [0; 3];
+        for axis in 0..3 {
+            if bounds.max[axis] < self.bounds.min[axis] || bounds.min[axis] > self.bounds.max[axis]
+            {
+                return 0.0;
+            }
+            let size = self.bounds.max[axis] - self.bounds.min[axis];
+            let last = self.resolution[axis] - 1;
+            let position = |x: f64| {
+                if size > 0.0 {
+                    let relative = (x - self.bounds.min[axis]) / size;
+                    (relative * self.resolution[axis] as f64 - 0.5).max(0.0)
+                } else {
+                    0.0
+                }
+            };
+            lower[axis] = (position(bounds.min[axis]) as usize).min(last);
+            upper[axis] = (position(bounds.max[axis]) as usize + 1).min(last);
+        }
+
+        let mut max = 0.0f64;
+        for z in lower[2]..=upper[2] {
+            for y in lower[1]..=upper[1] {
+                for x in lower[0]..=upper[0] {
+                    max = max.max(self.value(x, y, z));
+                }
+            }
+        }
+        max * self.scale
+    }
+
+    fn describe(&self) -> Option<DensityDescription> {
+        let (path, format) = self.source.clone()?;
+        Some(DensityDescription::VoxelGrid(VoxelGridDescription {
+            resolution: match format {
+                VolumeFormat::Vol => None,
+                _ => Some(self.resolution),
+            },
+            path,
+            format,
+            bounds: Some([self.bounds.min.into(), self.bounds.max.into()]),
+            scale: self.scale,
+        }))
+    }
+}